@@ -0,0 +1,156 @@
+//what happens to threshold events: quiet hours hold back the ordinary ones at night, and a threshold that
+//stays crossed escalates every --escalate-after until its price is back past the reset level
+use std::time::Duration;
+
+use crate::thresholds::Threshold;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    //armed, waiting for the trigger
+    Idle,
+    //unix seconds of the event and of the last escalation
+    Firing { since: u64, last: u64, escalations: u32 },
+}
+
+#[derive(Debug)]
+struct Alert {
+    threshold: Threshold,
+    state: State,
+    //the event line, repeated in escalations
+    event: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Notice {
+    pub text: String,
+    //escalations, sent even during quiet hours
+    pub critical: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Alerts {
+    alerts: Vec<Alert>,
+    //minutes of the utc day, start inclusive, end exclusive, may wrap midnight
+    pub quiet: Option<(u32, u32)>,
+    pub escalate_after: Option<Duration>,
+}
+
+impl Alerts {
+    pub fn add(&mut self, threshold: Threshold) {
+        self.alerts.push(Alert { threshold, state: State::Idle, event: String::new() });
+    }
+
+    //notices for a fresh price of the asset
+    pub fn observe(&mut self, asset: &str, price: f64, now: u64) -> Vec<Notice> {
+        let mut notices = Vec::new();
+        for alert in &mut self.alerts {
+            if let Some(event) = alert.threshold.observe(asset, price) {
+                alert.state = State::Firing { since: now, last: now, escalations: 0 };
+                notices.push(Notice { text: event.clone(), critical: false });
+                alert.event = event;
+                continue;
+            }
+            let State::Firing { since, last, escalations } = alert.state else { continue };
+            if alert.threshold.asset != asset {
+                continue;
+            }
+            if !alert.threshold.crossed() {
+                alert.state = State::Idle;
+                continue;
+            }
+            let Some(after) = self.escalate_after else { continue };
+            if now.saturating_sub(last) >= after.as_secs() {
+                let escalations = escalations + 1;
+                alert.state = State::Firing { since, last: now, escalations };
+                let text = format!(
+                    "{} still crossed after {} at {} (escalation {})",
+                    alert.event,
+                    sitewatch::duration::format(Duration::from_secs(now - since)),
+                    price,
+                    escalations
+                );
+                notices.push(Notice { text, critical: true });
+            }
+        }
+        notices
+    }
+
+    //whether ordinary notices are held back at this unix time
+    pub fn is_quiet(&self, now: u64) -> bool {
+        let Some((start, end)) = self.quiet else { return false };
+        let minute = (now % 86_400 / 60) as u32;
+        if start <= end { minute >= start && minute < end } else { minute >= start || minute < end }
+    }
+}
+
+//"22:00-07:00" or "22-7", utc
+pub fn parse_quiet_hours(spec: &str) -> Result<(u32, u32), String> {
+    let (start, end) = spec.split_once('-').ok_or("expected HH:MM-HH:MM")?;
+    let start = parse_clock(start)?;
+    let end = parse_clock(end)?;
+    if start == end {
+        return Err("quiet hours start and end at the same time".to_string());
+    }
+    Ok((start, end))
+}
+
+fn parse_clock(s: &str) -> Result<u32, String> {
+    let s = s.trim();
+    let (h, m) = s.split_once(':').unwrap_or((s, "0"));
+    match (h.parse::<u32>(), m.parse::<u32>()) {
+        (Ok(h), Ok(m)) if h < 24 && m < 60 => Ok(h * 60 + m),
+        _ => Err(format!("invalid time {}", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thresholds::Direction;
+
+    #[test]
+    fn test_escalation() {
+        let mut a = Alerts { escalate_after: Some(Duration::from_secs(600)), ..Alerts::default() };
+        a.add(Threshold::parse(Direction::Above, "bitcoin=100,90").unwrap());
+        let texts = |n: Vec<Notice>| n.into_iter().map(|n| (n.text, n.critical)).collect::<Vec<_>>();
+        assert_eq!(texts(a.observe("bitcoin", 101.0, 1000)), [("bitcoin above 100 at 101 (resets at 90)".to_string(), false)]);
+        //still crossed, but not for long enough yet
+        assert!(a.observe("bitcoin", 102.0, 1599).is_empty());
+        assert!(a.observe("ethereum", 5.0, 1700).is_empty());
+        assert_eq!(
+            texts(a.observe("bitcoin", 102.0, 1600)),
+            [("bitcoin above 100 at 101 (resets at 90) still crossed after 10m at 102 (escalation 1)".to_string(), true)]
+        );
+        //every escalate_after from the last one
+        assert!(a.observe("bitcoin", 103.0, 2100).is_empty());
+        assert!(texts(a.observe("bitcoin", 103.0, 2200))[0].0.ends_with("after 20m at 103 (escalation 2)"));
+        //back past reset: idle, nothing more until the next crossing
+        assert!(a.observe("bitcoin", 89.0, 2300).is_empty());
+        assert!(a.observe("bitcoin", 95.0, 5000).is_empty());
+        let again = a.observe("bitcoin", 100.0, 5100);
+        assert_eq!((again.len(), again[0].critical), (1, false));
+        assert!(a.observe("bitcoin", 100.0, 5699).is_empty());
+
+        //no escalate_after, no escalations
+        let mut a = Alerts::default();
+        a.add(Threshold::parse(Direction::Below, "sp500=4000,4100").unwrap());
+        assert_eq!(a.observe("sp500", 3900.0, 0).len(), 1);
+        assert!(a.observe("sp500", 3800.0, 86_400).is_empty());
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let at = |h: u64, m: u64| 19_000 * 86_400 + h * 3600 + m * 60;
+        let night = Alerts { quiet: Some(parse_quiet_hours("22:00-07:00").unwrap()), ..Alerts::default() };
+        assert!(night.is_quiet(at(22, 0)) && night.is_quiet(at(23, 59)) && night.is_quiet(at(0, 0)) && night.is_quiet(at(6, 59)));
+        assert!(!night.is_quiet(at(7, 0)) && !night.is_quiet(at(12, 0)) && !night.is_quiet(at(21, 59)));
+        let lunch = Alerts { quiet: Some(parse_quiet_hours("12-13:30").unwrap()), ..Alerts::default() };
+        assert!(lunch.is_quiet(at(13, 29)) && !lunch.is_quiet(at(13, 30)) && !lunch.is_quiet(at(11, 59)));
+        assert!(!Alerts::default().is_quiet(at(3, 0)));
+
+        assert_eq!(parse_quiet_hours("7-7").unwrap_err(), "quiet hours start and end at the same time");
+        assert_eq!(parse_quiet_hours("22:00-24:00").unwrap_err(), "invalid time 24:00");
+        assert!(parse_quiet_hours("22:60-07:00").is_err());
+        assert!(parse_quiet_hours("22:00").is_err());
+    }
+}
@@ -1,11 +1,37 @@
 //imports
+mod alerts;
+mod sinks;
+mod stats;
+mod thresholds;
+
+use alerts::Alerts;
 use serde::Deserialize;
-use std::{fs::OpenOptions, io::Write, thread, time::Duration};
+use sinks::{FanOut, FileSink, Sample, Sink, SqliteSink, WebhookSink};
+use stats::History;
+use thresholds::{Direction, Threshold};
+use sitewatch::duration;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, fs, process, thread};
 
 //defined price
 trait Pricing {
+    //api the price comes from, assets of one api share its quota
+    fn source(&self) -> &'static str;
+    //key in the last-known-good cache
+    fn name(&self) -> &'static str;
+    //ticker as the source knows it
+    fn symbol(&self) -> &'static str;
+    //every api here is asked for usd, quoted to the cent
+    fn currency(&self) -> &'static str {
+        "USD"
+    }
+    fn decimals(&self) -> u8 {
+        2
+    }
     fn fetch_price(&self) -> Option<f64>;
-    fn save_to_file(&self, price: f64);
+    //price file the file sink appends to
+    fn file(&self) -> &'static str;
 }
 
 //define structs
@@ -59,6 +85,18 @@ struct Meta {
 
 //implementations for assets
 impl Pricing for Bitcoin {
+    fn source(&self) -> &'static str {
+        "coingecko"
+    }
+
+    fn name(&self) -> &'static str {
+        "bitcoin"
+    }
+
+    fn symbol(&self) -> &'static str {
+        "BTC"
+    }
+
     fn fetch_price(&self) -> Option<f64> {
         //bitcoin price
         let url = "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies=usd";
@@ -77,18 +115,24 @@ impl Pricing for Bitcoin {
         }
     }
 
-    fn save_to_file(&self, price: f64) {
-        //writing price to file
-        let mut file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open("bitcoin_prices.txt")
-            .expect("Unable to open file");
-        writeln!(file, "{}", price).unwrap();
+    fn file(&self) -> &'static str {
+        "bitcoin_prices.txt"
     }
 }
 
 impl Pricing for Ethereum {
+    fn source(&self) -> &'static str {
+        "coingecko"
+    }
+
+    fn name(&self) -> &'static str {
+        "ethereum"
+    }
+
+    fn symbol(&self) -> &'static str {
+        "ETH"
+    }
+
     fn fetch_price(&self) -> Option<f64> {
         //ethereum price
         let url = "https://api.coingecko.com/api/v3/simple/price?ids=ethereum&vs_currencies=usd";
@@ -107,18 +151,24 @@ impl Pricing for Ethereum {
         }
     }
 
-    fn save_to_file(&self, price: f64) {
-        //write price to file
-        let mut file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open("ethereum_prices.txt")
-            .expect("Unable to open file");
-        writeln!(file, "{}", price).unwrap();
+    fn file(&self) -> &'static str {
+        "ethereum_prices.txt"
     }
 }
 
 impl Pricing for SP500 {
+    fn source(&self) -> &'static str {
+        "yahoo"
+    }
+
+    fn name(&self) -> &'static str {
+        "sp500"
+    }
+
+    fn symbol(&self) -> &'static str {
+        "^GSPC"
+    }
+
     fn fetch_price(&self) -> Option<f64> {
         //get s&p 500 index price
         let url = "https://query2.finance.yahoo.com/v8/finance/chart/%5EGSPC";
@@ -137,19 +187,243 @@ impl Pricing for SP500 {
         }
     }
 
-    fn save_to_file(&self, price: f64) {
-        //write price to file
-        let mut file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open("sp500_prices.txt")
-            .expect("Unable to open file");
-        writeln!(file, "{}", price).unwrap();
+    fn file(&self) -> &'static str {
+        "sp500_prices.txt"
     }
 }
 
+//last successful price per asset, kept on disk across restarts; lines are "name price unix_secs"
+struct LastGood {
+    path: &'static str,
+    prices: HashMap<String, (f64, u64)>,
+}
+
+impl LastGood {
+    fn load(path: &'static str) -> Self {
+        let text = fs::read_to_string(path).unwrap_or_default();
+        let prices = text
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let name = parts.next()?.to_string();
+                let price = parts.next()?.parse().ok()?;
+                let at = parts.next()?.parse().ok()?;
+                Some((name, (price, at)))
+            })
+            .collect();
+        LastGood { path, prices }
+    }
+
+    fn get(&self, name: &str) -> Option<(f64, u64)> {
+        self.prices.get(name).copied()
+    }
+
+    //rewritten whole on every update, it is a handful of lines
+    fn update(&mut self, name: &str, price: f64) {
+        self.prices.insert(name.to_string(), (price, now_secs()));
+        let mut lines: Vec<String> = self.prices.iter().map(|(n, (p, at))| format!("{} {} {}", n, p, at)).collect();
+        lines.sort();
+        if let Err(err) = fs::write(self.path, lines.join("\n") + "\n") {
+            eprintln!("Cache write error: {}", err);
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn sample(asset: &dyn Pricing, price: f64, stale: bool, history: &History) -> Sample {
+    let at = now_secs();
+    Sample {
+        asset: asset.name(),
+        symbol: asset.symbol(),
+        source: asset.source(),
+        currency: asset.currency(),
+        decimals: asset.decimals(),
+        file: asset.file(),
+        price,
+        stale,
+        windows: history.stats(asset.name(), at),
+        at,
+    }
+}
+
+//no fresh price this round: the cached one, flagged stale
+fn use_last_good(asset: &dyn Pricing, cache: &LastGood, history: &History, out: &FanOut) {
+    match cache.get(asset.name()) {
+        Some((price, at)) => {
+            let age = Duration::from_secs(now_secs().saturating_sub(at));
+            println!("Using cached {} price: {} stale=true (fetched {} ago)", asset.name(), price, duration::format(age));
+            out.send(&sample(asset, price, true, history));
+        }
+        None => eprintln!("No cached {} price to fall back on", asset.name()),
+    }
+}
+
+//critical alerts only, one try: the next escalation comes anyway
+fn escalate(url: &str, text: &str) {
+    let sent = ureq::post(url)
+        .timeout(Duration::from_secs(10))
+        .send_json(serde_json::json!({ "level": "critical", "event": text }));
+    if let Err(err) = sent {
+        eprintln!("Escalation webhook error: {}", err);
+    }
+}
+
+//token bucket per api, shared by every asset using it
+struct Bucket {
+    per_min: u32,
+    tokens: f64,
+    last: Instant,
+    //requests sent in the last minute, for the quota line
+    sent: VecDeque<Instant>,
+}
+
+impl Bucket {
+    fn new(per_min: u32, now: Instant) -> Self {
+        Bucket { per_min, tokens: per_min as f64, last: now, sent: VecDeque::new() }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let rate = self.per_min as f64 / 60.0;
+        self.tokens = (self.tokens + now.duration_since(self.last).as_secs_f64() * rate).min(self.per_min as f64);
+        self.last = now;
+        while self.sent.front().is_some_and(|t| now.duration_since(*t) >= Duration::from_secs(60)) {
+            self.sent.pop_front();
+        }
+    }
+
+    //how long until a request may go out, zero when one may now
+    fn wait(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) * 60.0 / self.per_min as f64)
+        }
+    }
+
+    fn take(&mut self, now: Instant) {
+        self.refill(now);
+        self.tokens -= 1.0;
+        self.sent.push_back(now);
+    }
+
+    fn usage(&self) -> String {
+        format!("{}/{} per min", self.sent.len(), self.per_min)
+    }
+}
+
+//flags, durations like 30s, 5m or 1h (bare numbers are seconds)
+struct Settings {
+    pause: Duration,
+    interval: Duration,
+    //longest wait for a free slot before the fetch is skipped this round
+    max_wait: Duration,
+    //requests per minute per api, coingecko's free tier allows 10-30
+    quotas: Vec<(&'static str, u32)>,
+    //extra sinks next to the price files
+    sqlite: Option<String>,
+    webhook: Option<String>,
+    alerts: Alerts,
+    //where escalations are POSTed as well
+    escalate_webhook: Option<String>,
+    //spans for min/max/drawdown, 1h and 24h unless --window is given
+    windows: Vec<Duration>,
+}
+
+//None when usage was asked for
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Settings>, String> {
+    let mut settings = Settings {
+        pause: Duration::from_secs(3),
+        interval: Duration::from_secs(10),
+        max_wait: Duration::from_secs(10),
+        quotas: vec![("coingecko", 10), ("yahoo", 60)],
+        sqlite: None,
+        webhook: None,
+        alerts: Alerts::default(),
+        escalate_webhook: None,
+        windows: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        //taken only once the flag is known to want one
+        let mut value = || args.next().ok_or_else(|| format!("{} requires a value", arg));
+        let d = |value: String| duration::parse(&value, Duration::from_secs(1)).map_err(|e| format!("{}: {}", arg, e));
+        match arg.as_str() {
+            "--help" | "-h" => return Ok(None),
+            //pause between assets
+            "--pause" => settings.pause = d(value()?)?,
+            //wait between rounds
+            "--interval" => settings.interval = d(value()?)?,
+            "--max-wait" => settings.max_wait = d(value()?)?,
+            //SOURCE=N requests per minute
+            "--quota" => {
+                let value = value()?;
+                let (source, n) = value.split_once('=').ok_or("--quota requires SOURCE=N")?;
+                let n: u32 = n.parse().ok().filter(|n| *n > 0).ok_or("invalid --quota value")?;
+                let quota = settings.quotas.iter_mut().find(|(s, _)| *s == source).ok_or_else(|| format!("unknown source {}", source))?;
+                quota.1 = n;
+            }
+            //also write every sample to a sqlite database / POST it as json
+            "--sqlite" => settings.sqlite = Some(value()?),
+            "--webhook" => settings.webhook = Some(value()?),
+            //ASSET=TRIGGER,RESET price alerts
+            "--above" | "--below" => {
+                let direction = if arg == "--above" { Direction::Above } else { Direction::Below };
+                settings.alerts.add(Threshold::parse(direction, &value()?).map_err(|e| format!("{}: {}", arg, e))?);
+            }
+            //HH:MM-HH:MM utc, events only go to events.txt then
+            "--quiet-hours" => settings.alerts.quiet = Some(alerts::parse_quiet_hours(&value()?).map_err(|e| format!("{}: {}", arg, e))?),
+            //repeat an event as critical while its threshold stays crossed
+            "--escalate-after" => settings.alerts.escalate_after = Some(d(value()?)?),
+            "--escalate-webhook" => settings.escalate_webhook = Some(value()?),
+            //repeatable
+            "--window" => {
+                let span = d(value()?)?;
+                if span.is_zero() {
+                    return Err("--window must be longer than 0s".to_string());
+                }
+                settings.windows.push(span);
+            }
+            _ => return Err(format!("unknown flag {}", arg)),
+        }
+    }
+    if settings.windows.is_empty() {
+        settings.windows = vec![Duration::from_secs(3600), Duration::from_secs(86_400)];
+    }
+    Ok(Some(settings))
+}
+
+const USAGE: &str = "usage: data_fetch [--pause DUR] [--interval DUR] [--max-wait DUR] [--quota coingecko|yahoo=N] [--sqlite PATH] [--webhook URL] [--above|--below ASSET=TRIGGER,RESET] [--quiet-hours HH:MM-HH:MM] [--escalate-after DUR] [--escalate-webhook URL] [--window DUR]...";
+
 //program
 fn main() {
+    let mut settings = match parse_args(env::args().skip(1)) {
+        Ok(Some(settings)) => settings,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    let start = Instant::now();
+    let mut buckets: HashMap<&str, Bucket> = settings.quotas.iter().map(|(s, n)| (*s, Bucket::new(*n, start))).collect();
+    let mut cache = LastGood::load("last_good.txt");
+    let mut history = History::new(settings.windows.clone());
+    let mut sinks: Vec<Box<dyn Sink>> = vec![Box::new(FileSink)];
+    if let Some(path) = settings.sqlite.clone() {
+        sinks.push(Box::new(SqliteSink { path }));
+    }
+    if let Some(url) = settings.webhook.clone() {
+        sinks.push(Box::new(WebhookSink { url }));
+    }
+    let out = FanOut::new(sinks);
+
     //lists of assets
     let assets: Vec<Box<dyn Pricing>> = vec![
         Box::new(Bitcoin),
@@ -160,18 +434,158 @@ fn main() {
     //repeat
     loop {
         for asset in &assets {
+            //wait for the api's quota, or skip when that takes too long
+            let bucket = buckets.get_mut(asset.source()).expect("quota for every source");
+            let wait = bucket.wait(Instant::now());
+            if wait > settings.max_wait {
+                println!("Skipped {} fetch: quota used ({}), next slot in {}", asset.source(), bucket.usage(), duration::format(wait));
+                use_last_good(asset.as_ref(), &cache, &history, &out);
+                continue;
+            }
+            if !wait.is_zero() {
+                println!("Waiting {} for {} quota...", duration::format(wait), asset.source());
+                thread::sleep(wait);
+            }
+            bucket.take(Instant::now());
+            println!("{} quota: {}", asset.source(), bucket.usage());
             //fetch and print price
             if let Some(price) = asset.fetch_price() {
                 println!("Fetched price: {}", price);
-                asset.save_to_file(price);
+                history.push(asset.name(), price, now_secs());
+                out.send(&sample(asset.as_ref(), price, false, &history));
+                cache.update(asset.name(), price);
+                //only fresh prices, a stale repeat crosses nothing
+                let now = now_secs();
+                for notice in settings.alerts.observe(asset.name(), price, now) {
+                    thresholds::record("events.txt", now, &notice.text);
+                    if notice.critical {
+                        println!("ESCALATED: {}", notice.text);
+                        if let Some(url) = &settings.escalate_webhook {
+                            escalate(url, &notice.text);
+                        }
+                    } else if settings.alerts.is_quiet(now) {
+                        println!("Quiet hours, event only recorded: {}", notice.text);
+                    } else {
+                        println!("EVENT: {}", notice.text);
+                    }
+                }
             } else {
                 eprintln!("Failed to fetch price");
+                use_last_good(asset.as_ref(), &cache, &history, &out);
+            }
+            //pause btw requests
+            thread::sleep(settings.pause);
+        }
+        //cycle summary
+        let now = now_secs();
+        for asset in &assets {
+            for window in history.stats(asset.name(), now) {
+                println!("{} {}", asset.name(), window.line());
             }
-            //pause 3 secs btw requests
-            thread::sleep(Duration::from_secs(3));
         }
         //wait before next round
-        println!("Waiting 10 seconds before next round...\n");
-        thread::sleep(Duration::from_secs(10));
+        println!("Waiting {} before next round...\n", duration::format(settings.interval));
+        thread::sleep(settings.interval);
+    }
+}
+
+//tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    //hands back what the fan-out gave it
+    struct Capture(mpsc::Sender<Sample>);
+
+    impl Sink for Capture {
+        fn name(&self) -> String {
+            "capture".to_string()
+        }
+
+        fn write(&mut self, sample: &Sample) -> Result<(), String> {
+            self.0.send(sample.clone()).map_err(|e| e.to_string())
+        }
+    }
+
+    fn temp_path(name: &str) -> &'static str {
+        let path = env::temp_dir().join(format!("data_fetch-{}-{}", name, process::id()));
+        Box::leak(path.to_string_lossy().into_owned().into_boxed_str())
+    }
+
+    #[test]
+    fn test_last_good() {
+        let path = temp_path("last_good.txt");
+        let _ = fs::remove_file(path);
+        let mut cache = LastGood::load(path);
+        assert_eq!(cache.get("bitcoin"), None);
+        cache.update("bitcoin", 61000.5);
+        cache.update("ethereum", 3000.25);
+        //a restart reads back what was written
+        let cache = LastGood::load(path);
+        let (price, at) = cache.get("bitcoin").unwrap();
+        assert_eq!(price, 61000.5);
+        assert!(now_secs() - at < 60);
+        assert_eq!(cache.get("ethereum").map(|(p, _)| p), Some(3000.25));
+        fs::remove_file(path).unwrap();
+
+        //a failed fetch falls back to the cached price, flagged stale
+        let (tx, rx) = mpsc::channel();
+        let out = FanOut::new(vec![Box::new(Capture(tx))]);
+        let history = History::new(vec![Duration::from_secs(3600)]);
+        use_last_good(&Bitcoin, &cache, &history, &out);
+        let mut stale = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!((stale.asset, stale.price, stale.stale), ("bitcoin", 61000.5, true));
+        //nothing cached, nothing sent
+        use_last_good(&SP500, &cache, &history, &out);
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+        stale.file = temp_path("bitcoin_prices.txt");
+        let _ = fs::remove_file(stale.file);
+        FileSink.write(&stale).unwrap();
+        assert_eq!(fs::read_to_string(stale.file).unwrap(), "61000.5 stale=true\n");
+        fs::remove_file(stale.file).unwrap();
+    }
+
+    #[test]
+    fn test_bucket() {
+        let t = Instant::now();
+        let secs = |n| t + Duration::from_secs(n);
+        let mut b = Bucket::new(6, t);
+        //a full minute's quota up front
+        for _ in 0..6 {
+            assert_eq!(b.wait(t), Duration::ZERO);
+            b.take(t);
+        }
+        assert_eq!(b.usage(), "6/6 per min");
+        //then one request every 10s
+        assert_eq!(b.wait(t).as_millis(), 10_000);
+        assert_eq!(b.wait(secs(4)).as_millis(), 6_000);
+        assert_eq!(b.wait(secs(10)), Duration::ZERO);
+        b.take(secs(10));
+        assert!(b.wait(secs(10)) > Duration::ZERO);
+        //usage counts the last minute only, an idle bucket refills to the quota and no further
+        assert_eq!(b.wait(secs(65)), Duration::ZERO);
+        assert_eq!(b.usage(), "1/6 per min");
+        assert_eq!(b.wait(secs(3600)), Duration::ZERO);
+        assert_eq!((b.usage().as_str(), b.tokens), ("0/6 per min", 6.0));
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parse = |line: &str| parse_args(line.split_whitespace().map(String::from));
+        let s = parse("--pause 500ms --interval 2m --max-wait 5 --escalate-after 1h30m --window 15m --window 1d").unwrap().unwrap();
+        assert_eq!((s.pause, s.interval, s.max_wait), (Duration::from_millis(500), Duration::from_secs(120), Duration::from_secs(5)));
+        assert_eq!(s.alerts.escalate_after, Some(Duration::from_secs(5400)));
+        assert_eq!(s.windows, vec![Duration::from_secs(900), Duration::from_secs(86_400)]);
+        //default windows only when none are given
+        assert_eq!(parse("").unwrap().unwrap().windows, vec![Duration::from_secs(3600), Duration::from_secs(86_400)]);
+
+        assert!(parse("--help").unwrap().is_none());
+        assert!(parse("--pause 1s -h").unwrap().is_none());
+        assert_eq!(parse("--bogus").err().as_deref(), Some("unknown flag --bogus"));
+        assert_eq!(parse("--interval").err().as_deref(), Some("--interval requires a value"));
+        assert!(parse("--pause soon").err().unwrap().starts_with("--pause: "));
+        assert_eq!(parse("--window 0s").err().as_deref(), Some("--window must be longer than 0s"));
     }
 }
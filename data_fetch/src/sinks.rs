@@ -0,0 +1,279 @@
+//where each sample goes: the price files, plus sqlite and a webhook when asked for;
+//every sink has its own bounded queue and thread, so a slow or broken one never holds up the others
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+
+use sitewatch::db::Db;
+
+use crate::stats::WindowStats;
+
+//samples waiting per sink before new ones are dropped
+const QUEUE_LEN: usize = 100;
+//tries per sample, waiting 1s, 2s, 4s... in between
+const TRIES: u32 = 4;
+const FIRST_WAIT: Duration = Duration::from_secs(1);
+
+//one price as written everywhere
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub asset: &'static str,
+    pub symbol: &'static str,
+    //api the price came from
+    pub source: &'static str,
+    //iso 4217 code the price is in
+    pub currency: &'static str,
+    //places the price is quoted to
+    pub decimals: u8,
+    //price file of the asset
+    pub file: &'static str,
+    pub price: f64,
+    //repeated from the last-known-good cache
+    pub stale: bool,
+    //min/max/drawdown over each --window, fresh prices up to this one
+    pub windows: Vec<WindowStats>,
+    //unix seconds
+    pub at: u64,
+}
+
+pub trait Sink: Send {
+    fn name(&self) -> String;
+    fn write(&mut self, sample: &Sample) -> Result<(), String>;
+}
+
+//one line per price, stale ones flagged so readers never see a gap
+pub struct FileSink;
+
+impl Sink for FileSink {
+    fn name(&self) -> String {
+        "file".to_string()
+    }
+
+    fn write(&mut self, sample: &Sample) -> Result<(), String> {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(sample.file)
+            .map_err(|e| format!("{}: {}", sample.file, e))?;
+        let line = if sample.stale { format!("{} stale=true", sample.price) } else { sample.price.to_string() };
+        writeln!(file, "{}", line).map_err(|e| format!("{}: {}", sample.file, e))
+    }
+}
+
+//through sitewatch's sqlite3 shell wrapper, one process per sample
+pub struct SqliteSink {
+    pub path: String,
+}
+
+impl Sink for SqliteSink {
+    fn name(&self) -> String {
+        format!("sqlite {}", self.path)
+    }
+
+    fn write(&mut self, sample: &Sample) -> Result<(), String> {
+        let mut sql = format!(
+            "CREATE TABLE IF NOT EXISTS prices (asset TEXT NOT NULL, symbol TEXT NOT NULL, source TEXT NOT NULL, currency TEXT NOT NULL, \
+             decimals INTEGER NOT NULL, price REAL NOT NULL, stale INTEGER NOT NULL, ts INTEGER NOT NULL);\n\
+             INSERT INTO prices VALUES ('{}', '{}', '{}', '{}', {}, {}, {}, {});\n",
+            sample.asset, sample.symbol, sample.source, sample.currency, sample.decimals, sample.price, sample.stale as u8, sample.at
+        );
+        if !sample.windows.is_empty() {
+            sql.push_str(
+                "CREATE TABLE IF NOT EXISTS price_windows (asset TEXT NOT NULL, window_secs INTEGER NOT NULL, min REAL NOT NULL, \
+                 max REAL NOT NULL, drawdown REAL NOT NULL, ts INTEGER NOT NULL);\n",
+            );
+        }
+        for w in &sample.windows {
+            sql.push_str(&format!(
+                "INSERT INTO price_windows VALUES ('{}', {}, {}, {}, {}, {});\n",
+                sample.asset,
+                w.span.as_secs(),
+                w.min,
+                w.max,
+                w.drawdown,
+                sample.at
+            ));
+        }
+        Db::at(&self.path).exec(&sql)
+    }
+}
+
+//json POST per sample
+pub struct WebhookSink {
+    pub url: String,
+}
+
+impl Sink for WebhookSink {
+    fn name(&self) -> String {
+        format!("webhook {}", self.url)
+    }
+
+    fn write(&mut self, sample: &Sample) -> Result<(), String> {
+        let body = serde_json::json!({
+            "asset": sample.asset,
+            "symbol": sample.symbol,
+            "source": sample.source,
+            "currency": sample.currency,
+            "decimals": sample.decimals,
+            "price": sample.price,
+            "stale": sample.stale,
+            "windows": sample
+                .windows
+                .iter()
+                .map(|w| serde_json::json!({ "secs": w.span.as_secs(), "min": w.min, "max": w.max, "drawdown": w.drawdown }))
+                .collect::<Vec<_>>(),
+            "ts": sample.at,
+        });
+        ureq::post(&self.url)
+            .timeout(Duration::from_secs(10))
+            .send_json(body)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+//hands every sample to every sink's queue, never waits on one
+pub struct FanOut {
+    queues: Vec<(String, SyncSender<Sample>)>,
+}
+
+impl FanOut {
+    pub fn new(sinks: Vec<Box<dyn Sink>>) -> Self {
+        Self::with_limits(sinks, QUEUE_LEN, FIRST_WAIT)
+    }
+
+    fn with_limits(sinks: Vec<Box<dyn Sink>>, queue_len: usize, first_wait: Duration) -> Self {
+        let queues = sinks
+            .into_iter()
+            .map(|sink| {
+                let (tx, rx) = mpsc::sync_channel(queue_len);
+                let name = sink.name();
+                thread::spawn(move || drain(sink, rx, first_wait));
+                (name, tx)
+            })
+            .collect();
+        FanOut { queues }
+    }
+
+    pub fn send(&self, sample: &Sample) {
+        for (name, tx) in &self.queues {
+            match tx.try_send(sample.clone()) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => eprintln!("{} sink is behind, dropped {} sample", name, sample.asset),
+                Err(TrySendError::Disconnected(_)) => eprintln!("{} sink has stopped", name),
+            }
+        }
+    }
+}
+
+//one sink's thread: each sample retried with doubling waits, then given up on so the queue moves
+fn drain(mut sink: Box<dyn Sink>, rx: mpsc::Receiver<Sample>, first_wait: Duration) {
+    for sample in rx {
+        for n in 1..=TRIES {
+            match sink.write(&sample) {
+                Ok(()) => break,
+                Err(err) if n < TRIES => {
+                    eprintln!("{} sink error (try {}/{}): {}", sink.name(), n, TRIES, err);
+                    thread::sleep(first_wait * (1 << (n - 1)));
+                }
+                Err(err) => eprintln!("{} sink gave up on {} sample: {}", sink.name(), sample.asset, err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{Receiver, Sender};
+    use std::time::Instant;
+
+    fn sample(price: f64) -> Sample {
+        Sample { asset: "bitcoin", symbol: "BTC", source: "test", currency: "USD", decimals: 2, price, stale: false, windows: Vec::new(), file: "unused", at: 0 }
+    }
+
+    //passes every price on
+    struct Capture(Sender<f64>);
+
+    impl Sink for Capture {
+        fn name(&self) -> String {
+            "capture".to_string()
+        }
+
+        fn write(&mut self, sample: &Sample) -> Result<(), String> {
+            self.0.send(sample.price).map_err(|e| e.to_string())
+        }
+    }
+
+    //fails the first try of every sample, passes every try on
+    struct Flaky(Sender<Result<f64, f64>>, bool);
+
+    impl Sink for Flaky {
+        fn name(&self) -> String {
+            "flaky".to_string()
+        }
+
+        fn write(&mut self, sample: &Sample) -> Result<(), String> {
+            self.1 = !self.1;
+            let tried = if self.1 { Err(sample.price) } else { Ok(sample.price) };
+            self.0.send(tried).map_err(|e| e.to_string())?;
+            tried.map(|_| ()).map_err(|p| format!("refused {}", p))
+        }
+    }
+
+    //says when it has a sample, then holds it until released
+    struct Stuck { entered: Sender<()>, release: Receiver<()>, got: Sender<f64> }
+
+    impl Sink for Stuck {
+        fn name(&self) -> String {
+            "stuck".to_string()
+        }
+
+        fn write(&mut self, sample: &Sample) -> Result<(), String> {
+            let _ = self.entered.send(());
+            let _ = self.release.recv();
+            self.got.send(sample.price).map_err(|e| e.to_string())
+        }
+    }
+
+    #[test]
+    fn test_fan_out_isolation() {
+        let wait = Duration::from_secs(2);
+        let (capture_tx, captured) = mpsc::channel();
+        let (flaky_tx, flaky) = mpsc::channel();
+        let (entered_tx, entered) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel();
+        let (stuck_tx, stuck) = mpsc::channel();
+        let sinks: Vec<Box<dyn Sink>> = vec![
+            Box::new(Stuck { entered: entered_tx, release: release_rx, got: stuck_tx }),
+            Box::new(Flaky(flaky_tx, false)),
+            Box::new(Capture(capture_tx)),
+        ];
+        let fan = FanOut::with_limits(sinks, 2, Duration::from_millis(1));
+
+        let start = Instant::now();
+        for n in 1..=5 {
+            fan.send(&sample(n as f64));
+            //the others keep up while the stuck sink holds its first sample
+            assert_eq!(captured.recv_timeout(wait), Ok(n as f64));
+            assert_eq!(flaky.recv_timeout(wait), Ok(Err(n as f64)));
+            assert_eq!(flaky.recv_timeout(wait), Ok(Ok(n as f64)));
+            if n == 1 {
+                entered.recv_timeout(wait).unwrap();
+            }
+        }
+        assert!(start.elapsed() < wait);
+
+        //one sample in hand and two queued; 4 and 5 came in while the queue was full and were dropped
+        for _ in 0..3 {
+            release.send(()).unwrap();
+        }
+        let got: Vec<f64> = (0..3).map(|_| stuck.recv_timeout(wait).unwrap()).collect();
+        assert_eq!(got, vec![1.0, 2.0, 3.0]);
+        drop(fan);
+        drop(release);
+        assert!(stuck.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//rolling min, max and maximum drawdown per asset over the --window spans, from fresh prices only
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use sitewatch::duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowStats {
+    pub span: Duration,
+    pub min: f64,
+    pub max: f64,
+    //largest fall from a peak to a later price, as a fraction of the peak
+    pub drawdown: f64,
+}
+
+impl WindowStats {
+    pub fn line(&self) -> String {
+        format!("{}: min {} max {} drawdown {:.2}%", duration::format(self.span), self.min, self.max, self.drawdown * 100.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct History {
+    spans: Vec<Duration>,
+    //(unix secs, price) per asset, oldest first, nothing older than the longest span
+    ticks: HashMap<&'static str, VecDeque<(u64, f64)>>,
+}
+
+impl History {
+    pub fn new(spans: Vec<Duration>) -> Self {
+        History { spans, ticks: HashMap::new() }
+    }
+
+    pub fn push(&mut self, asset: &'static str, price: f64, at: u64) {
+        let keep = self.spans.iter().max().map_or(0, Duration::as_secs);
+        let ticks = self.ticks.entry(asset).or_default();
+        ticks.push_back((at, price));
+        while ticks.front().is_some_and(|(t, _)| at.saturating_sub(*t) > keep) {
+            ticks.pop_front();
+        }
+    }
+
+    //one entry per span that has a price in it
+    pub fn stats(&self, asset: &str, now: u64) -> Vec<WindowStats> {
+        let Some(ticks) = self.ticks.get(asset) else { return Vec::new() };
+        self.spans
+            .iter()
+            .filter_map(|span| {
+                let mut prices = ticks.iter().filter(|(t, _)| now.saturating_sub(*t) <= span.as_secs()).map(|(_, p)| *p);
+                let first = prices.next()?;
+                let (mut min, mut max, mut peak, mut drawdown) = (first, first, first, 0.0f64);
+                for p in prices {
+                    min = min.min(p);
+                    max = max.max(p);
+                    peak = peak.max(p);
+                    if peak > 0.0 {
+                        drawdown = drawdown.max((peak - p) / peak);
+                    }
+                }
+                Some(WindowStats { span: *span, min, max, drawdown })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows() {
+        let mut h = History::new(vec![Duration::from_secs(60), Duration::from_secs(600)]);
+        //peak 120 then trough 90: a 25% drawdown; the later recovery does not undo it
+        for (at, price) in [(0, 100.0), (100, 120.0), (200, 90.0), (550, 110.0), (580, 99.0), (600, 105.0)] {
+            h.push("bitcoin", price, at);
+        }
+        let stats = h.stats("bitcoin", 600);
+        assert_eq!(stats[0], WindowStats { span: Duration::from_secs(60), min: 99.0, max: 110.0, drawdown: 0.1 });
+        assert_eq!(stats[1], WindowStats { span: Duration::from_secs(600), min: 90.0, max: 120.0, drawdown: 0.25 });
+        assert_eq!(stats[1].line(), "10m: min 90 max 120 drawdown 25.00%");
+        //a rise only is no drawdown
+        h.push("ethereum", 10.0, 0);
+        h.push("ethereum", 12.0, 30);
+        assert_eq!(h.stats("ethereum", 30)[0].drawdown, 0.0);
+        assert!(h.stats("sp500", 600).is_empty());
+
+        //ticks older than the longest span are dropped, spans with no tick left are left out
+        h.push("bitcoin", 50.0, 1300);
+        assert_eq!(h.ticks["bitcoin"].len(), 1);
+        assert_eq!(h.stats("bitcoin", 1300)[1], WindowStats { span: Duration::from_secs(600), min: 50.0, max: 50.0, drawdown: 0.0 });
+        assert_eq!(h.stats("bitcoin", 1400).len(), 1);
+    }
+}
@@ -0,0 +1,110 @@
+//price alerts with hysteresis: an event when a price crosses the trigger, and no other one until it has
+//gone back past the reset level, so a price wobbling around the trigger is one event and not a storm
+use std::fs::OpenOptions;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Above,
+    Below,
+}
+
+#[derive(Debug)]
+pub struct Threshold {
+    pub asset: String,
+    pub direction: Direction,
+    pub trigger: f64,
+    pub reset: f64,
+    //false from an event until the price is back past reset
+    armed: bool,
+}
+
+impl Threshold {
+    //"bitcoin=70000,69000": trigger at 70000, reset at 69000
+    pub fn parse(direction: Direction, spec: &str) -> Result<Self, String> {
+        let (asset, levels) = spec.split_once('=').ok_or("expected ASSET=TRIGGER,RESET")?;
+        let (trigger, reset) = levels.split_once(',').ok_or("expected ASSET=TRIGGER,RESET")?;
+        let trigger: f64 = trigger.trim().parse().map_err(|_| format!("invalid trigger {}", trigger))?;
+        let reset: f64 = reset.trim().parse().map_err(|_| format!("invalid reset {}", reset))?;
+        let wrong_side = match direction {
+            Direction::Above => reset >= trigger,
+            Direction::Below => reset <= trigger,
+        };
+        if wrong_side {
+            return Err(format!("reset {} has to be on the other side of trigger {}", reset, trigger));
+        }
+        Ok(Threshold { asset: asset.trim().to_string(), direction, trigger, reset, armed: true })
+    }
+
+    //between an event and the price going back past reset
+    pub fn crossed(&self) -> bool {
+        !self.armed
+    }
+
+    //the event line for this price, if it fires
+    pub fn observe(&mut self, asset: &str, price: f64) -> Option<String> {
+        if asset != self.asset {
+            return None;
+        }
+        let (crossed, back) = match self.direction {
+            Direction::Above => (price >= self.trigger, price <= self.reset),
+            Direction::Below => (price <= self.trigger, price >= self.reset),
+        };
+        if self.armed && crossed {
+            self.armed = false;
+            let word = if self.direction == Direction::Above { "above" } else { "below" };
+            return Some(format!("{} {} {} at {} (resets at {})", asset, word, self.trigger, price, self.reset));
+        }
+        if !self.armed && back {
+            self.armed = true;
+        }
+        None
+    }
+}
+
+//event history next to the price files, "unix_secs event"
+pub fn record(path: &str, at: u64, event: &str) {
+    let written = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{} {}", at, event));
+    if let Err(err) = written {
+        eprintln!("Event write error: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hysteresis() {
+        let mut t = Threshold::parse(Direction::Above, "bitcoin=70000,69000").unwrap();
+        assert_eq!(t.observe("bitcoin", 69_999.0), None);
+        assert_eq!(t.observe("bitcoin", 70_100.0).unwrap(), "bitcoin above 70000 at 70100 (resets at 69000)");
+        //wobbling around the trigger stays one event
+        for price in [69_800.0, 70_050.0, 69_500.0, 71_000.0] {
+            assert_eq!(t.observe("bitcoin", price), None);
+        }
+        assert!(t.crossed());
+        //other assets never touch it
+        assert_eq!(t.observe("ethereum", 68_000.0), None);
+        assert!(t.crossed());
+        //back past reset re-arms
+        assert_eq!(t.observe("bitcoin", 69_000.0), None);
+        assert!(!t.crossed());
+        assert!(t.observe("bitcoin", 70_000.0).is_some());
+
+        let mut t = Threshold::parse(Direction::Below, " sp500 = 4000 , 4100").unwrap();
+        assert_eq!(t.asset, "sp500");
+        assert_eq!(t.observe("sp500", 3990.0).unwrap(), "sp500 below 4000 at 3990 (resets at 4100)");
+        assert_eq!(t.observe("sp500", 4050.0), None);
+        assert_eq!(t.observe("sp500", 3900.0), None);
+
+        assert_eq!(Threshold::parse(Direction::Above, "bitcoin=70000,71000").unwrap_err(), "reset 71000 has to be on the other side of trigger 70000");
+        assert!(Threshold::parse(Direction::Below, "sp500=4000,4000").is_err());
+        assert!(Threshold::parse(Direction::Above, "bitcoin=70000").is_err());
+        assert!(Threshold::parse(Direction::Above, "bitcoin=lots,1").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//a/b mode: two configurations alternate round by round, compared pairwise per url
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::WebsiteStatus;
+
+//paired samples of one url
+#[derive(Debug, Clone, Default)]
+struct Pairs {
+    //b minus a response time in ms, only where both were up
+    diffs: Vec<f64>,
+    a_ms: Vec<f64>,
+    b_ms: Vec<f64>,
+    a_errors: u64,
+    b_errors: u64,
+    rounds: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Comparison {
+    by_url: HashMap<Arc<str>, Pairs>,
+}
+
+fn mean(v: &[f64]) -> f64 {
+    if v.is_empty() { 0.0 } else { v.iter().sum::<f64>() / v.len() as f64 }
+}
+
+//half width of the ~95% interval of the mean, normal approximation
+fn ci95(v: &[f64]) -> f64 {
+    if v.len() < 2 { return f64::NAN; }
+    let m = mean(v);
+    let var = v.iter().map(|x| (x - m).powi(2)).sum::<f64>() / (v.len() - 1) as f64;
+    1.96 * (var / v.len() as f64).sqrt()
+}
+
+impl Comparison {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //one a round and the b round that followed it
+    pub fn record_pair(&mut self, a: &[WebsiteStatus], b: &[WebsiteStatus]) {
+        let b_by_url: HashMap<&str, &WebsiteStatus> = b.iter().map(|r| (&*r.url, r)).collect();
+        for ra in a {
+            let Some(rb) = b_by_url.get(&*ra.url) else { continue };
+            let p = self.by_url.entry(ra.url.clone()).or_default();
+            p.rounds += 1;
+            if !ra.is_up() { p.a_errors += 1; }
+            if !rb.is_up() { p.b_errors += 1; }
+            if ra.is_up() && rb.is_up() {
+                let (ma, mb) = (ra.response_time.as_secs_f64() * 1000.0, rb.response_time.as_secs_f64() * 1000.0);
+                p.a_ms.push(ma);
+                p.b_ms.push(mb);
+                p.diffs.push(mb - ma);
+            }
+        }
+    }
+
+    //table of paired differences, one row per url
+    pub fn report(&self) -> Vec<String> {
+        let mut out = vec![
+            format!("{:<6} | {:<9} | {:<9} | {:<18} | {:<11} | URL", "pairs", "A avg ms", "B avg ms", "B-A ms (95% CI)", "errors A/B"),
+            "-".repeat(90),
+        ];
+        let mut urls: Vec<_> = self.by_url.keys().collect();
+        urls.sort();
+        for url in urls {
+            let p = &self.by_url[url];
+            let ci = ci95(&p.diffs);
+            let diff = if p.diffs.is_empty() {
+                "-".to_string()
+            } else if ci.is_nan() {
+                format!("{:+.1}", mean(&p.diffs))
+            } else {
+                format!("{:+.1} ±{:.1}", mean(&p.diffs), ci)
+            };
+            out.push(format!(
+                "{:<6} | {:<9.1} | {:<9.1} | {:<18} | {:<11} | {}",
+                p.rounds, mean(&p.a_ms), mean(&p.b_ms), diff, format!("{}/{}", p.a_errors, p.b_errors), url,
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+    use crate::{CheckError, ErrorKind};
+
+    #[test]
+    fn test_paired_comparison() {
+        let mut cmp = Comparison::new();
+        for (a_ms, b_ms) in [(100, 80), (120, 95), (110, 90)] {
+            cmp.record_pair(&[status_for("u", Ok(200), a_ms)], &[status_for("u", Ok(200), b_ms)]);
+        }
+        let down = Err(CheckError::new(ErrorKind::Transport, "x"));
+        cmp.record_pair(&[status_for("u", Ok(200), 100)], &[status_for("u", down, 5)]);
+        let lines = cmp.report();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[2].starts_with("4      | 110.0     | 88.3      | -21.7 ±"));
+        assert!(lines[2].contains("| 0/1 "));
+    }
+}
@@ -0,0 +1,315 @@
+//alert rules and notification channels
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use crate::canary::{CanaryPair, CanaryRules, CanaryWindows};
+use crate::{json, memory, DateTime, ErrorKind, Utc, WebsiteStatus};
+
+//which condition a check violated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rule {
+    Status,
+    Latency,
+    Header,
+    Canary,
+}
+
+impl Rule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::Status => "status",
+            Rule::Latency => "latency",
+            Rule::Header => "header",
+            Rule::Canary => "canary",
+        }
+    }
+}
+
+//one violated condition with its detail
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub rule: Rule,
+    pub detail: String,
+}
+
+//where notifications go
+#[derive(Debug, Clone, PartialEq)]
+pub enum Channel {
+    Console,
+    Webhook(String),
+}
+
+impl Channel {
+    pub fn describe(&self) -> String {
+        match self {
+            Channel::Console => "console".into(),
+            Channel::Webhook(url) => format!("webhook {}", url),
+        }
+    }
+}
+
+//rule thresholds
+#[derive(Debug, Clone, Default)]
+pub struct AlertRules {
+    pub latency_ms: Option<u64>,
+    pub canaries: Vec<CanaryPair>,
+    pub canary: CanaryRules,
+}
+
+//one coalesced notification for a url
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub url: String,
+    pub firing: bool,
+    pub test: bool,
+    pub violations: Vec<Violation>,
+    //context about the url from whoever configured it
+    pub note: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Alert {
+    //synthetic notification for channel self-tests
+    pub fn self_test() -> Self {
+        Self { url: "sitewatch://self-test".into(), firing: true, test: true, violations: Vec::new(), note: None, timestamp: DateTime::now() }
+    }
+
+    //human readable list of violated conditions
+    pub fn summary(&self) -> String {
+        if self.test {
+            return "synthetic test notification, no action needed".into();
+        }
+        if !self.firing {
+            return "recovered".into();
+        }
+        self.violations.iter().map(|v| v.detail.clone()).collect::<Vec<_>>().join("; ")
+    }
+
+    //webhook payload, per-rule detail kept
+    pub fn to_json(&self) -> String {
+        let ts_ms = self.timestamp.as_system_time().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let violations: Vec<String> = self.violations.iter()
+            .map(|v| format!("{{\"rule\":{},\"detail\":{}}}", json::string(v.rule.name()), json::string(&v.detail)))
+            .collect();
+        format!(
+            "{{\"url\":{},\"state\":{},\"ts_ms\":{},\"summary\":{},\"violations\":[{}],\"note\":{}}}",
+            json::string(&self.url),
+            json::string(if self.test { "test" } else if self.firing { "firing" } else { "resolved" }),
+            ts_ms,
+            json::string(&self.summary()),
+            violations.join(","),
+            self.note.as_deref().map(json::string).unwrap_or_else(|| "null".into()),
+        )
+    }
+}
+
+//acknowledged and snoozed urls; data is still recorded, only notifications stop
+#[derive(Debug, Default)]
+pub struct Silences {
+    acked: HashSet<String>,
+    snoozed: HashMap<String, Instant>,
+}
+
+impl Silences {
+    //mute until the url recovers
+    pub fn ack(&mut self, url: &str) {
+        self.acked.insert(url.to_string());
+    }
+
+    //mute for a fixed time
+    pub fn snooze(&mut self, url: &str, duration: Duration) {
+        self.snoozed.insert(url.to_string(), Instant::now() + duration);
+    }
+
+    pub fn clear(&mut self, url: &str) -> bool {
+        let acked = self.acked.remove(url);
+        let snoozed = self.snoozed.remove(url).is_some();
+        acked || snoozed
+    }
+
+    //whether this alert should be held back, clears the ack once the url recovers
+    pub fn suppress(&mut self, alert: &Alert) -> bool {
+        if let Some(until) = self.snoozed.get(&alert.url) {
+            if Instant::now() < *until { return true; }
+            self.snoozed.remove(&alert.url);
+        }
+        if !alert.firing {
+            self.acked.remove(&alert.url);
+            return false;
+        }
+        self.acked.contains(&alert.url)
+    }
+}
+
+//conditions a single result violates
+pub fn evaluate(r: &WebsiteStatus, rules: &AlertRules) -> Vec<Violation> {
+    let mut out = Vec::new();
+    match &r.status {
+        Err(e) if e.kind == ErrorKind::Header => out.push(Violation { rule: Rule::Header, detail: e.to_string() }),
+        Err(e) => out.push(Violation { rule: Rule::Status, detail: e.to_string() }),
+        Ok(code) if !r.is_up() => {
+            let detail = match &r.expect {
+                Some(want) => format!("status {}, expected {}", code, want),
+                None => format!("status {}", code),
+            };
+            out.push(Violation { rule: Rule::Status, detail });
+        }
+        Ok(_) => {}
+    }
+    if let Some(max) = rules.latency_ms {
+        let ms = r.response_time.as_millis();
+        if ms > max as u128 {
+            out.push(Violation { rule: Rule::Latency, detail: format!("latency {}ms > {}ms", ms, max) });
+        }
+    }
+    out
+}
+
+//turns round results into deduplicated notifications
+#[derive(Debug)]
+pub struct Alerter {
+    rules: AlertRules,
+    channels: Vec<Channel>,
+    timeout: Duration,
+    //rules last notified per url
+    notified: HashMap<String, Vec<Rule>>,
+    //recent samples for canary comparisons
+    windows: CanaryWindows,
+}
+
+impl Alerter {
+    pub fn new(rules: AlertRules, channels: Vec<Channel>, timeout: Duration) -> Self {
+        Self { rules, channels, timeout, notified: HashMap::new(), windows: CanaryWindows::default() }
+    }
+
+    //rough bytes held between rounds, for --memory-log
+    pub fn heap_bytes(&self) -> usize {
+        let notified: usize = self.notified.iter().map(|(url, rules)| url.capacity() + rules.capacity() * std::mem::size_of::<Rule>()).sum();
+        memory::table_bytes(&self.notified) + notified + self.windows.heap_bytes()
+    }
+
+    //one alert per url per round, only when the violated set changes
+    pub fn process_round(&mut self, results: &[WebsiteStatus]) -> Vec<Alert> {
+        let mut by_url: BTreeMap<&str, (Vec<Violation>, DateTime<Utc>)> = BTreeMap::new();
+        for r in results {
+            let entry = by_url.entry(&*r.url).or_insert_with(|| (Vec::new(), r.timestamp));
+            for v in evaluate(r, &self.rules) {
+                if !entry.0.contains(&v) { entry.0.push(v); }
+            }
+            self.windows.record(r, self.rules.canary.window);
+        }
+        //canary deviations land on the canary url
+        for pair in &self.rules.canaries {
+            let Some(entry) = by_url.get_mut(pair.canary.as_str()) else { continue };
+            entry.0.extend(self.windows.compare(pair, &self.rules.canary));
+        }
+
+        let mut alerts = Vec::new();
+        for (url, (mut violations, timestamp)) in by_url {
+            violations.sort_by_key(|v| v.rule);
+            let mut rules: Vec<Rule> = violations.iter().map(|v| v.rule).collect();
+            rules.dedup();
+            let previous = self.notified.get(url).cloned().unwrap_or_default();
+            if rules == previous { continue; }
+
+            let firing = !rules.is_empty();
+            if firing {
+                self.notified.insert(url.to_string(), rules);
+            } else {
+                self.notified.remove(url);
+            }
+            alerts.push(Alert { url: url.to_string(), firing, test: false, violations, note: None, timestamp });
+        }
+        alerts
+    }
+
+    //deliver to every channel, per-channel outcome
+    pub fn notify(&self, alert: &Alert) -> Vec<(String, Result<(), String>)> {
+        self.channels.iter().map(|c| (c.describe(), self.send(c, alert))).collect()
+    }
+
+    fn send(&self, channel: &Channel, alert: &Alert) -> Result<(), String> {
+        match channel {
+            Channel::Console => {
+                let state = if alert.test { "TEST" } else if alert.firing { "FIRING" } else { "RESOLVED" };
+                println!("ALERT {} {}: {}", state, alert.url, alert.summary());
+                if let Some(note) = &alert.note { println!("    note: {}", note); }
+                Ok(())
+            }
+            Channel::Webhook(url) => ureq::post(url)
+                .timeout(self.timeout)
+                .set("Content-Type", "application/json")
+                .send_string(&alert.to_json())
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for as status;
+    use crate::CheckError;
+
+    #[test]
+    fn test_overlapping_rules_coalesce_into_one_alert() {
+        let rules = AlertRules { latency_ms: Some(100), ..AlertRules::default() };
+        let mut alerter = Alerter::new(rules, vec![], Duration::from_secs(1));
+        let alerts = alerter.process_round(&[status("a", Ok(503), 500), status("b", Ok(200), 5)]);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].violations.len(), 2);
+        assert_eq!(alerts[0].summary(), "status 503; latency 500ms > 100ms");
+        assert!(alerts[0].to_json().contains("\"rule\":\"latency\""));
+        let noted = Alert { note: Some("behind \"cdn\"".into()), ..alerts[0].clone() };
+        assert!(noted.to_json().ends_with(",\"note\":\"behind \\\"cdn\\\"\"}"));
+
+        //same conditions next round stay quiet
+        assert!(alerter.process_round(&[status("a", Ok(502), 400)]).is_empty());
+
+        //header failure changes the set
+        let hdr = Err(CheckError::new(ErrorKind::Header, "missing header X"));
+        let alerts = alerter.process_round(&[status("a", hdr, 5)]);
+        assert_eq!(alerts[0].violations[0].rule, Rule::Header);
+
+        let alerts = alerter.process_round(&[status("a", Ok(200), 5)]);
+        assert!(!alerts[0].firing);
+    }
+
+    #[test]
+    fn test_silences() {
+        let mut alerter = Alerter::new(AlertRules::default(), vec![], Duration::from_secs(1));
+        let firing = alerter.process_round(&[status("a", Ok(500), 1)]).remove(0);
+        let mut silences = Silences::default();
+        assert!(!silences.suppress(&firing));
+        silences.ack("a");
+        assert!(silences.suppress(&firing));
+
+        //recovery is delivered and ends the ack
+        let resolved = alerter.process_round(&[status("a", Ok(200), 1)]).remove(0);
+        assert!(!silences.suppress(&resolved));
+        assert!(!silences.suppress(&firing));
+
+        assert!(Alert::self_test().to_json().contains("\"state\":\"test\""));
+
+        silences.snooze("a", Duration::from_secs(60));
+        assert!(silences.suppress(&resolved));
+        assert!(silences.clear("a"));
+        assert!(!silences.suppress(&firing));
+    }
+
+    #[test]
+    fn test_canary_alert() {
+        let rules = AlertRules {
+            canaries: vec![crate::canary::pair("prod", "canary").unwrap()],
+            canary: CanaryRules { window: 2, ..CanaryRules::default() },
+            ..AlertRules::default()
+        };
+        let mut alerter = Alerter::new(rules, vec![], Duration::from_secs(1));
+        assert!(alerter.process_round(&[status("prod", Ok(200), 10), status("canary", Ok(200), 50)]).is_empty());
+        let alerts = alerter.process_round(&[status("prod", Ok(200), 10), status("canary", Ok(200), 50)]);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].url, "canary");
+        assert_eq!(alerts[0].violations[0].rule, Rule::Canary);
+    }
+}
@@ -0,0 +1,83 @@
+//waits between retries: doubling from --backoff up to --backoff-max, with jitter so checks that failed together
+//do not retry together; a Retry-After on a retried status says when to come back instead
+use std::hash::{BuildHasher, RandomState};
+use std::time::{Duration, SystemTime};
+
+use crate::feed;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    //wait before the first retry, before jitter
+    pub base: Duration,
+    //no wait is longer, and a Retry-After beyond it is not waited for
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self { base: Duration::from_millis(200), max: Duration::from_secs(10) }
+    }
+}
+
+impl Backoff {
+    //wait before retry n (1 for the first): half of base * 2^(n-1) fixed, the other half random
+    pub fn delay(&self, n: u32) -> Duration {
+        let full = self.base.saturating_mul(1 << n.saturating_sub(1).min(20)).min(self.max);
+        full / 2 + (full / 2).mul_f64(jitter())
+    }
+
+    //wait before retrying an http status, None when its Retry-After asks for more than max
+    pub fn after_status(&self, n: u32, retry_after: Option<&str>, now: SystemTime) -> Option<Duration> {
+        match retry_after.and_then(|v| parse_retry_after(v, now)) {
+            Some(wait) if wait > self.max => None,
+            Some(wait) => Some(wait),
+            None => Some(self.delay(n)),
+        }
+    }
+}
+
+//statuses a server sends to say "later", retried unless --retry-on names others
+pub fn retryable(code: u16) -> bool {
+    code == 429 || code == 503
+}
+
+//delta-seconds or an http date; a date in the past means now
+pub fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    match value.parse::<u64>() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => feed::parse_rfc822(value).map(|at| at.duration_since(now).unwrap_or_default()),
+    }
+}
+
+//0.0..1.0, random enough to spread retries
+fn jitter() -> f64 {
+    (RandomState::new().hash_one(SystemTime::now()) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_backoff() {
+        let b = Backoff::default();
+        for (n, full) in [(1, 200), (2, 400), (3, 800), (7, 10_000), (40, 10_000)] {
+            let full = Duration::from_millis(full);
+            for _ in 0..20 {
+                let d = b.delay(n);
+                assert!(d >= full / 2 && d <= full, "retry {}: {:?}", n, d);
+            }
+        }
+        let now = UNIX_EPOCH + Duration::from_secs(1_719_828_000); //2024-07-01 10:00:00 GMT
+        assert_eq!(parse_retry_after(" 3 ", now), Some(Duration::from_secs(3)));
+        assert_eq!(parse_retry_after("Mon, 01 Jul 2024 10:00:05 GMT", now), Some(Duration::from_secs(5)));
+        assert_eq!(parse_retry_after("Mon, 01 Jul 2024 09:00:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert_eq!(b.after_status(1, Some("2"), now), Some(Duration::from_secs(2)));
+        assert_eq!(b.after_status(1, Some("3600"), now), None);
+        assert!(b.after_status(1, Some("soon"), now).is_some_and(|d| d <= Duration::from_millis(200)));
+        assert!(retryable(429) && retryable(503) && !retryable(500) && !retryable(404));
+    }
+}
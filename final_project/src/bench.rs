@@ -0,0 +1,205 @@
+//pipeline benchmarks against a loopback mock server, built with --features bench
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sitewatch::alerts::{AlertRules, Alerter};
+use sitewatch::incident::IncidentTracker;
+use sitewatch::{fleet_summary_json, run_once, Config, DateTime, Stats, WebsiteStatus};
+
+//benchmark sizes
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    pub checks: usize,
+    pub workers: usize,
+    pub rounds: usize,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self { checks: 2000, workers: 50, rounds: 5 }
+    }
+}
+
+//best and mean of the measured rounds
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    pub name: &'static str,
+    pub items: usize,
+    pub best: Duration,
+    pub mean: Duration,
+}
+
+impl Measurement {
+    pub fn per_sec(&self) -> f64 {
+        self.items as f64 / self.best.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn ns_per_item(&self) -> u128 {
+        self.best.as_nanos() / self.items.max(1) as u128
+    }
+}
+
+fn measure(name: &'static str, items: usize, rounds: usize, mut f: impl FnMut()) -> Measurement {
+    //warm up pools and caches once
+    f();
+    let mut times = Vec::with_capacity(rounds.max(1));
+    for _ in 0..rounds.max(1) {
+        let start = Instant::now();
+        f();
+        times.push(start.elapsed());
+    }
+    let best = times.iter().min().copied().unwrap_or_default();
+    let mean = times.iter().sum::<Duration>() / times.len() as u32;
+    Measurement { name, items, best, mean }
+}
+
+//keep-alive http server that answers every request with 200 OK
+fn spawn_mock_server() -> std::io::Result<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            thread::spawn(move || serve(stream));
+        }
+    });
+    Ok(port)
+}
+
+fn serve(stream: TcpStream) {
+    let Ok(mut out) = stream.try_clone() else { return };
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        //skip the request head
+        let mut blank = false;
+        while !blank {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => blank = line == "\r\n" || line == "\n",
+            }
+        }
+        let resp = "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\nOK";
+        if out.write_all(resp.as_bytes()).is_err() { return; }
+    }
+}
+
+//worker pool throughput, job send to result receive
+pub fn dispatch(bc: &BenchConfig) -> Result<Measurement, String> {
+    let port = spawn_mock_server().map_err(|e| format!("mock server: {}", e))?;
+    let cfg = Config {
+        workers: bc.workers.max(1).min(bc.checks.max(1)),
+        timeout: Duration::from_secs(5),
+        urls: (0..bc.checks).map(|i| format!("http://127.0.0.1:{}/ok?{}", port, i).into()).collect(),
+        ..Config::default()
+    };
+    let mut failed = 0;
+    let mut error = None;
+    let m = measure("dispatch", bc.checks, bc.rounds, || {
+        match run_once(&cfg) {
+            Ok(results) => failed += results.iter().filter(|r| !r.is_up()).count(),
+            Err(e) => error = Some(e.to_string()),
+        }
+    });
+    if let Some(e) = error { return Err(e); }
+    if failed > 0 {
+        return Err(format!("{} checks against the mock server failed", failed));
+    }
+    Ok(m)
+}
+
+//per-round bookkeeping on synthetic results, no network
+pub fn processing(bc: &BenchConfig) -> Vec<Measurement> {
+    let results: Vec<WebsiteStatus> = (0..bc.checks)
+        .map(|i| WebsiteStatus {
+            url: format!("http://bench.invalid/{}", i).into(),
+            status: Ok(if i % 10 == 0 { 503 } else { 200 }),
+            response_time: Duration::from_millis((i % 250) as u64),
+            tcp_connect: None,
+            title: None,
+            clock_offset_ms: None,
+            slot: None,
+            retries: 0,
+            expect: None,
+            latency_limit: None,
+            ignored: false,
+            tls: None,
+            phases: None,
+            http_version: None,
+            redirects: Vec::new(),
+            final_url: None,
+            content: None,
+            compression: None,
+            meta: Vec::new(),
+            attempts: Vec::new(),
+            timestamp: DateTime::now(),
+        })
+        .collect();
+    let n = results.len();
+    let mut agg: HashMap<Arc<str>, Stats> = HashMap::new();
+    let mut incidents = IncidentTracker::new(3);
+    let mut alerter = Alerter::new(AlertRules { latency_ms: Some(200), ..AlertRules::default() }, Vec::new(), Duration::from_secs(1));
+    vec![
+        measure("aggregate", n, bc.rounds, || {
+            for r in &results {
+                agg.entry(r.url.clone()).or_default().record(r);
+            }
+        }),
+        measure("fleet-json", n, bc.rounds, || {
+            std::hint::black_box(fleet_summary_json(&results));
+        }),
+        measure("incidents", n, bc.rounds, || {
+            for r in &results {
+                std::hint::black_box(incidents.observe(r));
+            }
+        }),
+        measure("alerts", n, bc.rounds, || {
+            std::hint::black_box(alerter.process_round(&results));
+        }),
+    ]
+}
+
+//--bench [--checks N] [--workers N] [--rounds N]
+pub fn run_cli(args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut bc = BenchConfig::default();
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let n = args.next().ok_or(format!("{} requires a value", arg))?;
+        let n: usize = n.parse().map_err(|_| format!("invalid {} value", arg))?;
+        match arg.as_str() {
+            "--checks" => bc.checks = n,
+            "--workers" => bc.workers = n,
+            "--rounds" => bc.rounds = n,
+            _ => return Err(format!("unknown bench flag: {}", arg)),
+        }
+    }
+
+    println!("bench: {} checks, {} workers, best of {} rounds", bc.checks, bc.workers, bc.rounds);
+    println!("{:<12} | {:>12} | {:>10} | {:>10} | {:>10}", "stage", "items/s", "ns/item", "best ms", "mean ms");
+    println!("{}", "-".repeat(66));
+    let mut all = vec![dispatch(&bc)?];
+    all.extend(processing(&bc));
+    for m in &all {
+        println!("{:<12} | {:>12.0} | {:>10} | {:>10.2} | {:>10.2}",
+            m.name, m.per_sec(), m.ns_per_item(), m.best.as_secs_f64() * 1e3, m.mean.as_secs_f64() * 1e3);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_smoke() {
+        let bc = BenchConfig { checks: 20, workers: 4, rounds: 1 };
+        let m = dispatch(&bc).unwrap();
+        assert_eq!(m.items, 20);
+        assert!(m.per_sec() > 0.0);
+        assert_eq!(processing(&bc).len(), 4);
+    }
+}
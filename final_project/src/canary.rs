@@ -0,0 +1,118 @@
+//canary vs production comparison over a sliding window of checks
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::alerts::{Rule, Violation};
+use crate::{memory, WebsiteStatus};
+
+//a canary url judged against its production counterpart
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanaryPair {
+    pub prod: String,
+    pub canary: String,
+}
+
+//window size and allowed deviation
+#[derive(Debug, Clone)]
+pub struct CanaryRules {
+    pub window: usize,
+    //percentage points of extra errors tolerated on the canary
+    pub error_margin_pct: f64,
+    //percent slower than prod tolerated on the canary
+    pub latency_margin_pct: f64,
+}
+
+impl Default for CanaryRules {
+    fn default() -> Self {
+        Self { window: 10, error_margin_pct: 5.0, latency_margin_pct: 50.0 }
+    }
+}
+
+//recent (up, response time) samples per url
+#[derive(Debug, Default)]
+pub struct CanaryWindows {
+    samples: HashMap<Arc<str>, VecDeque<(bool, Duration)>>,
+}
+
+impl CanaryWindows {
+    pub fn record(&mut self, r: &WebsiteStatus, window: usize) {
+        let w = self.samples.entry(r.url.clone()).or_default();
+        w.push_back((r.is_up(), r.response_time));
+        while w.len() > window.max(1) { w.pop_front(); }
+    }
+
+    pub fn heap_bytes(&self) -> usize {
+        memory::table_bytes(&self.samples) + self.samples.values().map(memory::deque_bytes).sum::<usize>()
+    }
+
+    //error rate in percent and mean latency, only once the window is full
+    fn window_stats(&self, url: &str, window: usize) -> Option<(f64, Duration)> {
+        let w = self.samples.get(url)?;
+        if w.len() < window.max(1) { return None; }
+        let errors = w.iter().filter(|(up, _)| !up).count();
+        let total: Duration = w.iter().map(|(_, t)| *t).sum();
+        Some((errors as f64 * 100.0 / w.len() as f64, total / w.len() as u32))
+    }
+
+    //deviations of the canary from prod, empty until both windows are full
+    pub fn compare(&self, pair: &CanaryPair, rules: &CanaryRules) -> Vec<Violation> {
+        let (Some((prod_err, prod_lat)), Some((can_err, can_lat))) =
+            (self.window_stats(&pair.prod, rules.window), self.window_stats(&pair.canary, rules.window))
+        else {
+            return Vec::new();
+        };
+        let mut out = Vec::new();
+        if can_err - prod_err > rules.error_margin_pct {
+            out.push(Violation {
+                rule: Rule::Canary,
+                detail: format!("canary error rate {:.1}% vs prod {:.1}% over {} checks", can_err, prod_err, rules.window),
+            });
+        }
+        let limit = prod_lat.as_secs_f64() * (1.0 + rules.latency_margin_pct / 100.0);
+        if can_lat.as_secs_f64() > limit {
+            out.push(Violation {
+                rule: Rule::Canary,
+                detail: format!("canary latency {}ms vs prod {}ms over {} checks", can_lat.as_millis(), prod_lat.as_millis(), rules.window),
+            });
+        }
+        out
+    }
+}
+
+//prod=canary pairing from two flag values
+pub fn pair(prod: &str, canary: &str) -> Result<CanaryPair, String> {
+    if prod == canary {
+        return Err(format!("canary '{}' is the same URL as prod", canary));
+    }
+    Ok(CanaryPair { prod: prod.to_string(), canary: canary.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+
+    #[test]
+    fn test_canary_deviation() {
+        let rules = CanaryRules { window: 4, ..CanaryRules::default() };
+        let p = pair("prod", "canary").unwrap();
+        let mut w = CanaryWindows::default();
+        for i in 0..4 {
+            w.record(&status_for("prod", Ok(200), 100), rules.window);
+            //one failure in four on the canary, and twice as slow
+            w.record(&status_for("canary", Ok(if i == 0 { 500 } else { 200 }), 200), rules.window);
+            if i < 3 { assert!(w.compare(&p, &rules).is_empty()); }
+        }
+        let v = w.compare(&p, &rules);
+        assert_eq!(v.len(), 2);
+        assert!(v[0].detail.contains("25.0%"));
+
+        //healthy samples push the bad ones out of the window
+        for _ in 0..4 {
+            w.record(&status_for("canary", Ok(200), 120), rules.window);
+        }
+        assert!(w.compare(&p, &rules).is_empty());
+        assert!(pair("a", "a").is_err());
+    }
+}
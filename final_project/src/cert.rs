@@ -0,0 +1,284 @@
+//--cert-warn-days and --tls-info: what an https server negotiates and the certificate it presents,
+//read straight from its der encoding
+use std::fmt;
+use std::io::{Read, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use url::Url;
+
+use crate::{scheduler, Network};
+
+//tag and contents of the der element at the start of buf, plus what follows it
+fn element(buf: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = buf.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        //long form, at most 4 length bytes
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n { return None; }
+        (rest[..n].iter().fold(0usize, |acc, &b| acc << 8 | b as usize), &rest[n..])
+    };
+    if rest.len() < len { return None; }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+//utctime (yymmddhhmmssZ) or generalizedtime (yyyymmddhhmmssZ)
+fn time(tag: u8, v: &[u8]) -> Option<SystemTime> {
+    let s = std::str::from_utf8(v).ok()?.strip_suffix('Z')?;
+    let (year, rest) = match tag {
+        0x17 if s.len() == 12 => {
+            let yy: i64 = s[..2].parse().ok()?;
+            (if yy < 50 { 2000 + yy } else { 1900 + yy }, &s[2..])
+        }
+        0x18 if s.len() == 14 => (s[..4].parse().ok()?, &s[4..]),
+        _ => return None,
+    };
+    if !rest.bytes().all(|b| b.is_ascii_digit()) { return None; }
+    let num = |i: usize| rest[i..i + 2].parse::<u32>().ok();
+    let (month, day, h, m, sec) = (num(0)?, num(2)?, num(4)? as i64, num(6)? as i64, num(8)? as i64);
+    let secs = scheduler::days_from_civil(year, month, day) * 86_400 + h * 3600 + m * 60 + sec;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+//short names of the usual name attributes, 2.5.4.x
+fn attr_name(oid: &[u8]) -> Option<&'static str> {
+    match oid {
+        [0x55, 0x04, 0x03] => Some("CN"),
+        [0x55, 0x04, 0x06] => Some("C"),
+        [0x55, 0x04, 0x07] => Some("L"),
+        [0x55, 0x04, 0x08] => Some("ST"),
+        [0x55, 0x04, 0x0a] => Some("O"),
+        [0x55, 0x04, 0x0b] => Some("OU"),
+        _ => None,
+    }
+}
+
+//"CN=a, O=b" from a distinguished name, unknown attributes left out
+fn name(der: &[u8]) -> String {
+    let mut parts = Vec::new();
+    let mut sets = der;
+    while let Some((_, set, rest)) = element(sets) {
+        sets = rest;
+        let mut atvs = set;
+        while let Some((_, atv, rest)) = element(atvs) {
+            atvs = rest;
+            let Some((0x06, oid, value)) = element(atv) else { continue };
+            if let (Some(key), Some((_, v, _))) = (attr_name(oid), element(value)) {
+                parts.push(format!("{}={}", key, String::from_utf8_lossy(v)));
+            }
+        }
+    }
+    parts.join(", ")
+}
+
+//the parts of an x.509 certificate the checks look at
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leaf {
+    pub subject: String,
+    pub issuer: String,
+    pub not_after: SystemTime,
+}
+
+pub fn parse(der: &[u8]) -> Result<Leaf, String> {
+    let bad = || "malformed certificate".to_string();
+    let (_, cert, _) = element(der).ok_or_else(bad)?;
+    let (_, tbs, _) = element(cert).ok_or_else(bad)?;
+    let mut rest = tbs;
+    //optional [0] version, then serial and signature algorithm before the issuer
+    if rest.first() == Some(&0xa0) { rest = element(rest).ok_or_else(bad)?.2; }
+    for _ in 0..2 { rest = element(rest).ok_or_else(bad)?.2; }
+    let (_, issuer, rest) = element(rest).ok_or_else(bad)?;
+    let (_, validity, rest) = element(rest).ok_or_else(bad)?;
+    let (_, subject, _) = element(rest).ok_or_else(bad)?;
+    let (_, _, after) = element(validity).ok_or_else(bad)?;
+    let (tag, value, _) = element(after).ok_or_else(bad)?;
+    Ok(Leaf { subject: name(subject), issuer: name(issuer), not_after: time(tag, value).ok_or_else(bad)? })
+}
+
+//handshake summary for --tls-info
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsInfo {
+    pub version: String,
+    pub cipher: String,
+    pub leaf: Leaf,
+    //whether a tls 1.0/1.1-only hello got a server hello back, None when not probed or the probe failed
+    pub legacy: Option<bool>,
+}
+
+impl fmt::Display for TlsInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}, subject {}, issuer {}", self.version, self.cipher, self.leaf.subject, self.leaf.issuer)?;
+        match self.legacy {
+            Some(true) => f.write_str(", legacy TLS 1.0/1.1 ACCEPTED"),
+            Some(false) => f.write_str(", legacy TLS 1.0/1.1 refused"),
+            None => Ok(()),
+        }
+    }
+}
+
+//one handshake; the legacy hello is a second connection
+pub fn inspect(net: &Network, url: &str, timeout: Duration, legacy: bool) -> Result<TlsInfo, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("invalid url: {}", e))?;
+    let hs = net.tls_handshake(&parsed, timeout)?;
+    let leaf = parse(hs.certs.first().ok_or("server sent no certificate")?)?;
+    let legacy = if legacy { legacy_accepted(net, &parsed, timeout).ok() } else { None };
+    Ok(TlsInfo { version: hs.version, cipher: hs.cipher, leaf, legacy })
+}
+
+//cbc and 3des suites a tls 1.0/1.1 server would pick from
+const LEGACY_SUITES: [u16; 8] = [0xc014, 0xc013, 0xc00a, 0xc009, 0x0035, 0x002f, 0x0039, 0x000a];
+
+//a client hello capped at tls 1.1, never finished; rustls cannot speak it
+fn legacy_hello(host: Option<&str>) -> Vec<u8> {
+    let mut body = vec![0x03, 0x02];
+    //not secret, the handshake is abandoned after the server hello
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    body.extend((0..32).map(|i| (seed >> (i % 16 * 8)) as u8 ^ i as u8));
+    body.push(0);
+    body.extend(((LEGACY_SUITES.len() * 2) as u16).to_be_bytes());
+    for suite in LEGACY_SUITES { body.extend(suite.to_be_bytes()); }
+    body.extend([1, 0]);
+    let mut ext = Vec::new();
+    if let Some(host) = host {
+        let len = host.len() as u16;
+        ext.extend([0x00, 0x00]);
+        ext.extend((len + 5).to_be_bytes());
+        ext.extend((len + 3).to_be_bytes());
+        ext.push(0);
+        ext.extend(len.to_be_bytes());
+        ext.extend(host.as_bytes());
+    }
+    //secp256r1 and secp384r1, uncompressed points
+    ext.extend([0x00, 0x0a, 0x00, 0x06, 0x00, 0x04, 0x00, 0x17, 0x00, 0x18]);
+    ext.extend([0x00, 0x0b, 0x00, 0x02, 0x01, 0x00]);
+    body.extend((ext.len() as u16).to_be_bytes());
+    body.extend(ext);
+    let mut hs = vec![0x01, 0, (body.len() >> 8) as u8, body.len() as u8];
+    hs.extend(body);
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend((hs.len() as u16).to_be_bytes());
+    record.extend(hs);
+    record
+}
+
+//true when the server answers a tls 1.1 hello with a server hello; an alert or a hang-up is a refusal
+pub fn legacy_accepted(net: &Network, url: &Url, timeout: Duration) -> Result<bool, String> {
+    let mut tcp = net.connect_tcp(url, None, timeout)?;
+    let host = url.host_str().filter(|h| h.parse::<std::net::IpAddr>().is_err() && !h.starts_with('['));
+    tcp.write_all(&legacy_hello(host)).map_err(|e| format!("send failed: {}", e))?;
+    let mut head = [0u8; 11];
+    let mut got = 0;
+    while got < head.len() {
+        match tcp.read(&mut head[got..]) {
+            Ok(0) => return Ok(false),
+            Ok(n) => got += n,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::ConnectionReset | std::io::ErrorKind::ConnectionAborted) => return Ok(false),
+            Err(e) => return Err(format!("read failed: {}", e)),
+        }
+        //an alert record is complete in 7 bytes
+        if got >= 7 && head[0] == 0x15 { return Ok(false); }
+    }
+    //handshake record holding a server hello, its version no newer than 1.1
+    Ok(head[0] == 0x16 && head[5] == 0x02 && u16::from_be_bytes([head[9], head[10]]) <= 0x0302)
+}
+
+//whole days from now until expiry, negative once expired
+pub fn days_left(expiry: SystemTime, now: SystemTime) -> i64 {
+    match expiry.duration_since(now) {
+        Ok(d) => (d.as_secs() / 86_400) as i64,
+        Err(e) => -(e.duration().as_secs().div_ceil(86_400) as i64),
+    }
+}
+
+//problem with an expiry inside the warning window
+pub fn check(expiry: SystemTime, warn_days: u32, now: SystemTime) -> Result<i64, String> {
+    let days = days_left(expiry, now);
+    let date = scheduler::format_utc(expiry);
+    if days < 0 { return Err(format!("certificate expired on {}", date)); }
+    if days < warn_days as i64 { return Err(format!("certificate expires in {} days ({}), within the {}-day warning window", days, date, warn_days)); }
+    Ok(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        if content.len() < 0x80 {
+            out.push(content.len() as u8);
+        } else {
+            out.extend([0x82, (content.len() >> 8) as u8, content.len() as u8]);
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    fn dn(attrs: &[(u8, &str)]) -> Vec<u8> {
+        let sets: Vec<u8> = attrs.iter().flat_map(|&(id, v)| der(0x31, &der(0x30, &[der(0x06, &[0x55, 0x04, id]), der(0x0c, v.as_bytes())].concat()))).collect();
+        der(0x30, &sets)
+    }
+
+    //just the fields parse walks past; the long issuer makes its length long form
+    fn cert(version: bool, not_after: (u8, &str)) -> Vec<u8> {
+        let mut tbs = Vec::new();
+        if version { tbs.extend(der(0xa0, &der(0x02, &[2]))); }
+        tbs.extend(der(0x02, &[0x01, 0x23]));
+        tbs.extend(der(0x30, &der(0x06, &[0x2a, 0x86, 0x48])));
+        tbs.extend(dn(&[(0x06, "US"), (0x0a, &"x".repeat(150)), (0x09, "skipped"), (0x03, "Test CA")]));
+        let validity = [der(0x17, b"240101000000Z"), der(not_after.0, not_after.1.as_bytes())].concat();
+        tbs.extend(der(0x30, &validity));
+        tbs.extend(dn(&[(0x03, "a.test")]));
+        der(0x30, &[der(0x30, &tbs), der(0x30, &[]), der(0x03, &[0])].concat())
+    }
+
+    #[test]
+    fn test_parse() {
+        let day = |y, m, d| UNIX_EPOCH + Duration::from_secs(scheduler::days_from_civil(y, m, d) as u64 * 86_400);
+        let leaf = parse(&cert(true, (0x17, "261030120000Z"))).unwrap();
+        assert_eq!(leaf.not_after, day(2026, 10, 30) + Duration::from_secs(12 * 3600));
+        assert_eq!((leaf.subject.as_str(), leaf.issuer.as_str()), ("CN=a.test", &*format!("C=US, O={}, CN=Test CA", "x".repeat(150))));
+        assert_eq!(parse(&cert(false, (0x18, "20510102000000Z"))).unwrap().not_after, day(2051, 1, 2));
+        assert!(parse(&cert(true, (0x17, "2610301200Z"))).is_err());
+        assert!(parse(&cert(true, (0x17, "261030120000Z"))[..40]).is_err());
+
+        let now = day(2026, 10, 16);
+        assert_eq!(check(day(2026, 11, 30), 14, now), Ok(45));
+        assert_eq!(check(day(2026, 10, 26), 14, now).unwrap_err(), "certificate expires in 10 days (2026-10-26 00:00:00 UTC), within the 14-day warning window");
+        assert_eq!(check(day(2026, 10, 15), 14, now).unwrap_err(), "certificate expired on 2026-10-15 00:00:00 UTC");
+        assert_eq!(days_left(now - Duration::from_secs(60), now), -1);
+    }
+
+    #[test]
+    fn test_legacy_probe() {
+        //first connection gets a tls 1.1 server hello, the second a protocol_version alert
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let mut hellos = Vec::new();
+            for (i, stream) in listener.incoming().take(2).enumerate() {
+                let mut s = stream.unwrap();
+                let mut head = [0u8; 5];
+                s.read_exact(&mut head).unwrap();
+                let mut body = vec![0u8; u16::from_be_bytes([head[3], head[4]]) as usize];
+                s.read_exact(&mut body).unwrap();
+                hellos.push([&head[..], &body].concat());
+                let reply: &[u8] = if i == 0 { &[0x16, 0x03, 0x02, 0x00, 0x2a, 0x02, 0x00, 0x00, 0x26, 0x03, 0x02] } else { &[0x15, 0x03, 0x01, 0x00, 0x02, 0x02, 0x46] };
+                s.write_all(reply).unwrap();
+            }
+            hellos
+        });
+        let url = Url::parse(&format!("https://127.0.0.1:{}/", port)).unwrap();
+        assert_eq!(legacy_accepted(&Network::default(), &url, Duration::from_secs(2)), Ok(true));
+        assert_eq!(legacy_accepted(&Network::default(), &url, Duration::from_secs(2)), Ok(false));
+        let hello = &server.join().unwrap()[0];
+        //client hello offering at most tls 1.1, no sni for an ip
+        assert_eq!((hello[5], hello[9], hello[10]), (0x01, 0x03, 0x02));
+        assert!(!hello.windows(9).any(|w| w == b"127.0.0.1"));
+        assert!(legacy_hello(Some("a.test")).windows(6).any(|w| w == b"a.test"));
+    }
+}
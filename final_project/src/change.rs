@@ -0,0 +1,125 @@
+//--detect-changes: size and hash of each body, and which urls answer with different content than last round
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{memory, WebsiteStatus};
+
+//the whole body of one answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Digest {
+    pub bytes: u64,
+    //fnv-1a 64, stable across runs and builds
+    pub hash: u64,
+}
+
+impl Digest {
+    pub fn hash_hex(&self) -> String {
+        format!("{:016x}", self.hash)
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes, hash {:016x}", self.bytes, self.hash)
+    }
+}
+
+//fed the body chunk by chunk as it is read
+#[derive(Debug, Clone)]
+pub(crate) struct Hasher {
+    bytes: u64,
+    hash: u64,
+}
+
+impl Hasher {
+    pub(crate) fn new() -> Self {
+        Self { bytes: 0, hash: 0xcbf29ce484222325 }
+    }
+
+    pub(crate) fn update(&mut self, chunk: &[u8]) {
+        self.bytes += chunk.len() as u64;
+        for &b in chunk {
+            self.hash ^= b as u64;
+            self.hash = self.hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> Digest {
+        Digest { bytes: self.bytes, hash: self.hash }
+    }
+}
+
+//a url whose content differs from its previous up check
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub url: Arc<str>,
+    pub before: Digest,
+    pub after: Digest,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} -> {} bytes, hash {} -> {})", self.url, self.before.bytes, self.after.bytes, self.before.hash_hex(), self.after.hash_hex())
+    }
+}
+
+//last content seen per url; only up checks count, so an outage page in between is no change
+#[derive(Debug, Default)]
+pub struct ChangeDetector {
+    last: HashMap<Arc<str>, Digest>,
+}
+
+impl ChangeDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn heap_bytes(&self) -> usize {
+        memory::table_bytes(&self.last)
+    }
+
+    //feed one result, returns the change when its content differs from the url's last up check
+    pub fn observe(&mut self, r: &WebsiteStatus) -> Option<Change> {
+        let after = r.content.filter(|_| r.is_up())?;
+        let before = self.last.insert(r.url.clone(), after)?;
+        (before.hash != after.hash).then(|| Change { url: r.url.clone(), before, after })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+    use crate::{CheckError, ErrorKind};
+
+    #[test]
+    fn test_change_detector() {
+        let digest = |body: &[&[u8]]| {
+            let mut h = Hasher::new();
+            body.iter().for_each(|c| h.update(c));
+            h.finish()
+        };
+        //chunking does not change the hash
+        assert_eq!(digest(&[b"hello ", b"world"]), digest(&[b"hello world"]));
+        assert_eq!(digest(&[b""]).hash_hex(), "cbf29ce484222325");
+        assert_eq!(digest(&[b"a"]).hash_hex(), "af63dc4c8601ec8c");
+
+        let check = |body: Option<&[u8]>, up: bool| {
+            let status = if up { Ok(200) } else { Err(CheckError::new(ErrorKind::Transport, "reset")) };
+            let mut r = status_for("https://a.test/", status, 10);
+            r.content = body.map(|b| digest(&[b]));
+            r
+        };
+        let mut d = ChangeDetector::new();
+        assert_eq!(d.observe(&check(Some(b"v1"), true)), None);
+        assert_eq!(d.observe(&check(Some(b"v1"), true)), None);
+        //down in between, compared with the last up answer
+        assert_eq!(d.observe(&check(None, false)), None);
+        assert_eq!(d.observe(&check(Some(b"v1"), true)), None);
+        let change = d.observe(&check(Some(b"defaced!"), true)).unwrap();
+        assert_eq!((change.before.bytes, change.after.bytes), (2, 8));
+        assert_eq!(change.to_string(), format!("https://a.test/ (2 -> 8 bytes, hash {} -> {})", digest(&[b"v1"]).hash_hex(), digest(&[b"defaced!"]).hash_hex()));
+        assert_eq!(d.observe(&check(Some(b"defaced!"), true)), None);
+    }
+}
@@ -0,0 +1,268 @@
+//per-check log lines (jsonl or csv) with batched writes
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use crate::{json, WebsiteStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Jsonl,
+    Csv,
+}
+
+impl LogFormat {
+    //.csv files get csv, everything else jsonl
+    pub fn for_path(path: &str) -> Self {
+        if path.to_ascii_lowercase().ends_with(".csv") { LogFormat::Csv } else { LogFormat::Jsonl }
+    }
+}
+
+//when written data is forced to disk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FsyncPolicy {
+    //leave it to the os
+    Never,
+    //after every batched flush
+    Flush,
+    //after every line, slow but nothing buffered is lost
+    Always,
+}
+
+impl FsyncPolicy {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "never" => Ok(FsyncPolicy::Never),
+            "flush" => Ok(FsyncPolicy::Flush),
+            "always" => Ok(FsyncPolicy::Always),
+            _ => Err(format!("unknown fsync policy '{}', expected never, flush or always", s)),
+        }
+    }
+}
+
+//flush thresholds
+#[derive(Debug, Clone)]
+pub struct LogOptions {
+    pub flush_interval: Duration,
+    pub flush_bytes: usize,
+    pub fsync: FsyncPolicy,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self { flush_interval: Duration::from_secs(1), flush_bytes: 64 * 1024, fsync: FsyncPolicy::Never }
+    }
+}
+
+//append-only file that collects lines and writes them in batches
+#[derive(Debug)]
+pub struct BatchedWriter {
+    file: File,
+    buf: Vec<u8>,
+    opts: LogOptions,
+    last_flush: Instant,
+}
+
+impl BatchedWriter {
+    pub fn open(path: &str, opts: LogOptions) -> io::Result<Self> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        Ok(Self { file, buf: Vec::with_capacity(opts.flush_bytes), opts, last_flush: Instant::now() })
+    }
+
+    //true when nothing has ever been written to the file
+    pub fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.buf.is_empty() && self.file.metadata()?.len() == 0)
+    }
+
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.buf.extend_from_slice(line.as_bytes());
+        self.buf.push(b'\n');
+        if self.opts.fsync == FsyncPolicy::Always || self.buf.len() >= self.opts.flush_bytes {
+            return self.flush();
+        }
+        self.tick()
+    }
+
+    //flush once the interval has passed, call while idle too
+    pub fn tick(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() && self.last_flush.elapsed() >= self.opts.flush_interval {
+            return self.flush();
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        if self.buf.is_empty() { return Ok(()); }
+        self.file.write_all(&self.buf)?;
+        self.buf.clear();
+        if self.opts.fsync != FsyncPolicy::Never {
+            self.file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BatchedWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+//log file plus its line format
+#[derive(Debug)]
+pub struct CheckLog {
+    writer: BatchedWriter,
+    format: LogFormat,
+}
+
+impl CheckLog {
+    //format from the extension, header written for new csv files
+    pub fn open(path: &str, opts: LogOptions) -> io::Result<Self> {
+        Self::open_as(path, LogFormat::for_path(path), opts)
+    }
+
+    pub fn open_as(path: &str, format: LogFormat, opts: LogOptions) -> io::Result<Self> {
+        let mut writer = BatchedWriter::open(path, opts)?;
+        if format == LogFormat::Csv && writer.is_empty()? {
+            writer.write_line(CSV_HEADER)?;
+        }
+        Ok(Self { writer, format })
+    }
+
+    pub fn write(&mut self, results: &[WebsiteStatus]) -> io::Result<()> {
+        for r in results {
+            self.write_one(r)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_one(&mut self, r: &WebsiteStatus) -> io::Result<()> {
+        self.writer.write_line(&record(r, self.format))
+    }
+
+    //a jsonl line that is not a check, like the run manifest; csv logs leave it out
+    pub fn write_extra(&mut self, line: &str) -> io::Result<()> {
+        if self.format == LogFormat::Csv { return Ok(()); }
+        self.writer.write_line(line)
+    }
+
+    pub fn tick(&mut self) -> io::Result<()> {
+        self.writer.tick()
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub const CSV_HEADER: &str = "ts_ms,url,status,error,response_ms,tcp_ms,title";
+
+//one line describing a check
+pub fn record(r: &WebsiteStatus, format: LogFormat) -> String {
+    let ts_ms = r.timestamp.as_system_time().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+    let (status, error) = match &r.status {
+        Ok(code) => (code.to_string(), None),
+        Err(e) => (String::new(), Some(e.to_string())),
+    };
+    let tcp_ms = r.tcp_connect.map(|d| d.as_millis().to_string());
+    match format {
+        LogFormat::Jsonl => format!(
+            "{{\"ts_ms\":{},\"url\":{},\"status\":{},\"error\":{},\"response_ms\":{},\"last_attempt_ms\":{},\"total_ms\":{},\"tcp_ms\":{},\"title\":{},\"http_version\":{},\"final_url\":{},\"redirects\":[{}],\"body_bytes\":{},\"content_hash\":{},\"compression\":{},\"meta\":{}}}",
+            ts_ms,
+            json::string(&r.url),
+            if status.is_empty() { "null".into() } else { status },
+            error.map(|e| json::string(&e)).unwrap_or_else(|| "null".into()),
+            r.response_time.as_millis(),
+            r.last_attempt_time().as_millis(),
+            r.total_time().as_millis(),
+            tcp_ms.unwrap_or_else(|| "null".into()),
+            r.title.as_deref().map(json::string).unwrap_or_else(|| "null".into()),
+            r.http_version.map(|v| json::string(v.as_str())).unwrap_or_else(|| "null".into()),
+            r.final_url.as_deref().map(json::string).unwrap_or_else(|| "null".into()),
+            r.redirects.iter().map(|u| json::string(u)).collect::<Vec<_>>().join(","),
+            r.content.map(|c| c.bytes.to_string()).unwrap_or_else(|| "null".into()),
+            r.content.map(|c| json::string(&c.hash_hex())).unwrap_or_else(|| "null".into()),
+            match &r.compression {
+                Some(Ok(c)) => format!("{{\"encoding\":{},\"wire_bytes\":{},\"identity_bytes\":{}}}",
+                    c.encoding.as_deref().map(json::string).unwrap_or_else(|| "null".into()), c.wire_bytes, c.identity_bytes),
+                _ => "null".into(),
+            },
+            if r.meta.is_empty() { "null".into() } else { json::Value::Object(r.meta.iter().cloned().collect()).to_string() },
+        ),
+        LogFormat::Csv => format!(
+            "{},{},{},{},{},{},{}",
+            ts_ms,
+            csv_field(&r.url),
+            status,
+            csv_field(error.as_deref().unwrap_or("")),
+            r.response_time.as_millis(),
+            tcp_ms.unwrap_or_default(),
+            csv_field(r.title.as_deref().unwrap_or("")),
+        ),
+    }
+}
+
+//quote fields holding separators, quotes or newlines
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+
+    #[test]
+    fn test_batched_writer_thresholds() {
+        let path = std::env::temp_dir().join(format!("sitewatch-log-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+        let opts = LogOptions { flush_interval: Duration::from_secs(3600), flush_bytes: 64, fsync: FsyncPolicy::Flush };
+        let mut w = BatchedWriter::open(&path, opts).unwrap();
+        assert!(w.is_empty().unwrap());
+
+        //below the size threshold nothing reaches the file
+        w.write_line("short").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        w.write_line(&"x".repeat(70)).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        w.write_line("tail").unwrap();
+        drop(w);
+        assert!(std::fs::read_to_string(&path).unwrap().ends_with("tail\n"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_csv_header_once() {
+        let path = std::env::temp_dir().join(format!("sitewatch-csv-{}.log", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+        for _ in 0..2 {
+            let mut log = CheckLog::open_as(&path, LogFormat::Csv, LogOptions::default()).unwrap();
+            log.write(&[status_for("http://a/", Ok(200), 5)]).unwrap();
+        }
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().filter(|l| *l == CSV_HEADER).count(), 1);
+        assert_eq!(text.lines().count(), 3);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_record_formats() {
+        let mut r = status_for("http://a/?x=1,2", Ok(200), 12);
+        r.title = Some("Say \"hi\"".into());
+        assert!(record(&r, LogFormat::Jsonl).contains("\"status\":200,\"error\":null,\"response_ms\":12,\"last_attempt_ms\":12,\"total_ms\":12"));
+        r.meta = vec![("team".into(), json::Value::from("web"))];
+        assert!(record(&r, LogFormat::Jsonl).ends_with(",\"body_bytes\":null,\"content_hash\":null,\"compression\":null,\"meta\":{\"team\":\"web\"}}"));
+        r.content = Some(crate::change::Digest { bytes: 5, hash: 0xab });
+        assert!(record(&r, LogFormat::Jsonl).contains(",\"body_bytes\":5,\"content_hash\":\"00000000000000ab\","));
+        assert!(record(&r, LogFormat::Csv).ends_with(",\"http://a/?x=1,2\",200,,12,,\"Say \"\"hi\"\"\""));
+        assert_eq!(LogFormat::for_path("checks.CSV"), LogFormat::Csv);
+    }
+}
@@ -0,0 +1,109 @@
+//--circuit-breaker: a host that keeps failing at the transport level is skipped for a cooldown,
+//instead of every one of its urls burning the full timeout
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use crate::{duration, memory, ErrorKind, WebsiteStatus};
+
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default)]
+struct Host {
+    //transport failures in a row
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+//shared by workers and rounds
+#[derive(Debug)]
+pub struct Breaker {
+    after: u32,
+    cooldown: Duration,
+    hosts: Mutex<HashMap<String, Host>>,
+}
+
+impl Breaker {
+    pub fn new(after: u32, cooldown: Duration) -> Self {
+        Self { after: after.max(1), cooldown, hosts: Mutex::new(HashMap::new()) }
+    }
+
+    //why the url is not checked now; after the cooldown one check goes through, the rest wait for how it does
+    pub fn skip(&self, url: &str, now: Instant) -> Option<String> {
+        let key = host_key(url)?;
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let host = hosts.get_mut(&key)?;
+        let until = host.open_until?;
+        if now >= until {
+            host.open_until = Some(now + self.cooldown);
+            return None;
+        }
+        Some(format!("skipped (circuit open): {} failed {} times in a row, next try in {}", key, host.failures, duration::format(until - now)))
+    }
+
+    //transport failures count towards opening, any answer closes the circuit again
+    pub fn record(&self, r: &WebsiteStatus, now: Instant) {
+        let Some(key) = host_key(&r.url) else { return };
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        match &r.status {
+            Err(e) if e.kind == ErrorKind::Transport => {
+                let host = hosts.entry(key).or_default();
+                host.failures += 1;
+                if host.failures >= self.after { host.open_until = Some(now + self.cooldown); }
+            }
+            Err(e) if e.kind == ErrorKind::Circuit => {}
+            _ => { hosts.remove(&key); }
+        }
+    }
+
+    pub fn heap_bytes(&self) -> usize {
+        let hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        memory::table_bytes(&hosts) + hosts.keys().map(String::capacity).sum::<usize>()
+    }
+}
+
+//host:port, so two services on one machine trip separately
+pub(crate) fn host_key(url: &str) -> Option<String> {
+    let u = Url::parse(url).ok()?;
+    let host = u.host_str()?;
+    Some(match u.port_or_known_default() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+    use crate::CheckError;
+
+    #[test]
+    fn test_breaker() {
+        let b = Breaker::new(3, Duration::from_secs(30));
+        let t = Instant::now();
+        let refused = |url: &str| status_for(url, Err(CheckError::new(ErrorKind::Transport, "connection refused")), 1);
+        for i in 0..2 { b.record(&refused(&format!("https://dead.test/{}", i)), t); }
+        assert_eq!(b.skip("https://dead.test/x", t), None);
+        b.record(&refused("https://dead.test/2"), t);
+        assert_eq!(b.skip("https://dead.test/x", t + Duration::from_secs(5)).unwrap(), "skipped (circuit open): dead.test:443 failed 3 times in a row, next try in 25s");
+        //other hosts, and other ports of the same host, are not affected
+        assert_eq!(b.skip("http://dead.test/x", t), None);
+        assert_eq!(b.skip("https://alive.test/", t), None);
+
+        //after the cooldown a single probe goes through
+        let later = t + Duration::from_secs(30);
+        assert_eq!(b.skip("https://dead.test/a", later), None);
+        assert!(b.skip("https://dead.test/b", later).is_some());
+        b.record(&refused("https://dead.test/a"), later);
+        assert!(b.skip("https://dead.test/b", later + Duration::from_secs(29)).unwrap().contains("failed 4 times"));
+        //an answer, even a 500, closes it
+        let again = later + Duration::from_secs(30);
+        assert_eq!(b.skip("https://dead.test/a", again), None);
+        b.record(&status_for("https://dead.test/a", Ok(500), 1), again);
+        assert_eq!(b.skip("https://dead.test/b", again), None);
+        assert!(b.heap_bytes() > 0);
+    }
+}
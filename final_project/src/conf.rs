@@ -0,0 +1,377 @@
+//config files in a toml subset, turned into the equivalent command-line flags
+//supported: comments, [tables] (dotted names too), bare or quoted keys, strings, integers, floats, booleans and arrays
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(s) => f.write_str(s),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Array(items) => {
+                let parts: Vec<String> = items.iter().map(Value::to_string).collect();
+                write!(f, "{}", parts.join(","))
+            }
+        }
+    }
+}
+
+//(table, key, value) in file order; top-level keys have an empty table
+pub type Entry = (String, String, Value);
+
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn err(&self, msg: impl fmt::Display) -> String {
+        format!("line {}: {}", self.line, msg)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == b'\n' { self.line += 1; }
+        Some(c)
+    }
+
+    //spaces and tabs only
+    fn skip_blank(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) { self.pos += 1; }
+    }
+
+    //blank, newlines and comments, inside arrays
+    fn skip_ws(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\r' | b'\n') => { self.bump(); }
+                Some(b'#') => while !matches!(self.peek(), None | Some(b'\n')) { self.pos += 1; },
+                _ => return,
+            }
+        }
+    }
+
+    //rest of the line may hold only a comment
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_blank();
+        match self.peek() {
+            None | Some(b'\n') | Some(b'#') | Some(b'\r') => {
+                while !matches!(self.peek(), None | Some(b'\n')) { self.pos += 1; }
+                self.bump();
+                Ok(())
+            }
+            Some(c) => Err(self.err(format!("unexpected '{}' after value", c as char))),
+        }
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(b'"') => self.basic_string(),
+            Some(b'\'') => self.literal_string(),
+            _ => {
+                let start = self.pos;
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == b'_' || c == b'-') { self.pos += 1; }
+                if start == self.pos { return Err(self.err("expected a key")); }
+                Ok(String::from_utf8_lossy(&self.s[start..self.pos]).into_owned())
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.bump();
+        let mut out = Vec::new();
+        loop {
+            match self.bump() {
+                None | Some(b'\n') => return Err(self.err("unterminated string")),
+                Some(b'"') => return Ok(String::from_utf8_lossy(&out).into_owned()),
+                Some(b'\\') => match self.bump() {
+                    Some(b'n') => out.push(b'\n'),
+                    Some(b't') => out.push(b'\t'),
+                    Some(b'r') => out.push(b'\r'),
+                    Some(b'"') => out.push(b'"'),
+                    Some(b'\\') => out.push(b'\\'),
+                    Some(b'u') => {
+                        let hex = self.s.get(self.pos..self.pos + 4).ok_or_else(|| self.err("short \\u escape"))?;
+                        let code = u32::from_str_radix(&String::from_utf8_lossy(hex), 16).map_err(|_| self.err("bad \\u escape"))?;
+                        let c = char::from_u32(code).ok_or_else(|| self.err("bad \\u escape"))?;
+                        out.extend_from_slice(c.to_string().as_bytes());
+                        self.pos += 4;
+                    }
+                    _ => return Err(self.err("unknown escape")),
+                },
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.bump();
+        let start = self.pos;
+        loop {
+            match self.bump() {
+                None | Some(b'\n') => return Err(self.err("unterminated string")),
+                Some(b'\'') => return Ok(String::from_utf8_lossy(&self.s[start..self.pos - 1]).into_owned()),
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some(b'"') => self.basic_string().map(Value::Str),
+            Some(b'\'') => self.literal_string().map(Value::Str),
+            Some(b'[') => {
+                self.bump();
+                let mut items = Vec::new();
+                loop {
+                    self.skip_ws();
+                    if self.peek() == Some(b']') { self.bump(); return Ok(Value::Array(items)); }
+                    items.push(self.value()?);
+                    self.skip_ws();
+                    match self.bump() {
+                        Some(b',') => {}
+                        Some(b']') => return Ok(Value::Array(items)),
+                        _ => return Err(self.err("expected ',' or ']' in array")),
+                    }
+                }
+            }
+            _ => {
+                let start = self.pos;
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b'+' | b'.')) { self.pos += 1; }
+                let word = String::from_utf8_lossy(&self.s[start..self.pos]).replace('_', "");
+                match word.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "" => Err(self.err("expected a value")),
+                    w => w.parse().map(Value::Int)
+                        .or_else(|_| w.parse().map(Value::Float))
+                        .map_err(|_| self.err(format!("invalid value '{}'", w))),
+                }
+            }
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Vec<Entry>, String> {
+    let mut p = Parser { s: text.as_bytes(), pos: 0, line: 1 };
+    let mut table = String::new();
+    let mut entries: Vec<Entry> = Vec::new();
+    loop {
+        p.skip_ws();
+        match p.peek() {
+            None => return Ok(entries),
+            Some(b'[') => {
+                p.bump();
+                p.skip_blank();
+                table = p.key()?;
+                p.skip_blank();
+                while p.peek() == Some(b'.') {
+                    p.bump();
+                    p.skip_blank();
+                    table = format!("{}.{}", table, p.key()?);
+                    p.skip_blank();
+                }
+                if p.bump() != Some(b']') { return Err(p.err("expected ']' after table name")); }
+                p.end_of_line()?;
+            }
+            Some(_) => {
+                let line = p.line;
+                let key = p.key()?;
+                p.skip_blank();
+                if p.bump() != Some(b'=') { return Err(p.err(format!("expected '=' after '{}'", key))); }
+                p.skip_blank();
+                let value = p.value()?;
+                p.end_of_line()?;
+                if entries.iter().any(|(t, k, _)| *t == table && *k == key) {
+                    return Err(format!("line {}: duplicate key '{}'", line, key));
+                }
+                entries.push((table.clone(), key, value));
+            }
+        }
+    }
+}
+
+//entries of [profile.NAME] and [profile.NAME.TABLE], as if they were top-level
+fn profile_entries<'a>(entries: &'a [Entry], name: &str) -> impl Iterator<Item = Entry> + 'a {
+    let prefix = format!("profile.{}", name);
+    entries.iter().filter_map(move |(t, k, v)| {
+        let rest = t.strip_prefix(&prefix)?;
+        let table = if rest.is_empty() { "" } else { rest.strip_prefix('.')? };
+        Some((table.to_string(), k.clone(), v.clone()))
+    })
+}
+
+//the entries outside any profile, overlaid with the chosen profile; a profile can build on
+//another one with inherits = "NAME", keys it sets replace the inherited ones
+pub fn select_profile(entries: &[Entry], profile: Option<&str>) -> Result<Vec<Entry>, String> {
+    let mut out: Vec<Entry> = entries.iter().filter(|(t, _, _)| t != "profile" && !t.starts_with("profile.")).cloned().collect();
+    let Some(name) = profile else { return Ok(out) };
+    let mut chain = vec![name.to_string()];
+    loop {
+        let current = &chain[chain.len() - 1];
+        let parent = profile_entries(entries, current).find(|(t, k, _)| t.is_empty() && k == "inherits");
+        let Some((_, _, parent)) = parent else { break };
+        let parent = parent.to_string();
+        if chain.contains(&parent) { return Err(format!("profile inheritance loop: {} -> {}", chain.join(" -> "), parent)); }
+        chain.push(parent);
+    }
+    for name in chain.iter().rev() {
+        let own: Vec<Entry> = profile_entries(entries, name).collect();
+        if own.is_empty() && !entries.iter().any(|(t, _, _)| *t == format!("profile.{}", name)) {
+            let mut known: Vec<&str> = entries.iter().filter_map(|(t, _, _)| t.strip_prefix("profile.")?.split('.').next()).collect();
+            known.dedup();
+            return Err(format!("unknown profile '{}' (defined: {})", name, if known.is_empty() { "none".to_string() } else { known.join(", ") }));
+        }
+        for (t, k, v) in own {
+            if t.is_empty() && k == "inherits" { continue; }
+            match out.iter_mut().find(|(ot, ok, _)| *ot == t && *ok == k) {
+                Some(slot) => slot.2 = v,
+                None => out.push((t, k, v)),
+            }
+        }
+    }
+    Ok(out)
+}
+
+//tables whose entries become one KEY=VALUE flag each
+const KV_TABLES: [(&str, &str); 4] = [("headers", "--header"), ("send_headers", "--send-header"), ("weights", "--weight"), ("expect_status", "--expect-status")];
+
+//tables for the flags taking a value and a url: "URL" = "VALUE" is --flag VALUE URL
+const URL_TABLES: [(&str, &str); 2] = [("cron", "--cron"), ("at", "--at")];
+
+//flags for parse_args: key_name = v is --key-name v, true adds a bare switch, arrays repeat the flag,
+//urls = [...] are plain arguments
+pub fn to_args(entries: &[Entry]) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    for (table, key, value) in entries {
+        if let Some((_, flag)) = URL_TABLES.iter().find(|(t, _)| t == table) {
+            args.extend([flag.to_string(), value.to_string(), key.clone()]);
+            continue;
+        }
+        if table.is_empty() && let Some((t, _)) = URL_TABLES.iter().find(|(t, _)| t == key) {
+            return Err(format!("{} needs a url too: put \"URL\" = {} under a [{}] table", key, value, t));
+        }
+        if !table.is_empty() {
+            let flag = KV_TABLES.iter().find(|(t, _)| t == table).map(|(_, f)| *f)
+                .ok_or_else(|| format!("unknown table [{}]", table))?;
+            args.push(flag.to_string());
+            args.push(format!("{}={}", key, value));
+            continue;
+        }
+        if key == "urls" {
+            match value {
+                Value::Array(items) => args.extend(items.iter().map(Value::to_string)),
+                other => args.push(other.to_string()),
+            }
+            continue;
+        }
+        let flag = format!("--{}", key.replace('_', "-"));
+        match value {
+            Value::Bool(true) => args.push(flag),
+            Value::Bool(false) => {}
+            Value::Array(items) => {
+                for item in items {
+                    args.push(flag.clone());
+                    args.push(item.to_string());
+                }
+            }
+            v => {
+                args.push(flag);
+                args.push(v.to_string());
+            }
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_to_args() {
+        let text = r#"
+# fleet checks
+workers = 20
+timeout_ms = 2_500
+titles = true
+tcp_latency = false
+urls = [
+    "https://a.test/",   # primary
+    'https://b.test/{1..2}',
+]
+alert_webhook = ["https://hook.test/1", "https://hook.test/2"]
+
+[headers]
+Content-Type = "text/html"
+
+[weights]
+"https://a.test/" = 2.5
+"#;
+        let args = to_args(&parse(text).unwrap()).unwrap();
+        assert_eq!(args, [
+            "--workers", "20", "--timeout-ms", "2500", "--titles",
+            "https://a.test/", "https://b.test/{1..2}",
+            "--alert-webhook", "https://hook.test/1", "--alert-webhook", "https://hook.test/2",
+            "--header", "Content-Type=text/html", "--weight", "https://a.test/=2.5",
+        ]);
+
+        assert_eq!(parse("a = \"x\\ty\\u00e9\"").unwrap()[0].2, Value::Str("x\ty\u{e9}".into()));
+        assert!(parse("a = 1\na = 2").unwrap_err().contains("line 2: duplicate"));
+        assert!(parse("a = [1, 2").is_err());
+        assert!(parse("a = 1 b").unwrap_err().starts_with("line 1:"));
+        assert!(to_args(&parse("[nope]\nx = 1").unwrap()).is_err());
+
+        //a value and a url per entry
+        let args = to_args(&parse("[cron]\n\"https://a.test/\" = \"*/5 * * * *\"\n[at]\n\"https://b.test/\" = \"2030-01-01T09:00:00Z\"").unwrap()).unwrap();
+        assert_eq!(args, ["--cron", "*/5 * * * *", "https://a.test/", "--at", "2030-01-01T09:00:00Z", "https://b.test/"]);
+        assert_eq!(to_args(&parse("cron = \"0 * * * *\"").unwrap()).unwrap_err(), "cron needs a url too: put \"URL\" = 0 * * * * under a [cron] table");
+    }
+
+    #[test]
+    fn test_profiles() {
+        let text = r#"
+workers = 5
+urls = ["https://example.test/"]
+[headers]
+X-Env = "base"
+
+[profile.staging]
+urls = ["https://staging.example.test/"]
+[profile.staging.headers]
+X-Env = "staging"
+
+[profile.staging-eu]
+inherits = "staging"
+workers = 2
+
+[profile.loop-a]
+inherits = "loop-b"
+[profile.loop-b]
+inherits = "loop-a"
+"#;
+        let entries = parse(text).unwrap();
+        let args = |p| to_args(&select_profile(&entries, p).unwrap()).unwrap();
+        assert_eq!(args(None), ["--workers", "5", "https://example.test/", "--header", "X-Env=base"]);
+        assert_eq!(args(Some("staging")), ["--workers", "5", "https://staging.example.test/", "--header", "X-Env=staging"]);
+        assert_eq!(args(Some("staging-eu")), ["--workers", "2", "https://staging.example.test/", "--header", "X-Env=staging"]);
+        assert!(select_profile(&entries, Some("prod")).unwrap_err().contains("defined: staging, staging-eu, loop-a, loop-b"));
+        assert!(select_profile(&entries, Some("loop-a")).unwrap_err().starts_with("profile inheritance loop"));
+    }
+}
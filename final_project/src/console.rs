@@ -0,0 +1,79 @@
+//runtime commands read from stdin during periodic monitoring
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use sitewatch::alerts::Silences;
+use sitewatch::duration;
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Stop,
+    Ack(String),
+    Snooze(String, Duration),
+    Clear(String),
+    Unknown(String),
+}
+
+pub const HELP: &str = "commands: ack <url> | snooze <url> <minutes|duration> | clear <url> | ENTER to stop";
+
+//one console line to a command
+pub fn parse_command(line: &str) -> Command {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    match parts.as_slice() {
+        [] | ["quit"] | ["q"] => Command::Stop,
+        ["ack", url] => Command::Ack(url.to_string()),
+        ["snooze", url, dur] => match duration::parse(dur, Duration::from_secs(60)) {
+            Ok(d) => Command::Snooze(url.to_string(), d),
+            Err(_) => Command::Unknown(line.trim().to_string()),
+        },
+        ["clear", url] => Command::Clear(url.to_string()),
+        _ => Command::Unknown(line.trim().to_string()),
+    }
+}
+
+//stdin reader, sets shutdown on ENTER or end of input
+pub fn spawn(shutdown: Arc<AtomicBool>, silences: Arc<Mutex<Silences>>) {
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let line = match line { Ok(l) => l, Err(_) => break };
+            match parse_command(&line) {
+                Command::Stop => break,
+                Command::Ack(url) => {
+                    silences.lock().unwrap().ack(&url);
+                    println!("acknowledged {} (muted until it recovers)", url);
+                }
+                Command::Snooze(url, d) => {
+                    silences.lock().unwrap().snooze(&url, d);
+                    println!("snoozed {} for {}", url, duration::format(d));
+                }
+                Command::Clear(url) => {
+                    if silences.lock().unwrap().clear(&url) {
+                        println!("alerts re-enabled for {}", url);
+                    } else {
+                        println!("{} was not muted", url);
+                    }
+                }
+                Command::Unknown(s) => eprintln!("unknown command '{}'; {}", s, HELP),
+            }
+        }
+        shutdown.store(true, Ordering::Relaxed);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command(""), Command::Stop);
+        assert_eq!(parse_command("ack https://a"), Command::Ack("https://a".into()));
+        assert_eq!(parse_command("snooze https://a 5"), Command::Snooze("https://a".into(), Duration::from_secs(300)));
+        assert_eq!(parse_command("snooze https://a 2h"), Command::Snooze("https://a".into(), Duration::from_secs(7200)));
+        assert!(matches!(parse_command("snooze https://a soon"), Command::Unknown(_)));
+        assert_eq!(parse_command(" clear x "), Command::Clear("x".into()));
+    }
+}
@@ -0,0 +1,84 @@
+//cors_origin=: the OPTIONS preflight a browser sends before a cross-origin request, and whether its answer lets the request through
+use std::time::Duration;
+
+use crate::{rawhttp, HttpVersion, Network};
+
+//methods a browser never preflights, allowed whatever Access-Control-Allow-Methods says
+const SAFELISTED: [&str; 3] = ["GET", "HEAD", "POST"];
+
+//what the page asks for
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preflight {
+    pub origin: String,
+    pub method: String,
+    //Access-Control-Request-Headers, empty sends none
+    pub headers: Vec<String>,
+}
+
+impl Default for Preflight {
+    fn default() -> Self {
+        Self { origin: String::new(), method: "GET".into(), headers: Vec::new() }
+    }
+}
+
+impl Preflight {
+    //sends the preflight, Err says what a browser would have refused
+    pub fn check(&self, net: &Network, url: &str, extra_headers: &[(String, String)], timeout: Duration) -> Result<(), String> {
+        let requested = self.headers.join(", ");
+        let mut headers: Vec<(&str, &str)> = extra_headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        headers.push(("Origin", &self.origin));
+        headers.push(("Access-Control-Request-Method", &self.method));
+        if !requested.is_empty() { headers.push(("Access-Control-Request-Headers", &requested)); }
+        let resp = rawhttp::request(net, "OPTIONS", url, &headers, &[], timeout, 0, HttpVersion::Http11)
+            .map_err(|e| format!("cors preflight failed: {}", e))?;
+        self.validate(&resp).map_err(|e| format!("cors preflight from {}: {}", self.origin, e))
+    }
+
+    pub(crate) fn validate(&self, resp: &rawhttp::RawResponse) -> Result<(), String> {
+        if !(200..300).contains(&resp.status) { return Err(format!("answered {}", resp.status)); }
+        let origin = resp.header("Access-Control-Allow-Origin").map(str::trim).ok_or("no Access-Control-Allow-Origin")?;
+        if origin != "*" && origin != self.origin { return Err(format!("Access-Control-Allow-Origin is {}", origin)); }
+        let methods = list(resp.header("Access-Control-Allow-Methods"));
+        let method_ok = SAFELISTED.contains(&self.method.as_str()) || methods.iter().any(|m| *m == "*" || *m == self.method);
+        if !method_ok { return Err(format!("{} not in Access-Control-Allow-Methods ({})", self.method, methods.join(", "))); }
+        let allowed = list(resp.header("Access-Control-Allow-Headers"));
+        //the wildcard never covers authorization
+        let missing: Vec<&str> = self.headers.iter().map(String::as_str)
+            .filter(|h| !allowed.iter().any(|a| a.eq_ignore_ascii_case(h) || (*a == "*" && !h.eq_ignore_ascii_case("authorization"))))
+            .collect();
+        if !missing.is_empty() { return Err(format!("{} not in Access-Control-Allow-Headers", missing.join(", "))); }
+        Ok(())
+    }
+}
+
+fn list(value: Option<&str>) -> Vec<&str> {
+    value.unwrap_or("").split(',').map(str::trim).filter(|s| !s.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preflight_validate() {
+        let resp = |status: u16, headers: &[(&str, &str)]| rawhttp::RawResponse {
+            status,
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            body: Vec::new(),
+        };
+        let put = Preflight { origin: "https://app.test".into(), method: "PUT".into(), headers: vec!["Content-Type".into(), "Authorization".into()] };
+        let ok = resp(204, &[("access-control-allow-origin", "https://app.test"), ("Access-Control-Allow-Methods", "GET, PUT"), ("Access-Control-Allow-Headers", "content-type,authorization")]);
+        assert_eq!(put.validate(&ok), Ok(()));
+        assert_eq!(put.validate(&resp(405, &[])), Err("answered 405".into()));
+        assert_eq!(put.validate(&resp(200, &[])), Err("no Access-Control-Allow-Origin".into()));
+        assert_eq!(put.validate(&resp(200, &[("Access-Control-Allow-Origin", "https://other.test")])), Err("Access-Control-Allow-Origin is https://other.test".into()));
+        assert_eq!(put.validate(&resp(200, &[("Access-Control-Allow-Origin", "*"), ("Access-Control-Allow-Methods", "GET, POST")])),
+            Err("PUT not in Access-Control-Allow-Methods (GET, POST)".into()));
+        //* covers content-type but not authorization
+        assert_eq!(put.validate(&resp(200, &[("Access-Control-Allow-Origin", "*"), ("Access-Control-Allow-Methods", "*"), ("Access-Control-Allow-Headers", "*")])),
+            Err("Authorization not in Access-Control-Allow-Headers".into()));
+        //a safelisted method needs no Allow-Methods
+        let get = Preflight { origin: "https://app.test".into(), ..Preflight::default() };
+        assert_eq!(get.validate(&resp(200, &[("Access-Control-Allow-Origin", "*")])), Ok(()));
+    }
+}
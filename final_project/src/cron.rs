@@ -0,0 +1,161 @@
+//five-field cron expressions (minute hour day-of-month month day-of-week), evaluated in utc
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::scheduler::{civil_from_days, days_from_civil};
+
+const MONTHS: [&str; 12] = ["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+const DAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronExpr {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    //classic cron ors day-of-month and day-of-week when both are restricted
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronExpr {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("cron '{}' must have 5 fields (min hour dom month dow)", expr));
+        }
+        let field = |i: usize, min: u32, max: u32, names: &[&str], base: u32| {
+            parse_field(fields[i], min, max, names, base).map_err(|e| format!("cron '{}': {}", expr, e))
+        };
+        let mut weekdays = field(4, 0, 7, &DAYS, 0)?;
+        //7 is sunday too
+        if weekdays & (1 << 7) != 0 { weekdays = (weekdays & !(1 << 7)) | 1; }
+        Ok(Self {
+            source: expr.trim().to_string(),
+            minutes: field(0, 0, 59, &[], 0)?,
+            hours: field(1, 0, 23, &[], 0)?,
+            days: field(2, 1, 31, &[], 0)?,
+            months: field(3, 1, 12, &MONTHS, 1)?,
+            weekdays,
+            days_restricted: fields[2] != "*",
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    fn day_matches(&self, day: u32, weekday: u32) -> bool {
+        let dom = self.days & (1 << day) != 0;
+        let dow = self.weekdays & (1 << weekday) != 0;
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+
+    //first matching minute strictly after `after`
+    pub fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        let secs = after.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let mut t = (secs / 60 + 1) * 60;
+        //jumps are at least a minute, enough for several years of search
+        for _ in 0..200_000 {
+            let days = (t / 86_400) as i64;
+            let (year, month, day) = civil_from_days(days);
+            if self.months & (1 << month) == 0 {
+                let (ny, nm) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+                t = days_from_civil(ny, nm, 1) as u64 * 86_400;
+                continue;
+            }
+            let weekday = ((days + 4).rem_euclid(7)) as u32;
+            if !self.day_matches(day, weekday) {
+                t = (days as u64 + 1) * 86_400;
+                continue;
+            }
+            let hour = ((t % 86_400) / 3600) as u32;
+            if self.hours & (1 << hour) == 0 {
+                t = (t / 3600 + 1) * 3600;
+                continue;
+            }
+            let minute = ((t % 3600) / 60) as u32;
+            if self.minutes & (1 << minute) == 0 {
+                t += 60;
+                continue;
+            }
+            return Some(UNIX_EPOCH + Duration::from_secs(t));
+        }
+        None
+    }
+}
+
+//one comma list: *, */n, a, a-b, a-b/n, names allowed
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], base: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => (r, s.parse::<u32>().map_err(|_| format!("bad step in '{}'", part))?),
+            None => (part, 1),
+        };
+        if step == 0 { return Err(format!("zero step in '{}'", part)); }
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (value(a, names, base)?, value(b, names, base)?)
+        } else {
+            let v = value(range, names, base)?;
+            //a/n means a through the end
+            (v, if part.contains('/') { max } else { v })
+        };
+        if lo < min || hi > max || lo > hi {
+            return Err(format!("'{}' out of range {}-{}", part, min, max));
+        }
+        let mut v = lo;
+        while v <= hi {
+            mask |= 1 << v;
+            v += step;
+        }
+    }
+    Ok(mask)
+}
+
+fn value(s: &str, names: &[&str], base: u32) -> Result<u32, String> {
+    if let Ok(v) = s.parse::<u32>() { return Ok(v); }
+    let upper = s.to_ascii_uppercase();
+    names.iter()
+        .position(|n| *n == upper)
+        .map(|i| i as u32 + base)
+        .ok_or_else(|| format!("unknown value '{}'", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::parse_timestamp;
+
+    #[test]
+    fn test_cron_next_after() {
+        let c = CronExpr::parse("*/5 9-17 * * MON-FRI").unwrap();
+        //saturday 2024-07-06 rolls to monday 09:00
+        let next = c.next_after(parse_timestamp("2024-07-06T12:00Z").unwrap()).unwrap();
+        assert_eq!(next, parse_timestamp("2024-07-08T09:00Z").unwrap());
+        let next = c.next_after(parse_timestamp("2024-07-08T09:03:10Z").unwrap()).unwrap();
+        assert_eq!(next, parse_timestamp("2024-07-08T09:05Z").unwrap());
+
+        let c = CronExpr::parse("0 0 29 FEB *").unwrap();
+        let next = c.next_after(parse_timestamp("2024-03-01T00:00Z").unwrap()).unwrap();
+        assert_eq!(next, parse_timestamp("2028-02-29T00:00Z").unwrap());
+    }
+
+    #[test]
+    fn test_cron_parse_errors() {
+        assert!(CronExpr::parse("* * * *").is_err());
+        assert!(CronExpr::parse("60 * * * *").is_err());
+        assert!(CronExpr::parse("*/0 * * * *").is_err());
+        assert!(CronExpr::parse("0 0 * * FUNDAY").is_err());
+        assert!(CronExpr::parse("0 0 * * 7").is_ok());
+    }
+}
@@ -0,0 +1,203 @@
+//sqlite history of checks and rounds, written through the sqlite3 command-line shell
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::WebsiteStatus;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS rounds (
+    id INTEGER PRIMARY KEY,
+    ts_ms INTEGER NOT NULL,
+    total INTEGER NOT NULL,
+    up INTEGER NOT NULL,
+    avg_ms INTEGER NOT NULL,
+    uptime_pct REAL NOT NULL,
+    ignored INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS rounds_ts ON rounds(ts_ms);
+CREATE TABLE IF NOT EXISTS checks (
+    id INTEGER PRIMARY KEY,
+    round_id INTEGER NOT NULL REFERENCES rounds(id),
+    ts_ms INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    up INTEGER NOT NULL,
+    status INTEGER,
+    error TEXT,
+    response_ms INTEGER NOT NULL,
+    tcp_ms INTEGER,
+    title TEXT,
+    ignored INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS checks_url_ts ON checks(url, ts_ms);
+CREATE INDEX IF NOT EXISTS checks_ts ON checks(ts_ms);
+";
+
+//database file; every call is one sqlite3 process and one transaction
+#[derive(Debug, Clone)]
+pub struct Db {
+    path: String,
+}
+
+fn text(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn opt<T: ToString>(v: Option<T>) -> String {
+    v.map(|v| v.to_string()).unwrap_or_else(|| "NULL".into())
+}
+
+impl Db {
+    //a database file as it is, for callers bringing their own tables
+    pub fn at(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+
+    //creates tables and indices if needed; fails when sqlite3 is not installed
+    pub fn open(path: &str) -> Result<Self, String> {
+        let db = Self::at(path);
+        db.exec(SCHEMA)?;
+        //databases from before --ignore-status was recorded
+        for table in ["rounds", "checks"] {
+            let columns = db.query(&format!("SELECT name FROM pragma_table_info('{}');", table))?;
+            if !columns.iter().any(|c| c[0] == "ignored") {
+                db.exec(&format!("ALTER TABLE {} ADD COLUMN ignored INTEGER NOT NULL DEFAULT 0;", table))?;
+            }
+        }
+        Ok(db)
+    }
+
+    fn sqlite(&self, args: &[&str], script: &str) -> Result<String, String> {
+        let mut child = Command::new("sqlite3")
+            .args(["-batch", "-bail"])
+            .args(args)
+            .arg(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("cannot run sqlite3: {}", e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(script.as_bytes()).map_err(|e| format!("sqlite3 input failed: {}", e))?;
+        }
+        let out = child.wait_with_output().map_err(|e| format!("sqlite3 failed: {}", e))?;
+        if !out.status.success() {
+            return Err(format!("sqlite3: {}", String::from_utf8_lossy(&out.stderr).trim()));
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+    }
+
+    pub fn exec(&self, script: &str) -> Result<(), String> {
+        self.sqlite(&[], script).map(|_| ())
+    }
+
+    //rows of a select, fields as text (NULL is empty)
+    pub fn query(&self, sql: &str) -> Result<Vec<Vec<String>>, String> {
+        //ascii mode separates with unit/record separators, safe for any text
+        let out = self.sqlite(&["-ascii", "-noheader"], sql)?;
+        Ok(out.split('\u{1e}').filter(|r| !r.is_empty()).map(|r| r.split('\u{1f}').map(str::to_string).collect()).collect())
+    }
+
+    //one round row plus a row per check; --ignore-status checks are neither up nor down
+    pub fn record_round(&self, results: &[WebsiteStatus]) -> Result<(), String> {
+        let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let ignored = results.iter().filter(|r| r.ignored).count();
+        let counted = results.len() - ignored;
+        let up = results.iter().filter(|r| r.is_up() && !r.ignored).count();
+        let total_ms: u128 = results.iter().map(|r| r.response_time.as_millis()).sum();
+        let avg_ms = if results.is_empty() { 0 } else { total_ms / results.len() as u128 };
+        let uptime = if counted == 0 { 0.0 } else { up as f64 * 100.0 / counted as f64 };
+
+        let mut sql = String::from("BEGIN;\n");
+        sql.push_str(&format!(
+            "INSERT INTO rounds(ts_ms, total, up, avg_ms, uptime_pct, ignored) VALUES({}, {}, {}, {}, {:.4}, {});\n",
+            now_ms, results.len(), up, avg_ms, uptime, ignored,
+        ));
+        for r in results {
+            let ts_ms = r.timestamp.as_system_time().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+            sql.push_str(&format!(
+                "INSERT INTO checks(round_id, ts_ms, url, up, status, error, response_ms, tcp_ms, title, ignored) VALUES((SELECT max(id) FROM rounds), {}, {}, {}, {}, {}, {}, {}, {}, {});\n",
+                ts_ms,
+                text(&r.url),
+                r.is_up() as u8,
+                opt(r.status.as_ref().ok()),
+                opt(r.status.as_ref().err().map(|e| text(&e.message))),
+                r.response_time.as_millis(),
+                opt(r.tcp_connect.map(|d| d.as_millis())),
+                opt(r.title.as_deref().map(text)),
+                r.ignored as u8,
+            ));
+        }
+        sql.push_str("COMMIT;\n");
+        self.exec(&sql)
+    }
+
+    //(url, samples, uptime %) per url for checks in [from_ms, to_ms), ignored checks left out
+    pub fn uptime_between(&self, from_ms: u128, to_ms: u128) -> Result<Vec<(String, u64, f64)>, String> {
+        let rows = self.query(&format!(
+            "SELECT url, count(*), 100.0 * sum(up) / count(*) FROM checks WHERE ts_ms >= {} AND ts_ms < {} AND ignored = 0 GROUP BY url ORDER BY url;",
+            from_ms, to_ms,
+        ))?;
+        rows.into_iter().map(|r| match r.as_slice() {
+            [url, n, pct] => Ok((url.clone(), n.parse().map_err(|_| "bad count")?, pct.parse().map_err(|_| "bad uptime")?)),
+            _ => Err(format!("unexpected row {:?}", r)),
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+    use crate::{CheckError, ErrorKind};
+
+    #[test]
+    fn test_record_and_query() {
+        //sqlite3 is an optional runtime tool, skip where it is missing
+        if Command::new("sqlite3").arg("-version").output().is_err() { return; }
+        let path = std::env::temp_dir().join(format!("sitewatch-{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+
+        let db = Db::open(&path).unwrap();
+        let mut page = status_for("http://a/", Ok(200), 10);
+        page.title = Some("it's | ok".into());
+        let down = status_for("http://b/", Err(CheckError::new(ErrorKind::Transport, "refused")), 3);
+        db.record_round(&[page.clone(), down.clone()]).unwrap();
+        db.record_round(&[page, status_for("http://b/", Ok(200), 4)]).unwrap();
+        //reopening keeps the data
+        let db = Db::open(&path).unwrap();
+
+        let uptime = db.uptime_between(0, u128::MAX >> 64).unwrap();
+        assert_eq!(uptime, vec![("http://a/".to_string(), 2, 100.0), ("http://b/".to_string(), 2, 50.0)]);
+        let rounds = db.query("SELECT count(*), sum(total) FROM rounds;").unwrap();
+        assert_eq!(rounds, vec![vec!["2".to_string(), "4".to_string()]]);
+        let title = db.query("SELECT title FROM checks WHERE title IS NOT NULL LIMIT 1;").unwrap();
+        assert_eq!(title[0][0], "it's | ok");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_ignored_checks() {
+        if Command::new("sqlite3").arg("-version").output().is_err() { return; }
+        let path = std::env::temp_dir().join(format!("sitewatch-ignored-{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+        //a database from before the ignored columns gets them on open
+        Db { path: path.clone() }.exec("CREATE TABLE rounds (id INTEGER PRIMARY KEY, ts_ms INTEGER NOT NULL, total INTEGER NOT NULL, up INTEGER NOT NULL, avg_ms INTEGER NOT NULL, uptime_pct REAL NOT NULL);").unwrap();
+
+        let db = Db::open(&path).unwrap();
+        let auth_wall = WebsiteStatus { ignored: true, ..status_for("http://a/", Ok(401), 5) };
+        let down = status_for("http://b/", Err(CheckError::new(ErrorKind::Transport, "refused")), 3);
+        db.record_round(&[auth_wall, down, status_for("http://c/", Ok(200), 4)]).unwrap();
+
+        let round = db.query("SELECT total, up, ignored, uptime_pct FROM rounds;").unwrap();
+        assert_eq!(round, vec![vec!["3".to_string(), "1".to_string(), "1".to_string(), "50.0".to_string()]]);
+        let checks = db.query("SELECT url, ignored FROM checks ORDER BY url;").unwrap();
+        assert_eq!(checks[0], ["http://a/", "1"]);
+        assert_eq!(checks[1], ["http://b/", "0"]);
+        let uptime = db.uptime_between(0, u128::MAX >> 64).unwrap();
+        assert_eq!(uptime.iter().map(|(u, _, _)| u.as_str()).collect::<Vec<_>>(), ["http://b/", "http://c/"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+//durations as flags and url options take them: "250ms", "2s", "5m", "24h", "7d", or parts like "1h30m"
+use std::time::Duration;
+
+const UNITS: [(&str, u64); 6] = [("ms", 1), ("s", 1_000), ("m", 60_000), ("h", 3_600_000), ("d", 86_400_000), ("w", 604_800_000)];
+
+//a bare number is in the unit the flag always used (bare = Duration::from_millis(1) for --timeout-ms and so on)
+pub fn parse(s: &str, bare: Duration) -> Result<Duration, String> {
+    let s = s.trim();
+    let invalid = || format!("invalid duration '{}', expected e.g. 500ms, 2s, 5m, 24h or 1h30m", s);
+    if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        let n: u32 = s.parse().map_err(|_| invalid())?;
+        return bare.checked_mul(n).ok_or_else(invalid);
+    }
+    let mut total = Duration::ZERO;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit() && c != '.').ok_or_else(invalid)?;
+        let n: f64 = rest[..digits].parse().map_err(|_| invalid())?;
+        let unit_len = rest[digits..].find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len() - digits);
+        let unit = &rest[digits..digits + unit_len];
+        let (_, ms) = UNITS.iter().find(|(u, _)| *u == unit).ok_or_else(invalid)?;
+        total += Duration::try_from_secs_f64(n * *ms as f64 / 1000.0).map_err(|_| invalid())?;
+        rest = &rest[digits + unit_len..];
+    }
+    if s.is_empty() { Err(invalid()) } else { Ok(total) }
+}
+
+//the largest units that fit, "1h30m", "2s", "250ms"
+pub fn format(d: Duration) -> String {
+    let mut ms = d.as_millis() as u64;
+    if ms == 0 { return "0s".into(); }
+    let mut out = String::new();
+    for (unit, size) in UNITS.iter().rev().filter(|(u, _)| *u != "w") {
+        if ms >= *size {
+            out += &format!("{}{}", ms / size, unit);
+            ms %= size;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duration() {
+        let ms = Duration::from_millis(1);
+        assert_eq!(parse("2500", ms), Ok(Duration::from_millis(2500)));
+        assert_eq!(parse("30", Duration::from_secs(1)), Ok(Duration::from_secs(30)));
+        assert_eq!(parse("2s", ms), Ok(Duration::from_secs(2)));
+        assert_eq!(parse("5m", ms), Ok(Duration::from_secs(300)));
+        assert_eq!(parse("24h", ms), Ok(Duration::from_secs(86_400)));
+        assert_eq!(parse("1h30m", ms), Ok(Duration::from_secs(5_400)));
+        assert_eq!(parse("1.5s", ms), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse("2w", ms), Ok(Duration::from_secs(14 * 86_400)));
+        assert_eq!(parse(" 250ms ", ms), Ok(Duration::from_millis(250)));
+        for bad in ["", "s", "5x", "1h 30m", "-2s", "1..5s", "5sm"] {
+            assert!(parse(bad, ms).is_err(), "{}", bad);
+        }
+        assert_eq!(parse("5q", ms).unwrap_err(), "invalid duration '5q', expected e.g. 500ms, 2s, 5m, 24h or 1h30m");
+
+        assert_eq!(format(Duration::from_secs(5_400)), "1h30m");
+        assert_eq!(format(Duration::from_millis(2_250)), "2s250ms");
+        assert_eq!(format(Duration::from_secs(86_400)), "1d");
+        assert_eq!(format(Duration::ZERO), "0s");
+    }
+}
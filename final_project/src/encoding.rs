@@ -0,0 +1,182 @@
+//content-delivery audit: one request per accept-encoding, raw body sizes compared to identity;
+//--expect-compression: the same comparison for what a browser offers, on every check
+use std::fmt;
+use std::time::Duration;
+
+use crate::{rawhttp, HttpVersion, Network};
+
+//offered one at a time, identity first as the baseline
+pub const ENCODINGS: [&str; 5] = ["identity", "gzip", "deflate", "br", "zstd"];
+
+//bytes read per response, larger bodies are compared on this prefix
+pub const BODY_LIMIT: u64 = 16 * 1024 * 1024;
+
+//what the server did with one accept-encoding
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probe {
+    pub offered: &'static str,
+    pub status: u16,
+    //content-encoding of the answer, None when sent as is
+    pub served: Option<String>,
+    pub bytes: u64,
+}
+
+impl Probe {
+    pub fn honored(&self) -> bool {
+        self.offered == "identity" || self.served.as_deref().is_some_and(|s| s.eq_ignore_ascii_case(self.offered))
+    }
+}
+
+//raw GET offering these encodings, the body left as it came over the wire
+fn fetch(net: &Network, url: &str, extra_headers: &[(String, String)], offered: &str, timeout: Duration) -> Result<rawhttp::RawResponse, String> {
+    let mut headers: Vec<(&str, &str)> = extra_headers.iter()
+        .filter(|(k, _)| !k.eq_ignore_ascii_case("accept-encoding"))
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    headers.push(("Accept-Encoding", offered));
+    rawhttp::request(net, "GET", url, &headers, &[], timeout, BODY_LIMIT, HttpVersion::Http11)
+}
+
+fn served(resp: &rawhttp::RawResponse) -> Option<String> {
+    resp.header("Content-Encoding").map(str::trim).filter(|e| !e.is_empty() && !e.eq_ignore_ascii_case("identity")).map(String::from)
+}
+
+pub fn audit(net: &Network, url: &str, extra_headers: &[(String, String)], timeout: Duration) -> Vec<Result<Probe, String>> {
+    ENCODINGS.iter().map(|&offered| {
+        let resp = fetch(net, url, extra_headers, offered, timeout)?;
+        Ok(Probe { offered, status: resp.status, served: served(&resp), bytes: resp.body.len() as u64 })
+    }).collect()
+}
+
+//what --expect-compression offers, as browsers do
+pub const NEGOTIATED: &str = "gzip, br";
+//bodies below this are often sent as is on purpose, compressing them gains nothing
+pub const MIN_COMPRESSIBLE: u64 = 1024;
+
+//how one url answered a client offering NEGOTIATED
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compression {
+    //content-encoding of the answer, None when it came uncompressed
+    pub encoding: Option<String>,
+    pub wire_bytes: u64,
+    //the same url asked for identity
+    pub identity_bytes: u64,
+    pub content_type: String,
+}
+
+impl Compression {
+    //text-like and big enough that sending it uncompressed is a config regression
+    pub fn expected(&self) -> bool {
+        let ctype = self.content_type.to_ascii_lowercase();
+        let textual = ctype.starts_with("text/") || ["json", "xml", "javascript", "svg", "wasm"].iter().any(|t| ctype.contains(t));
+        textual && self.identity_bytes >= MIN_COMPRESSIBLE
+    }
+
+    //percent of the identity size saved on the wire, None when not compressed
+    pub fn saving_pct(&self) -> Option<f64> {
+        self.encoding.as_ref()?;
+        if self.identity_bytes == 0 { return None; }
+        Some((self.identity_bytes as f64 - self.wire_bytes as f64) * 100.0 / self.identity_bytes as f64)
+    }
+
+    //why the check fails, when it does
+    pub fn problem(&self) -> Option<String> {
+        if self.encoding.is_some() || !self.expected() { return None; }
+        Some(format!("not compressed: offered {}, {} of {} sent as is", NEGOTIATED, size(self.identity_bytes), self.content_type))
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.encoding, self.saving_pct()) {
+            (Some(enc), Some(pct)) => write!(f, "{}, {} of {} ({:.0}% saved)", enc, size(self.wire_bytes), size(self.identity_bytes), pct),
+            (Some(enc), None) => write!(f, "{}, {}", enc, size(self.wire_bytes)),
+            (None, _) => write!(f, "none, {} sent as is", size(self.wire_bytes)),
+        }
+    }
+}
+
+//two raw requests, NEGOTIATED and identity, so the saving is measured on this very url
+pub fn negotiate(net: &Network, url: &str, extra_headers: &[(String, String)], timeout: Duration) -> Result<Compression, String> {
+    let resp = fetch(net, url, extra_headers, NEGOTIATED, timeout)?;
+    let encoding = served(&resp);
+    let wire_bytes = resp.body.len() as u64;
+    let content_type = resp.header("Content-Type").unwrap_or("").trim().to_string();
+    let identity_bytes = match encoding {
+        Some(_) => fetch(net, url, extra_headers, "identity", timeout)?.body.len() as u64,
+        None => wire_bytes,
+    };
+    Ok(Compression { encoding, wire_bytes, identity_bytes, content_type })
+}
+
+fn size(bytes: u64) -> String {
+    if bytes >= BODY_LIMIT { return format!(">={} KiB", BODY_LIMIT / 1024); }
+    if bytes < 1024 { format!("{} B", bytes) } else { format!("{:.1} KiB", bytes as f64 / 1024.0) }
+}
+
+//report lines for one url, savings against the identity response
+pub fn lines(url: &str, probes: &[Result<Probe, String>]) -> Vec<String> {
+    let mut out = vec![url.to_string()];
+    let base = probes.first().and_then(|p| p.as_ref().ok()).map(|p| p.bytes).filter(|&b| b > 0);
+    for (offered, probe) in ENCODINGS.iter().zip(probes) {
+        let line = match probe {
+            Err(e) => format!("request failed: {}", e),
+            Ok(p) if p.offered == "identity" => format!("{}  status {}", size(p.bytes), p.status),
+            Ok(p) if p.honored() => {
+                let saving = match base {
+                    Some(b) => format!(", {:+.1}% vs identity", (p.bytes as f64 - b as f64) * 100.0 / b as f64),
+                    None => String::new(),
+                };
+                format!("{}  status {}, honored{}", size(p.bytes), p.status, saving)
+            }
+            Ok(p) => match &p.served {
+                Some(other) => format!("{}  status {}, not honored (sent {})", size(p.bytes), p.status, other),
+                None => format!("{}  status {}, not honored (sent uncompressed)", size(p.bytes), p.status),
+            },
+        };
+        out.push(format!("  {:<9} {}", offered, line));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_encoding_audit() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(ENCODINGS.len()) {
+                let Ok(mut s) = stream else { continue };
+                let mut buf = [0u8; 1024];
+                let n = s.read(&mut buf).unwrap_or(0);
+                let req = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+                //gzip and br supported, zstd answered with gzip, the rest plain
+                let (enc, len) = if req.contains("accept-encoding: br") {
+                    ("br", 250)
+                } else if req.contains("accept-encoding: gzip") || req.contains("accept-encoding: zstd") {
+                    ("gzip", 300)
+                } else {
+                    ("", 1000)
+                };
+                let head = if enc.is_empty() { String::new() } else { format!("Content-Encoding: {}\r\n", enc) };
+                let _ = s.write_all(format!("HTTP/1.1 200 OK\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}", head, len, "x".repeat(len)).as_bytes());
+            }
+        });
+        let url = format!("http://127.0.0.1:{}/", port);
+        let probes = audit(&Network::default(), &url, &[("Accept-Encoding".into(), "ignored".into())], Duration::from_secs(2));
+        let ok: Vec<&Probe> = probes.iter().map(|p| p.as_ref().unwrap()).collect();
+        assert_eq!(ok.iter().map(|p| p.honored()).collect::<Vec<_>>(), [true, true, false, true, false]);
+        assert_eq!(ok[4].served.as_deref(), Some("gzip"));
+        let report = lines(&url, &probes);
+        assert_eq!(report[1], "  identity  1000 B  status 200");
+        assert_eq!(report[2], "  gzip      300 B  status 200, honored, -70.0% vs identity");
+        assert_eq!(report[3], "  deflate   1000 B  status 200, not honored (sent uncompressed)");
+        assert_eq!(report[5], "  zstd      300 B  status 200, not honored (sent gzip)");
+    }
+}
@@ -0,0 +1,177 @@
+//feed:// checks: the body must be rss or atom and the newest item recent enough
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::html;
+use crate::scheduler::{days_from_civil, parse_timestamp};
+
+//what a valid feed looked like
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedInfo {
+    pub kind: &'static str,
+    pub title: Option<String>,
+    pub items: usize,
+    pub newest: Option<SystemTime>,
+}
+
+//http(s) url to fetch for a feed:// or feed:https://... entry
+pub fn target(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("feed:")?;
+    if rest.starts_with("http://") || rest.starts_with("https://") {
+        return Some(rest.to_string());
+    }
+    rest.strip_prefix("//").map(|r| format!("https://{}", r))
+}
+
+//parse the body and enforce the max age of its newest entry
+pub fn validate(body: &str, max_age: Duration, now: SystemTime) -> Result<FeedInfo, String> {
+    let info = parse(body)?;
+    let newest = info.newest.ok_or_else(|| format!("{} feed has no parseable item dates", info.kind))?;
+    let age = now.duration_since(newest).unwrap_or_default();
+    if age > max_age {
+        return Err(format!("newest {} item is {}h old (max {}h)", info.kind, age.as_secs() / 3600, max_age.as_secs() / 3600));
+    }
+    Ok(info)
+}
+
+pub fn parse(body: &str) -> Result<FeedInfo, String> {
+    //root element decides the dialect and which tags carry dates
+    let (kind, root, item, date_tags): (&'static str, &str, &str, &[&str]) = if has_tag(body, "rss") {
+        ("rss", "rss", "item", &["pubDate", "dc:date"])
+    } else if has_tag(body, "feed") {
+        ("atom", "feed", "entry", &["updated", "published"])
+    } else if has_tag(body, "rdf:RDF") {
+        ("rss", "rdf:RDF", "item", &["dc:date"])
+    } else {
+        return Err("not an RSS or Atom feed".into());
+    };
+    if !body.contains(&format!("</{}>", root)) {
+        return Err(format!("{} feed is truncated or malformed (no closing </{}>)", kind, root));
+    }
+
+    let items = elements(body, item);
+    let mut newest = items.iter()
+        .filter_map(|it| date_tags.iter().find_map(|t| elements(it, t).first().and_then(|d| parse_date(d))))
+        .max();
+    //an empty feed can still say when it was last built
+    if newest.is_none() {
+        newest = ["lastBuildDate", "updated", "dc:date"].iter()
+            .find_map(|t| elements(body, t).first().and_then(|d| parse_date(d)));
+    }
+    Ok(FeedInfo { kind, title: html::extract_title(body), items: items.len(), newest })
+}
+
+fn has_tag(body: &str, name: &str) -> bool {
+    let open = format!("<{}", name);
+    body.match_indices(&open).any(|(i, _)| matches!(body[i + open.len()..].chars().next(), Some(' ' | '>' | '\n' | '\r' | '\t')))
+}
+
+//inner text of every <name ...>...</name>, cdata unwrapped
+fn elements<'a>(body: &'a str, name: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{}", name), format!("</{}>", name));
+    let mut out = Vec::new();
+    let mut rest = body;
+    while let Some(i) = rest.find(&open) {
+        let after = &rest[i + open.len()..];
+        //skip longer names sharing the prefix, e.g. <itemCount>
+        if !matches!(after.chars().next(), Some(' ' | '>' | '\n' | '\r' | '\t' | '/')) {
+            rest = after;
+            continue;
+        }
+        let Some(gt) = after.find('>') else { break };
+        if after[..gt].ends_with('/') {
+            rest = &after[gt + 1..];
+            continue;
+        }
+        let inner = &after[gt + 1..];
+        let Some(end) = inner.find(&close) else { break };
+        let text = inner[..end].trim();
+        let text = text.strip_prefix("<![CDATA[").and_then(|t| t.strip_suffix("]]>")).unwrap_or(text);
+        out.push(text.trim());
+        rest = &inner[end + close.len()..];
+    }
+    out
+}
+
+//rfc 3339 (atom, dc:date) or rfc 822 (rss pubDate)
+fn parse_date(s: &str) -> Option<SystemTime> {
+    let s = s.trim();
+    if s.as_bytes().get(4) == Some(&b'-') {
+        return parse_timestamp(&strip_fraction(s)).ok();
+    }
+    parse_rfc822(s)
+}
+
+//2024-07-01T10:00:00.123Z -> 2024-07-01T10:00:00Z
+fn strip_fraction(s: &str) -> String {
+    match s.find('.') {
+        Some(dot) => {
+            let end = s[dot + 1..].find(|c: char| !c.is_ascii_digit()).map_or(s.len(), |e| dot + 1 + e);
+            format!("{}{}", &s[..dot], &s[end..])
+        }
+        None => s.to_string(),
+    }
+}
+
+//"Mon, 01 Jul 2024 10:00:00 GMT", weekday and seconds optional
+pub(crate) fn parse_rfc822(s: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let s = s.split_once(',').map_or(s, |(_, r)| r);
+    let mut parts = s.split_whitespace();
+    let day: u32 = parts.next()?.parse().ok()?;
+    let mon = parts.next()?.to_ascii_lowercase();
+    let month = MONTHS.iter().position(|m| mon.starts_with(m))? as u32 + 1;
+    let mut year: i64 = parts.next()?.parse().ok()?;
+    if year < 100 { year += if year < 70 { 2000 } else { 1900 }; }
+    let clock: Vec<i64> = parts.next()?.split(':').map(|v| v.parse().ok()).collect::<Option<_>>()?;
+    let (h, m, sec) = (*clock.first()?, *clock.get(1)?, clock.get(2).copied().unwrap_or(0));
+    let offset_secs = match parts.next().unwrap_or("GMT") {
+        "GMT" | "UT" | "UTC" | "Z" => 0,
+        "EST" => -5 * 3600,
+        "EDT" => -4 * 3600,
+        "CST" => -6 * 3600,
+        "CDT" => -5 * 3600,
+        "MST" => -7 * 3600,
+        "MDT" => -6 * 3600,
+        "PST" => -8 * 3600,
+        "PDT" => -7 * 3600,
+        z if z.len() == 5 && (z.starts_with('+') || z.starts_with('-')) => {
+            let v: i64 = z[1..].parse().ok()?;
+            let secs = (v / 100) * 3600 + (v % 100) * 60;
+            if z.starts_with('-') { -secs } else { secs }
+        }
+        _ => return None,
+    };
+    let secs = days_from_civil(year, month, day) * 86_400 + h * 3600 + m * 60 + sec - offset_secs;
+    if secs < 0 { return None; }
+    Some(UNIX_EPOCH + Duration::from_secs(secs as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0"?><rss version="2.0"><channel><title>Pod &amp; Cast</title>
+        <item><title>Ep 2</title><pubDate>Tue, 02 Jul 2024 10:00:00 +0200</pubDate></item>
+        <item><title>Ep 1</title><pubDate>Mon, 01 Jul 2024 10:00:00 GMT</pubDate></item>
+        </channel></rss>"#;
+
+    #[test]
+    fn test_feed_validate() {
+        let now = parse_timestamp("2024-07-03T08:00Z").unwrap();
+        let info = validate(RSS, Duration::from_secs(2 * 86_400), now).unwrap();
+        assert_eq!((info.kind, info.items), ("rss", 2));
+        assert_eq!(info.title.as_deref(), Some("Pod & Cast"));
+        assert_eq!(info.newest, parse_timestamp("2024-07-02T08:00Z").ok());
+        assert!(validate(RSS, Duration::from_secs(3600), now).unwrap_err().contains("24h old"));
+
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>A</title>
+            <entry><updated>2024-07-02T09:30:00.250Z</updated></entry></feed>"#;
+        assert_eq!(parse(atom).unwrap().newest, parse_timestamp("2024-07-02T09:30Z").ok());
+
+        assert!(parse("<html><title>x</title></html>").is_err());
+        assert!(parse("<rss><channel><item>").unwrap_err().contains("truncated"));
+        assert_eq!(target("feed://a.test/rss").as_deref(), Some("https://a.test/rss"));
+        assert_eq!(target("feed:http://a.test/rss").as_deref(), Some("http://a.test/rss"));
+        assert_eq!(target("https://a.test/rss"), None);
+    }
+}
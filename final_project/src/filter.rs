@@ -0,0 +1,118 @@
+//--only: which checks of a round get printed, so big healthy fleets stay quiet
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{memory, Config, WebsiteStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Only {
+    Failures,
+    Degraded,
+    //health differs from the url's previous check
+    Changed,
+}
+
+impl Only {
+    //comma-separated, a check is shown when any filter matches
+    pub fn parse_list(s: &str) -> Result<Vec<Only>, String> {
+        s.split(',').map(|f| match f.trim() {
+            "failures" => Ok(Only::Failures),
+            "degraded" => Ok(Only::Degraded),
+            "changed" => Ok(Only::Changed),
+            other => Err(format!("unknown --only filter '{}', expected failures, degraded or changed", other)),
+        }).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Up,
+    //up, but over its latency sla, needed retries or was slower than the alert latency
+    Degraded,
+    Down,
+}
+
+pub fn health(r: &WebsiteStatus, cfg: &Config) -> Health {
+    if !r.is_up() { return Health::Down; }
+    let slow = cfg.alert_rules.latency_ms.is_some_and(|max| r.response_time.as_millis() > max as u128);
+    if slow || r.retries > 0 || r.is_degraded() { Health::Degraded } else { Health::Up }
+}
+
+//remembers each url's last health to tell what changed
+#[derive(Debug, Clone, Default)]
+pub struct ResultFilter {
+    only: Vec<Only>,
+    last: HashMap<Arc<str>, Health>,
+}
+
+impl ResultFilter {
+    pub fn new(only: Vec<Only>) -> Self {
+        Self { only, last: HashMap::new() }
+    }
+
+    pub fn heap_bytes(&self) -> usize {
+        memory::table_bytes(&self.last)
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.only.is_empty()
+    }
+
+    //checks to show; every result updates the remembered health, shown or not
+    pub fn apply(&mut self, results: &[WebsiteStatus], cfg: &Config) -> Vec<WebsiteStatus> {
+        let mut shown = Vec::new();
+        for r in results {
+            let now = health(r, cfg);
+            //unseen urls count as up, so a new url only shows when it is not
+            let before = self.last.insert(r.url.clone(), now).unwrap_or(Health::Up);
+            if self.keeps(now, before) { shown.push(r.clone()); }
+        }
+        shown
+    }
+
+    //whether apply would show this check, without remembering its health; for rows printed mid-round
+    pub fn shows(&self, r: &WebsiteStatus, cfg: &Config) -> bool {
+        let before = self.last.get(&r.url).copied().unwrap_or(Health::Up);
+        self.keeps(health(r, cfg), before)
+    }
+
+    fn keeps(&self, now: Health, before: Health) -> bool {
+        !self.is_active() || self.only.iter().any(|f| match f {
+            Only::Failures => now == Health::Down,
+            Only::Degraded => now == Health::Degraded,
+            Only::Changed => now != before,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+    use crate::{CheckError, ErrorKind};
+
+    #[test]
+    fn test_only_filters() {
+        let cfg = Config::default();
+        let down = || Err(CheckError::new(ErrorKind::Transport, "refused"));
+        let mut f = ResultFilter::new(Only::parse_list("changed").unwrap());
+        let first = f.apply(&[status_for("a", Ok(200), 5), status_for("b", down(), 5)], &cfg);
+        assert_eq!(first.iter().map(|r| &*r.url).collect::<Vec<_>>(), ["b"]);
+        //same states again, nothing changed
+        assert!(f.apply(&[status_for("a", Ok(200), 5), status_for("b", down(), 5)], &cfg).is_empty());
+        let recovered = f.apply(&[status_for("a", Ok(200), 5), status_for("b", Ok(200), 5)], &cfg);
+        assert_eq!(recovered.len(), 1);
+
+        let mut f = ResultFilter::new(Only::parse_list("failures,degraded").unwrap());
+        let retried = WebsiteStatus { retries: 1, ..status_for("c", Ok(200), 5) };
+        let shown = f.apply(&[status_for("a", Ok(200), 5), status_for("b", down(), 5), retried], &cfg);
+        assert_eq!(shown.iter().map(|r| &*r.url).collect::<Vec<_>>(), ["b", "c"]);
+        //the mid-round check agrees with apply and leaves the remembered health alone
+        let mut f = ResultFilter::new(Only::parse_list("changed").unwrap());
+        assert!(f.shows(&status_for("b", down(), 5), &cfg));
+        assert!(f.shows(&status_for("b", down(), 5), &cfg));
+        f.apply(&[status_for("b", down(), 5)], &cfg);
+        assert!(!f.shows(&status_for("b", down(), 5), &cfg));
+        assert!(Only::parse_list("failures,nope").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//ftp:// banner, optional login and directory listing; sftp:// ssh identification banner
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use url::Url;
+
+use crate::{CheckError, Network, PROBE_OK};
+
+//largest listing read for a path check
+const LIST_MAX_BYTES: u64 = 1024 * 1024;
+
+//status for an ftp:// url (last reply code) or an sftp:// url (PROBE_OK)
+pub fn check(net: &Network, url: &str, login: bool, timeout: Duration) -> Result<u16, CheckError> {
+    let parsed = Url::parse(url).map_err(|e| CheckError::transport(format!("invalid url: {}", e)))?;
+    match parsed.scheme() {
+        "ftp" => ftp(net, &parsed, login, timeout),
+        "sftp" => sftp(net, &parsed, timeout),
+        other => Err(CheckError::transport(format!("unsupported scheme '{}'", other))),
+    }
+}
+
+//"123-first" ... "123 last", code and last line's text
+fn reply(reader: &mut impl BufRead) -> Result<(u16, String), CheckError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).map_err(CheckError::transport)? == 0 {
+        return Err(CheckError::transport("connection closed mid-reply"));
+    }
+    let first = line.trim_end().to_string();
+    let code: u16 = first.get(..3).and_then(|c| c.parse().ok()).ok_or_else(|| CheckError::protocol(format!("not an ftp reply: '{}'", first)))?;
+    if first.as_bytes().get(3) != Some(&b'-') {
+        return Ok((code, first.get(4..).unwrap_or_default().to_string()));
+    }
+    //continuation lines may carry anything until the code comes back with a space
+    let end = format!("{} ", code);
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).map_err(CheckError::transport)? == 0 {
+            return Err(CheckError::transport("connection closed mid-reply"));
+        }
+        if let Some(text) = line.trim_end().strip_prefix(&end) {
+            return Ok((code, text.to_string()));
+        }
+    }
+}
+
+fn command(writer: &mut impl Write, reader: &mut impl BufRead, cmd: &str) -> Result<(u16, String), CheckError> {
+    writer.write_all(format!("{}\r\n", cmd).as_bytes()).map_err(CheckError::transport)?;
+    reply(reader)
+}
+
+//data port from "227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)"
+fn pasv_port(text: &str) -> Option<u16> {
+    let inner = &text[text.find('(')? + 1..text.find(')')?];
+    let nums: Vec<u16> = inner.split(',').map(|n| n.trim().parse().ok()).collect::<Option<_>>()?;
+    if nums.len() != 6 { return None; }
+    Some(nums[4] * 256 + nums[5])
+}
+
+fn ftp(net: &Network, url: &Url, login: bool, timeout: Duration) -> Result<u16, CheckError> {
+    let tcp = net.connect_tcp(url, Some(21), timeout).map_err(CheckError::transport)?;
+    let peer = tcp.peer_addr().map_err(CheckError::transport)?;
+    let mut writer = tcp.try_clone().map_err(CheckError::transport)?;
+    let mut reader = BufReader::new(tcp);
+
+    let (mut code, text) = reply(&mut reader)?;
+    if code != 220 {
+        return Err(CheckError::protocol(format!("ftp greeting {} {}", code, text)));
+    }
+    //a path asks for a listing, which needs a session
+    let path = url.path();
+    let list = !path.is_empty() && path != "/";
+    if login || list || !url.username().is_empty() {
+        let user = if url.username().is_empty() { "anonymous" } else { url.username() };
+        let pass = url.password().unwrap_or("sitewatch@");
+        let (c, text) = command(&mut writer, &mut reader, &format!("USER {}", user))?;
+        code = c;
+        if code == 331 {
+            let (c, text) = command(&mut writer, &mut reader, &format!("PASS {}", pass))?;
+            code = c;
+            if code != 230 {
+                return Err(CheckError::protocol(format!("ftp login as {} refused: {} {}", user, code, text)));
+            }
+        } else if code != 230 {
+            return Err(CheckError::protocol(format!("ftp USER {} answered {} {}", user, code, text)));
+        }
+    }
+    if list {
+        code = listing(&mut writer, &mut reader, peer, path, timeout)?;
+    }
+    let _ = writer.write_all(b"QUIT\r\n");
+    Ok(code)
+}
+
+//passive LIST of path, the data connection goes to the control peer (pasv addresses are often wrong behind nat)
+fn listing(writer: &mut impl Write, reader: &mut impl BufRead, peer: SocketAddr, path: &str, timeout: Duration) -> Result<u16, CheckError> {
+    let (code, text) = command(writer, reader, "PASV")?;
+    let port = match code {
+        227 => pasv_port(&text).ok_or_else(|| CheckError::protocol(format!("unparseable PASV reply '{}'", text)))?,
+        _ => return Err(CheckError::protocol(format!("ftp PASV answered {} {}", code, text))),
+    };
+    let data = TcpStream::connect_timeout(&SocketAddr::new(peer.ip(), port), timeout).map_err(|e| CheckError::transport(format!("ftp data connect failed: {}", e)))?;
+    data.set_read_timeout(Some(timeout)).map_err(CheckError::transport)?;
+
+    let (code, text) = command(writer, reader, &format!("LIST {}", path))?;
+    if code != 125 && code != 150 {
+        return Err(CheckError::protocol(format!("ftp LIST {} answered {} {}", path, code, text)));
+    }
+    let mut body = Vec::new();
+    data.take(LIST_MAX_BYTES).read_to_end(&mut body).map_err(|e| CheckError::transport(format!("ftp listing read failed: {}", e)))?;
+    let (code, text) = reply(reader)?;
+    if code != 226 && code != 250 {
+        return Err(CheckError::protocol(format!("ftp LIST {} ended with {} {}", path, code, text)));
+    }
+    Ok(code)
+}
+
+//sftp runs over ssh, so reachability is the ssh identification line
+fn sftp(net: &Network, url: &Url, timeout: Duration) -> Result<u16, CheckError> {
+    let tcp = net.connect_tcp(url, Some(22), timeout).map_err(CheckError::transport)?;
+    let mut reader = BufReader::new(tcp);
+    let mut line = String::new();
+    //servers may send other lines before the identification
+    for _ in 0..16 {
+        line.clear();
+        if reader.read_line(&mut line).map_err(CheckError::transport)? == 0 {
+            return Err(CheckError::transport("connection closed before the ssh banner"));
+        }
+        if line.starts_with("SSH-") {
+            return if line.starts_with("SSH-2.0-") || line.starts_with("SSH-1.99-") {
+                Ok(PROBE_OK)
+            } else {
+                Err(CheckError::protocol(format!("ssh banner '{}' does not speak ssh 2", line.trim_end())))
+            };
+        }
+    }
+    Err(CheckError::protocol("no ssh banner"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorKind;
+    use std::net::TcpListener;
+    use std::thread;
+
+    //scripted control connection; "{PASV}" is replaced by a data listener that serves `listing`
+    fn serve(greeting: &'static str, answers: &'static [&'static str], listing: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let _ = s.write_all(greeting.as_bytes());
+            let mut data = None;
+            for a in answers {
+                let mut buf = [0u8; 256];
+                if s.read(&mut buf).unwrap_or(0) == 0 { return; }
+                if a.contains("{PASV}") {
+                    let l = TcpListener::bind("127.0.0.1:0").unwrap();
+                    let p = l.local_addr().unwrap().port();
+                    let _ = s.write_all(a.replace("{PASV}", &format!("127,0,0,1,{},{}", p / 256, p % 256)).as_bytes());
+                    data = Some(l);
+                    continue;
+                }
+                let _ = s.write_all(a.as_bytes());
+                if a.starts_with("150")
+                    && let Some(l) = data.take()
+                    && let Ok((mut d, _)) = l.accept()
+                {
+                    let _ = d.write_all(listing.as_bytes());
+                    drop(d);
+                    let _ = s.write_all(b"226 Transfer complete\r\n");
+                }
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_ftp_and_sftp() {
+        let t = Duration::from_secs(2);
+        let port = serve("220-Welcome\r\n  to the archive\r\n220 ready\r\n", &[], "");
+        assert_eq!(check(&Network::default(), &format!("ftp://127.0.0.1:{}", port), false, t), Ok(220));
+
+        let port = serve("220 ready\r\n", &["331 send password\r\n", "230 logged in\r\n"], "");
+        assert_eq!(check(&Network::default(), &format!("ftp://127.0.0.1:{}/", port), true, t), Ok(230));
+
+        let port = serve("220 ready\r\n", &["530 no anonymous\r\n"], "");
+        let err = check(&Network::default(), &format!("ftp://127.0.0.1:{}", port), true, t).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Protocol);
+
+        let answers = &["331 send password\r\n", "230 logged in\r\n", "227 Entering Passive Mode ({PASV})\r\n", "150 Here comes the listing\r\n"];
+        let port = serve("220 ready\r\n", answers, "-rw-r--r-- 1 ftp ftp 3 Jul 01 10:00 a.txt\r\n");
+        assert_eq!(check(&Network::default(), &format!("ftp://127.0.0.1:{}/pub", port), false, t), Ok(226));
+
+        let port = serve("SSH-2.0-OpenSSH_9.6\r\n", &[], "");
+        assert_eq!(check(&Network::default(), &format!("sftp://127.0.0.1:{}", port), false, t), Ok(PROBE_OK));
+        let port = serve("220 not ssh\r\n", &[], "");
+        assert!(check(&Network::default(), &format!("sftp://127.0.0.1:{}", port), false, t).is_err());
+        assert_eq!(pasv_port("Entering Passive Mode (10,0,0,1,195,80)."), Some(50000));
+    }
+}
@@ -0,0 +1,88 @@
+//gantt-style view of how the worker pool scheduled a round
+use std::time::Duration;
+
+use crate::{html, Slot, WebsiteStatus};
+
+//width of the text bars in columns
+const TEXT_WIDTH: usize = 60;
+
+//checks that ran in the pool, by start time
+fn scheduled(results: &[WebsiteStatus]) -> (Vec<(&WebsiteStatus, Slot)>, Duration) {
+    let mut rows: Vec<_> = results.iter().filter_map(|r| r.slot.map(|s| (r, s))).collect();
+    rows.sort_by_key(|(_, s)| (s.start, s.worker));
+    let span = rows.iter().map(|(_, s)| s.end).max().unwrap_or_default();
+    (rows, span)
+}
+
+fn column(at: Duration, span: Duration) -> usize {
+    if span.is_zero() { return 0; }
+    ((at.as_secs_f64() / span.as_secs_f64()) * TEXT_WIDTH as f64).round() as usize
+}
+
+//one line per check: worker, bar over the round's timeline, duration and url
+pub fn text(results: &[WebsiteStatus]) -> Vec<String> {
+    let (rows, span) = scheduled(results);
+    if rows.is_empty() { return Vec::new(); }
+    let workers = rows.iter().map(|(_, s)| s.worker).collect::<std::collections::HashSet<_>>().len();
+    let mut out = vec![format!("Schedule ({} checks on {} worker(s) over {}ms):", rows.len(), workers, span.as_millis())];
+    for (r, s) in rows {
+        let from = column(s.start, span).min(TEXT_WIDTH - 1);
+        let to = column(s.end, span).clamp(from + 1, TEXT_WIDTH);
+        let bar = format!("{}{}{}", " ".repeat(from), "#".repeat(to - from), " ".repeat(TEXT_WIDTH - to));
+        let mark = if r.is_up() { ' ' } else { '!' };
+        out.push(format!("w{:<3} |{}|{} {:>6}ms {}", s.worker, bar, mark, (s.end - s.start).as_millis(), r.url));
+    }
+    out
+}
+
+//self-contained html page with the same rows as positioned bars
+pub fn html(results: &[WebsiteStatus]) -> String {
+    let (rows, span) = scheduled(results);
+    let pct = |d: Duration| if span.is_zero() { 0.0 } else { d.as_secs_f64() * 100.0 / span.as_secs_f64() };
+    let mut out = String::from("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>sitewatch round schedule</title>\n<style>\n");
+    out.push_str("body{font:13px sans-serif} .row{display:flex;align-items:center;height:20px}\n");
+    out.push_str(".label{width:60px;color:#666} .lane{position:relative;flex:1;height:14px;background:#f3f3f3}\n");
+    out.push_str(".bar{position:absolute;height:14px;background:#4a8} .bar.down{background:#d55} .url{width:40%;padding-left:8px;white-space:nowrap;overflow:hidden}\n");
+    out.push_str(&format!("</style></head><body>\n<h3>{} checks over {}ms</h3>\n", rows.len(), span.as_millis()));
+    for (r, s) in rows {
+        let class = if r.is_up() { "bar" } else { "bar down" };
+        out.push_str(&format!(
+            "<div class=\"row\"><span class=\"label\">w{}</span><span class=\"lane\"><span class=\"{}\" style=\"left:{:.2}%;width:{:.2}%\" title=\"{}-{}ms\"></span></span><span class=\"url\">{}</span></div>\n",
+            s.worker,
+            class,
+            pct(s.start),
+            (pct(s.end) - pct(s.start)).max(0.2),
+            s.start.as_millis(),
+            s.end.as_millis(),
+            html::escape(&r.url),
+        ));
+    }
+    out.push_str("</body></html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+
+    #[test]
+    fn test_gantt_rows() {
+        let slot = |worker, start, end| Some(Slot { worker, start: Duration::from_millis(start), end: Duration::from_millis(end) });
+        let mut a = status_for("http://a/", Ok(200), 100);
+        a.slot = slot(0, 0, 100);
+        let mut b = status_for("http://b/<x>", Ok(503), 50);
+        b.slot = slot(1, 50, 100);
+        let unscheduled = status_for("http://c/", Ok(200), 1);
+
+        let lines = text(&[b.clone(), a.clone(), unscheduled]);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Schedule (2 checks on 2 worker(s) over 100ms)"));
+        assert!(lines[1].starts_with(&format!("w0   |{}|", "#".repeat(TEXT_WIDTH))));
+        assert!(lines[2].contains(&format!("|{}{}|!", " ".repeat(30), "#".repeat(30))));
+
+        let page = html(&[a, b]);
+        assert!(page.contains("left:50.00%;width:50.00%"));
+        assert!(page.contains("http://b/&lt;x&gt;"));
+    }
+}
@@ -0,0 +1,65 @@
+//--strict-headers: duplicate and inconsistently cased response headers, usual signs of a misconfigured proxy
+
+//headers that are legitimately sent more than once
+const REPEATABLE: [&str; 8] = ["set-cookie", "link", "via", "vary", "warning", "www-authenticate", "proxy-authenticate", "cache-control"];
+
+//problems found in a raw header list, one line per header name, empty when it looks clean
+pub fn anomalies(headers: &[(String, String)]) -> Vec<String> {
+    //values and casings by lowercased name, in first-seen order
+    let mut seen: Vec<(String, Vec<&str>, Vec<&str>)> = Vec::new();
+    for (name, value) in headers {
+        let lower = name.to_ascii_lowercase();
+        let i = match seen.iter().position(|(n, _, _)| *n == lower) {
+            Some(i) => i,
+            None => { seen.push((lower, Vec::new(), Vec::new())); seen.len() - 1 }
+        };
+        let (_, values, casings) = &mut seen[i];
+        values.push(value);
+        if !casings.contains(&name.as_str()) { casings.push(name); }
+    }
+    let mut out = Vec::new();
+    for (name, values, casings) in &seen {
+        //same name with different casing inside one response: part of the head was rewritten or injected
+        let mixed = if casings.len() > 1 { Some(casings.join(", ")) } else { None };
+        if values.len() > 1 && !REPEATABLE.contains(&name.as_str()) {
+            let conflicting = values.iter().any(|v| v != &values[0]);
+            out.push(format!(
+                "duplicate header {} ({} times{}{})",
+                name,
+                values.len(),
+                if conflicting { ", conflicting values" } else { "" },
+                mixed.map(|m| format!(", mixed casing {}", m)).unwrap_or_default(),
+            ));
+        } else if let Some(m) = mixed {
+            out.push(format!("header {} sent with mixed casing ({})", name, m));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_header_anomalies() {
+        assert!(anomalies(&h(&[("Content-Type", "text/html"), ("Set-Cookie", "a=1"), ("Set-Cookie", "b=2")])).is_empty());
+        assert!(anomalies(&h(&[("content-type", "text/html"), ("etag", "x")])).is_empty());
+
+        let a = anomalies(&h(&[("Content-Length", "10"), ("Content-Length", "12"), ("Server", "x")]));
+        assert_eq!(a, vec!["duplicate header content-length (2 times, conflicting values)"]);
+
+        //lowercase names next to capitalized ones are common and fine
+        assert!(anomalies(&h(&[("Content-Type", "a"), ("Server", "x"), ("x-cache", "HIT")])).is_empty());
+
+        let a = anomalies(&h(&[("X-Id", "1"), ("x-id", "1"), ("Server", "x")]));
+        assert_eq!(a, vec!["duplicate header x-id (2 times, mixed casing X-Id, x-id)"]);
+
+        let a = anomalies(&h(&[("Set-Cookie", "a=1"), ("set-cookie", "b=2")]));
+        assert_eq!(a, vec!["header set-cookie sent with mixed casing (Set-Cookie, set-cookie)"]);
+    }
+}
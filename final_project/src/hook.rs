@@ -0,0 +1,89 @@
+//user commands: --on-check-hook sees each finished check as one json line on stdin and may print a json object back,
+//--on-down/--on-recover run in the background with the details in the environment
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::json::{self, Value};
+
+//a hook still running after this is killed, the check keeps no meta
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+//fields of the object the command printed; empty output adds nothing
+pub fn run(cmd: &str, record: &str, timeout: Duration) -> Result<Vec<(String, Value)>, String> {
+    let mut child = Command::new("sh")
+        .args(["-c", cmd])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("cannot run hook: {}", e))?;
+    //a hook that never reads stdin closes it early, that is fine
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(record.as_bytes()).and_then(|_| stdin.write_all(b"\n"));
+    }
+    //read on the side so a chatty hook cannot block on a full pipe while we wait
+    let mut stdout = child.stdout.take().ok_or("hook stdout missing")?;
+    let reader = thread::spawn(move || {
+        let mut out = String::new();
+        stdout.read_to_string(&mut out).map(|_| out)
+    });
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait().map_err(|e| format!("hook failed: {}", e))? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("hook timed out after {}s", timeout.as_secs_f64()));
+            }
+            None => thread::sleep(Duration::from_millis(10)),
+        }
+    };
+    let out = reader.join().map_err(|_| "hook output reader panicked")?.map_err(|e| format!("hook output unreadable: {}", e))?;
+    if !status.success() { return Err(format!("hook exited with {}", status)); }
+    if out.trim().is_empty() { return Ok(Vec::new()); }
+    match json::parse(out.trim())? {
+        Value::Object(fields) => Ok(fields.into_iter().collect()),
+        _ => Err("hook output is not a json object".into()),
+    }
+}
+
+//started and left to finish on its own, a waiter thread reaps it
+pub fn spawn(cmd: &str, env: &[(&str, String)]) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .args(["-c", cmd])
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("cannot run {}: {}", cmd, e))?;
+    thread::spawn(move || { let _ = child.wait(); });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook() {
+        let line = r#"{"url":"http://a/","status":200}"#;
+        //reads the record and answers from it
+        let fields = run(r#"read l; case "$l" in *'"status":200'*) echo '{"team":"web","tags":["a"]}';; esac"#, line, HOOK_TIMEOUT).unwrap();
+        assert_eq!(fields, vec![("tags".to_string(), Value::from(vec!["a"])), ("team".to_string(), Value::from("web"))]);
+        assert_eq!(run("true", line, HOOK_TIMEOUT).unwrap(), Vec::new());
+        assert!(run("echo 42", line, HOOK_TIMEOUT).unwrap_err().contains("not a json object"));
+        assert!(run("exit 3", line, HOOK_TIMEOUT).unwrap_err().contains("exited"));
+        let start = Instant::now();
+        assert!(run("sleep 5", line, Duration::from_millis(100)).unwrap_err().contains("timed out"));
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        let out = std::env::temp_dir().join(format!("sitewatch-hook-{}", std::process::id()));
+        spawn(&format!("printf '%s %s' \"$SITEWATCH_EVENT\" \"$SITEWATCH_URL\" > {}", out.display()), &[("SITEWATCH_EVENT", "down".into()), ("SITEWATCH_URL", "http://a/".into())]).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while std::fs::read_to_string(&out).map_or(true, |s| s.is_empty()) && Instant::now() < deadline { thread::sleep(Duration::from_millis(10)); }
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "down http://a/");
+        std::fs::remove_file(&out).unwrap();
+    }
+}
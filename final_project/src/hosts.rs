@@ -0,0 +1,154 @@
+//--allow-hosts / --deny-hosts: which hosts checks may target, enforced when the config loads
+use std::net::IpAddr;
+
+use url::Url;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostPattern {
+    //example.com, localhost or an ip address
+    Exact(String),
+    //*.example.com: any subdomain, not example.com itself
+    Subdomains(String),
+    //10.0.0.0/8, fd00::/8
+    Net(IpAddr, u8),
+}
+
+fn valid_name(s: &str) -> bool {
+    !s.is_empty() && s.split('.').all(|l| !l.is_empty() && l.len() <= 63 && l.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'))
+}
+
+fn in_net(ip: IpAddr, net: IpAddr, bits: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(a), IpAddr::V4(n)) => {
+            let mask = u32::MAX.checked_shl(32 - bits as u32).unwrap_or(0);
+            u32::from(a) & mask == u32::from(n) & mask
+        }
+        (IpAddr::V6(a), IpAddr::V6(n)) => {
+            let mask = u128::MAX.checked_shl(128 - bits as u32).unwrap_or(0);
+            u128::from(a) & mask == u128::from(n) & mask
+        }
+        _ => false,
+    }
+}
+
+impl HostPattern {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let bad = |why: &str| format!("invalid host pattern '{}': {}", s, why);
+        let p = s.trim().trim_end_matches('.').to_ascii_lowercase();
+        if p.contains("://") {
+            return Err(bad("give a host name, not a url"));
+        }
+        if let Some((addr, bits)) = p.split_once('/') {
+            let addr: IpAddr = addr.parse().map_err(|_| bad("network must be an ip address"))?;
+            let max = if addr.is_ipv4() { 32 } else { 128 };
+            let bits: u8 = bits.parse().ok().filter(|b| *b <= max).ok_or_else(|| bad(&format!("prefix length must be 0-{}", max)))?;
+            return Ok(HostPattern::Net(addr, bits));
+        }
+        if let Ok(ip) = p.trim_matches(['[', ']']).parse::<IpAddr>() {
+            return Ok(HostPattern::Exact(ip.to_string()));
+        }
+        if let Some(domain) = p.strip_prefix("*.") {
+            if !valid_name(domain) { return Err(bad("expected *.domain")); }
+            return Ok(HostPattern::Subdomains(domain.to_string()));
+        }
+        if p.contains('*') { return Err(bad("* only works as a leading *.")); }
+        if p.contains(':') { return Err(bad("ports are not part of a host pattern")); }
+        if !valid_name(&p) { return Err(bad("not a host name")); }
+        Ok(HostPattern::Exact(p))
+    }
+
+    pub fn matches(&self, host: &str) -> bool {
+        match self {
+            HostPattern::Exact(h) => h == host,
+            HostPattern::Subdomains(d) => host.strip_suffix(d.as_str()).is_some_and(|rest| rest.ends_with('.') && rest.len() > 1),
+            HostPattern::Net(net, bits) => host.parse::<IpAddr>().is_ok_and(|ip| in_net(ip, *net, *bits)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HostPolicy {
+    //non-empty: every target has to match one of these
+    pub allow: Vec<HostPattern>,
+    //wins over allow
+    pub deny: Vec<HostPattern>,
+}
+
+//comma separated, as given to the flags
+fn parse_list(list: &str) -> Result<Vec<HostPattern>, String> {
+    let patterns: Vec<HostPattern> = list.split(',').map(str::trim).filter(|p| !p.is_empty()).map(HostPattern::parse).collect::<Result<_, _>>()?;
+    if patterns.is_empty() { return Err("empty host list".into()); }
+    Ok(patterns)
+}
+
+//lowercased, no brackets or trailing dot, so patterns compare on one form
+pub(crate) fn host_of(url: &str) -> Result<String, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("{}: invalid url: {}", url, e))?;
+    let host = parsed.host_str().filter(|h| !h.is_empty()).ok_or_else(|| format!("{}: no host to check against the host rules", url))?;
+    let host = host.trim_matches(['[', ']']).trim_end_matches('.').to_ascii_lowercase();
+    Ok(host.parse::<IpAddr>().map(|ip| ip.to_string()).unwrap_or(host))
+}
+
+impl HostPolicy {
+    pub fn allow(&mut self, list: &str) -> Result<(), String> {
+        self.allow.extend(parse_list(list)?);
+        Ok(())
+    }
+
+    pub fn deny(&mut self, list: &str) -> Result<(), String> {
+        self.deny.extend(parse_list(list)?);
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    pub fn check(&self, url: &str) -> Result<(), String> {
+        if self.is_empty() { return Ok(()); }
+        let host = host_of(url)?;
+        if self.deny.iter().any(|p| p.matches(&host)) {
+            return Err(format!("{}: host {} is denied by --deny-hosts", url, host));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|p| p.matches(&host)) {
+            return Err(format!("{}: host {} is not in --allow-hosts", url, host));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_policy() {
+        let mut p = HostPolicy::default();
+        p.allow("*.example.com, status.test,10.0.0.0/8").unwrap();
+        p.deny("admin.example.com,10.9.0.0/16").unwrap();
+        assert!(p.check("https://www.Example.com./x").is_ok());
+        assert!(p.check("https://a.b.example.com").is_ok());
+        assert!(p.check("http://status.test:8080/health").is_ok());
+        assert!(p.check("tcp://10.1.2.3:22").is_ok());
+        //the apex is not a subdomain
+        assert!(p.check("https://example.com/").unwrap_err().contains("not in --allow-hosts"));
+        assert!(p.check("https://evilexample.com/").is_err());
+        assert!(p.check("https://admin.example.com/").unwrap_err().contains("denied by --deny-hosts"));
+        assert!(p.check("tcp://10.9.0.1:22").unwrap_err().contains("denied"));
+        assert!(p.check("https://169.254.169.254/latest").is_err());
+
+        let mut v6 = HostPolicy::default();
+        v6.deny("fd00::/8,localhost").unwrap();
+        assert!(v6.check("http://[fd12::1]:80/").is_err());
+        assert!(v6.check("http://LOCALHOST/").is_err());
+        assert!(v6.check("http://[2001:db8::1]/").is_ok());
+        assert!(HostPolicy::default().check("not a url").is_ok());
+
+        for bad in ["https://a.com", "a.*.com", "*.", "a.com:80", "10.0.0.0/33", "::/129", "a..b", ""] {
+            assert!(HostPolicy::default().allow(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(HostPattern::parse("[::1]").unwrap(), HostPattern::Exact("::1".into()));
+        assert_eq!(HostPattern::parse("0.0.0.0/0").unwrap(), HostPattern::Net("0.0.0.0".parse().unwrap(), 0));
+        assert!(HostPattern::parse("0.0.0.0/0").unwrap().matches("8.8.8.8"));
+    }
+}
@@ -0,0 +1,113 @@
+//small html/xml helpers for response bodies
+
+//upstream proxy and cdn error pages that still come back with a title
+const SUSPICIOUS_TITLES: [&str; 8] = [
+    "bad gateway",
+    "gateway timeout",
+    "gateway time-out",
+    "service unavailable",
+    "service temporarily unavailable",
+    "origin is unreachable",
+    "web server is down",
+    "502 ",
+];
+
+//first <title> element, entities decoded and whitespace collapsed
+pub fn extract_title(body: &str) -> Option<String> {
+    let lower = body.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let raw = decode_entities(&body[start..end]);
+    let title = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    if title.is_empty() { None } else { Some(title) }
+}
+
+//why a title looks like an error page, if it does
+pub fn suspicious_title(title: &str) -> Option<&'static str> {
+    let lower = format!("{} ", title.to_ascii_lowercase());
+    SUSPICIOUS_TITLES.iter().copied().find(|p| lower.contains(p))
+}
+
+//value of attr inside one tag, quoted or bare; tag is already lowercased
+fn attr<'a>(tag: &str, raw: &'a str, name: &str) -> Option<&'a str> {
+    let mut from = 0;
+    while let Some(i) = tag[from..].find(name) {
+        let at = from + i;
+        from = at + name.len();
+        if !tag[..at].ends_with(char::is_whitespace) { continue; }
+        let rest = tag[from..].trim_start();
+        let Some(rest) = rest.strip_prefix('=') else { continue };
+        let start = tag.len() - rest.trim_start().len();
+        let value = &raw[start..];
+        return Some(match value.chars().next() {
+            Some(q @ ('"' | '\'')) => value[1..].split(q).next().unwrap_or_default(),
+            _ => value.split(|c: char| c.is_whitespace() || c == '>').next().unwrap_or_default(),
+        });
+    }
+    None
+}
+
+//target of a <meta http-equiv="refresh" content="N; url=..."> soft redirect; a bare reload has none
+pub fn meta_refresh(body: &str) -> Option<String> {
+    let lower = body.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find("<meta") {
+        let start = from + i;
+        let end = start + lower[start..].find('>').unwrap_or(lower.len() - start);
+        from = end;
+        let (tag, raw) = (&lower[start..end], &body[start..end]);
+        if !attr(tag, raw, "http-equiv").is_some_and(|v| v.eq_ignore_ascii_case("refresh")) { continue; }
+        let content = attr(tag, raw, "content")?;
+        let (_, target) = content.split_once(';').or_else(|| content.split_once(','))?;
+        let target = target.trim();
+        let url = match target.get(..4) {
+            Some(k) if k.eq_ignore_ascii_case("url=") => &target[4..],
+            _ => target,
+        };
+        let url = decode_entities(url.trim().trim_matches(|c| c == '\'' || c == '"'));
+        return if url.is_empty() { None } else { Some(url) };
+    }
+    None
+}
+
+//text safe inside html elements and quoted attributes
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+//the handful of entities that show up in titles
+fn decode_entities(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_title() {
+        let html = "<html><HEAD><Title lang=\"en\">\n  Tom &amp; Jerry\n</TITLE></head></html>";
+        assert_eq!(extract_title(html).as_deref(), Some("Tom & Jerry"));
+        assert_eq!(extract_title("<title></title>"), None);
+        assert_eq!(extract_title("no markup"), None);
+        assert_eq!(suspicious_title("502 Bad Gateway"), Some("bad gateway"));
+        assert_eq!(suspicious_title("Example Domain"), None);
+    }
+
+    #[test]
+    fn test_meta_refresh() {
+        let page = "<head><meta charset=utf-8><META HTTP-EQUIV=\"Refresh\" CONTENT=\"0; URL='/new?a=1&amp;b=2'\"></head>";
+        assert_eq!(meta_refresh(page).as_deref(), Some("/new?a=1&b=2"));
+        assert_eq!(meta_refresh("<meta http-equiv=refresh content=\"5;https://b.test/\">").as_deref(), Some("https://b.test/"));
+        //plain reloads and other meta tags are not redirects
+        assert_eq!(meta_refresh("<meta http-equiv=\"refresh\" content=\"30\">"), None);
+        assert_eq!(meta_refresh("<meta name=\"description\" content=\"0; url=/x\">"), None);
+    }
+}
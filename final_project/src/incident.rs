@@ -0,0 +1,112 @@
+//incident tracking for urls that fail several rounds in a row
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{memory, DateTime, Utc, WebsiteStatus};
+
+//open incident for one url
+#[derive(Debug, Clone)]
+pub struct Incident {
+    pub url: Arc<str>,
+    pub opened: DateTime<Utc>,
+    pub failures: u32,
+    pub last_error: String,
+    pub path_report: Vec<String>,
+    //the url's configured note, filled in by the caller
+    pub note: Option<String>,
+}
+
+//state change produced by a result
+#[derive(Debug)]
+pub enum IncidentEvent {
+    Opened(Arc<str>),
+    Resolved(Incident),
+}
+
+//consecutive failure counts and open incidents
+#[derive(Debug)]
+pub struct IncidentTracker {
+    threshold: u32,
+    streaks: HashMap<Arc<str>, u32>,
+    open: HashMap<Arc<str>, Incident>,
+}
+
+impl IncidentTracker {
+    pub fn new(threshold: u32) -> Self {
+        Self { threshold: threshold.max(1), streaks: HashMap::new(), open: HashMap::new() }
+    }
+
+    //feed one result, returns an event when an incident opens or resolves
+    pub fn observe(&mut self, r: &WebsiteStatus) -> Option<IncidentEvent> {
+        if r.is_up() {
+            self.streaks.remove(&*r.url);
+            return self.open.remove(&*r.url).map(IncidentEvent::Resolved);
+        }
+
+        let streak = self.streaks.entry(r.url.clone()).or_insert(0);
+        *streak += 1;
+        let failures = *streak;
+        let error = describe_failure(r);
+
+        if let Some(inc) = self.open.get_mut(&*r.url) {
+            inc.failures = failures;
+            inc.last_error = error;
+            return None;
+        }
+        if failures < self.threshold {
+            return None;
+        }
+        self.open.insert(r.url.clone(), Incident {
+            url: r.url.clone(),
+            opened: r.timestamp,
+            failures,
+            last_error: error,
+            path_report: Vec::new(),
+            note: None,
+        });
+        Some(IncidentEvent::Opened(r.url.clone()))
+    }
+
+    //rough bytes held, for --memory-log
+    pub fn heap_bytes(&self) -> usize {
+        let open: usize = self.open.values().map(|i| i.last_error.capacity() + i.path_report.iter().map(String::capacity).sum::<usize>()).sum();
+        memory::table_bytes(&self.streaks) + memory::table_bytes(&self.open) + open
+    }
+
+    pub fn get_mut(&mut self, url: &str) -> Option<&mut Incident> {
+        self.open.get_mut(url)
+    }
+
+    pub fn open_incidents(&self) -> impl Iterator<Item = &Incident> {
+        self.open.values()
+    }
+}
+
+//short reason a check counted as down
+fn describe_failure(r: &WebsiteStatus) -> String {
+    match &r.status {
+        Ok(code) => format!("status {}", code),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+    use crate::{CheckError, ErrorKind};
+
+    #[test]
+    fn test_incident_opens_after_threshold_and_resolves() {
+        let mut t = IncidentTracker::new(2);
+        assert!(t.observe(&status_for("u", Ok(503), 1)).is_none());
+        assert!(matches!(t.observe(&status_for("u", Err(CheckError::new(ErrorKind::Transport, "boom")), 1)), Some(IncidentEvent::Opened(_))));
+        assert!(t.observe(&status_for("u", Ok(500), 1)).is_none());
+        assert_eq!(t.open_incidents().next().unwrap().failures, 3);
+        match t.observe(&status_for("u", Ok(200), 1)) {
+            Some(IncidentEvent::Resolved(inc)) => assert_eq!(inc.last_error, "status 500"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(t.observe(&status_for("u", Ok(500), 1)).is_none());
+    }
+}
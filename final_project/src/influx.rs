@@ -0,0 +1,72 @@
+//influxdb line protocol: one point per check, posted to the v2 write api
+use std::time::{Duration, UNIX_EPOCH};
+
+use url::Url;
+
+use crate::WebsiteStatus;
+
+pub const MEASUREMENT: &str = "sitewatch";
+
+//tag keys and values escape commas, equals signs and spaces
+fn tag(s: &str) -> String {
+    s.replace('\\', "\\\\").replace(',', "\\,").replace('=', "\\=").replace(' ', "\\ ")
+}
+
+fn string_field(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+//one line per check, millisecond timestamps
+pub fn lines(results: &[WebsiteStatus]) -> String {
+    let mut out = String::new();
+    for r in results {
+        let mut fields = vec![
+            format!("up={}i", r.is_up() as u8),
+            format!("response_ms={}i", r.response_time.as_millis()),
+            format!("retries={}i", r.retries),
+        ];
+        match &r.status {
+            Ok(code) => fields.push(format!("status={}i", code)),
+            Err(e) => fields.push(format!("error={}", string_field(&e.message))),
+        }
+        if let Some(tcp) = r.tcp_connect { fields.push(format!("tcp_ms={}i", tcp.as_millis())); }
+        if let Some(ms) = r.clock_offset_ms { fields.push(format!("clock_offset_ms={}i", ms)); }
+        let ts_ms = r.timestamp.as_system_time().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        out.push_str(&format!("{},url={} {} {}\n", MEASUREMENT, tag(&r.url), fields.join(","), ts_ms));
+    }
+    out
+}
+
+//write endpoint for a server base url like http://influx:8086
+pub fn write_url(base: &str, bucket: &str) -> Result<String, String> {
+    let mut url = Url::parse(base).map_err(|e| format!("invalid influx url: {}", e))?;
+    url.set_path("/api/v2/write");
+    url.query_pairs_mut().append_pair("bucket", bucket).append_pair("precision", "ms");
+    Ok(url.into())
+}
+
+pub fn write(endpoint: &str, token: Option<&str>, body: &str, timeout: Duration) -> Result<(), String> {
+    if body.is_empty() { return Ok(()); }
+    let mut req = ureq::post(endpoint).timeout(timeout).set("Content-Type", "text/plain; charset=utf-8");
+    if let Some(token) = token { req = req.set("Authorization", &format!("Token {}", token)); }
+    req.send_string(body).map(|_| ()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+    use crate::{CheckError, ErrorKind};
+
+    #[test]
+    fn test_line_protocol() {
+        let mut ok = status_for("http://a/x,y=1 z", Ok(200), 12);
+        ok.tcp_connect = Some(Duration::from_millis(3));
+        let down = status_for("http://b/", Err(CheckError::new(ErrorKind::Transport, "said \"no\"")), 5);
+        let body = lines(&[ok, down]);
+        let rows: Vec<&str> = body.lines().collect();
+        assert!(rows[0].starts_with("sitewatch,url=http://a/x\\,y\\=1\\ z up=1i,response_ms=12i,retries=0i,status=200i,tcp_ms=3i "));
+        assert!(rows[1].starts_with("sitewatch,url=http://b/ up=0i,response_ms=5i,retries=0i,error=\"said \\\"no\\\"\" "));
+        assert_eq!(write_url("http://influx:8086/", "ops lat").unwrap(), "http://influx:8086/api/v2/write?bucket=ops+lat&precision=ms");
+    }
+}
@@ -0,0 +1,95 @@
+//json through serde_json: strings escaped for the hand-laid output lines, documents read for body checks, oauth and hooks
+pub use serde_json::Value;
+
+//quoted and escaped json string
+pub fn string(s: &str) -> String {
+    Value::from(s).to_string()
+}
+
+pub fn parse(text: &str) -> Result<Value, String> {
+    serde_json::from_str(text).map_err(|e| e.to_string())
+}
+
+//dotted path, array elements by index: data.items.0.name or data.items[0].name
+pub fn path<'a>(doc: &'a Value, path: &str) -> Option<&'a Value> {
+    let pointer: String = path.replace('[', ".").replace(']', "").split('.').filter(|p| !p.is_empty())
+        .map(|p| format!("/{}", p.replace('~', "~0").replace('/', "~1")))
+        .collect();
+    doc.pointer(&pointer)
+}
+
+//scalars as they read in a flag: strings unquoted, anything else as json
+pub fn text(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn kind(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+//one --expect-json PATH=VALUE condition
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonCheck {
+    pub path: String,
+    pub want: String,
+}
+
+impl JsonCheck {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (path, want) = s.split_once('=').ok_or_else(|| format!("expected PATH=VALUE, got '{}'", s))?;
+        if path.trim().is_empty() { return Err("empty json path".into()); }
+        Ok(Self { path: path.trim().to_string(), want: want.trim().to_string() })
+    }
+
+    //mismatch in words, values compared as they print
+    pub fn mismatch(&self, doc: &Value) -> Option<String> {
+        match path(doc, &self.path) {
+            None => Some(format!("json {} missing, expected {}", self.path, self.want)),
+            Some(v @ (Value::Array(_) | Value::Object(_))) => Some(format!("json {} is {}, expected {}", self.path, kind(v), self.want)),
+            Some(v) if text(v) == self.want => None,
+            Some(v) => Some(format!("json {} is {}, expected {}", self.path, text(v), self.want)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_escaping() {
+        assert_eq!(string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+        assert_eq!(parse(&string("caf\u{e9} \u{1}")).unwrap(), Value::from("caf\u{e9} \u{1}"));
+    }
+
+    #[test]
+    fn test_parse_and_check() {
+        let doc = parse(r#" {"status": "ok", "data": {"health": "green", "nodes": [{"up": true}, {"up": false, "load": 0.5}], "count": 3},
+            "note": "caf\u00e9 \ud83d\ude00", "none": null, "a/b": 1} "#).unwrap();
+        assert_eq!(path(&doc, "data.nodes[1].load"), Some(&Value::from(0.5)));
+        assert_eq!(path(&doc, "data.nodes.0.up"), Some(&Value::Bool(true)));
+        assert_eq!(path(&doc, "a/b"), Some(&Value::from(1)));
+        assert_eq!(text(path(&doc, "note").unwrap()), "café 😀");
+        let check = |s: &str| JsonCheck::parse(s).unwrap().mismatch(&doc);
+        assert_eq!(check("status=ok"), None);
+        assert_eq!(check("data.count=3"), None);
+        assert_eq!(check("none=null"), None);
+        assert_eq!(check("data.health=red").unwrap(), "json data.health is green, expected red");
+        assert_eq!(check("data.nodes=1").unwrap(), "json data.nodes is an array, expected 1");
+        assert_eq!(check("data.nodes[5].up=true").unwrap(), "json data.nodes[5].up missing, expected true");
+        assert!(JsonCheck::parse("nope").is_err());
+        for bad in ["{\"a\":}", "[1,]", "inf", "{\"a\" 1}", "\"open", "1 2", "tru"] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//smtp:// and imap:// banner checks, optionally one EHLO/CAPABILITY round trip
+use std::io::{BufRead, BufReader, Write};
+use std::time::Duration;
+
+use url::Url;
+
+use crate::{CheckError, Network, PROBE_OK};
+
+//status for an smtp:// or imap:// url: the smtp reply code, or PROBE_OK for imap
+pub fn check(net: &Network, url: &str, handshake: bool, timeout: Duration) -> Result<u16, CheckError> {
+    let parsed = Url::parse(url).map_err(|e| CheckError::transport(format!("invalid url: {}", e)))?;
+    match parsed.scheme() {
+        "smtp" => smtp(net, &parsed, handshake, timeout),
+        "imap" => imap(net, &parsed, handshake, timeout),
+        other => Err(CheckError::transport(format!("unsupported scheme '{}'", other))),
+    }
+}
+
+//multi-line smtp reply: "250-a" ... "250 z", code and last line's text
+fn smtp_reply(reader: &mut impl BufRead) -> Result<(u16, String), CheckError> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(CheckError::transport)? == 0 {
+            return Err(CheckError::transport("connection closed mid-reply"));
+        }
+        let line = line.trim_end();
+        let code: u16 = line.get(..3).and_then(|c| c.parse().ok()).ok_or_else(|| CheckError::protocol(format!("not an smtp reply: '{}'", line)))?;
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok((code, line.get(4..).unwrap_or_default().to_string()));
+        }
+    }
+}
+
+fn smtp(net: &Network, url: &Url, handshake: bool, timeout: Duration) -> Result<u16, CheckError> {
+    let tcp = net.connect_tcp(url, Some(25), timeout).map_err(CheckError::transport)?;
+    let mut writer = tcp.try_clone().map_err(CheckError::transport)?;
+    let mut reader = BufReader::new(tcp);
+
+    let (mut code, text) = smtp_reply(&mut reader)?;
+    if code != 220 {
+        return Err(CheckError::protocol(format!("smtp greeting {} {}", code, text)));
+    }
+    if handshake {
+        writer.write_all(b"EHLO sitewatch.invalid\r\n").map_err(CheckError::transport)?;
+        let (ehlo, text) = smtp_reply(&mut reader)?;
+        if ehlo != 250 {
+            return Err(CheckError::protocol(format!("smtp EHLO answered {} {}", ehlo, text)));
+        }
+        code = ehlo;
+    }
+    let _ = writer.write_all(b"QUIT\r\n");
+    Ok(code)
+}
+
+fn imap(net: &Network, url: &Url, handshake: bool, timeout: Duration) -> Result<u16, CheckError> {
+    let tcp = net.connect_tcp(url, Some(143), timeout).map_err(CheckError::transport)?;
+    let mut writer = tcp.try_clone().map_err(CheckError::transport)?;
+    let mut reader = BufReader::new(tcp);
+    let mut line = String::new();
+
+    if reader.read_line(&mut line).map_err(CheckError::transport)? == 0 {
+        return Err(CheckError::transport("connection closed before the greeting"));
+    }
+    let greeting = line.trim_end().to_string();
+    if !(greeting.starts_with("* OK") || greeting.starts_with("* PREAUTH")) {
+        return Err(CheckError::protocol(format!("imap greeting '{}'", greeting)));
+    }
+    if handshake {
+        writer.write_all(b"sw1 CAPABILITY\r\n").map_err(CheckError::transport)?;
+        //untagged lines until our tag comes back
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).map_err(CheckError::transport)? == 0 {
+                return Err(CheckError::transport("connection closed during CAPABILITY"));
+            }
+            if let Some(rest) = line.trim_end().strip_prefix("sw1 ") {
+                if !rest.starts_with("OK") {
+                    return Err(CheckError::protocol(format!("imap CAPABILITY answered '{}'", rest)));
+                }
+                break;
+            }
+        }
+    }
+    let _ = writer.write_all(b"sw2 LOGOUT\r\n");
+    Ok(PROBE_OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorKind;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    //scripted server: greeting, then one canned answer per command read
+    fn serve(greeting: &'static str, answers: &'static [&'static str]) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let _ = s.write_all(greeting.as_bytes());
+            for a in answers {
+                let mut buf = [0u8; 256];
+                if s.read(&mut buf).unwrap_or(0) == 0 { return; }
+                let _ = s.write_all(a.as_bytes());
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_smtp_and_imap() {
+        let t = Duration::from_secs(2);
+        let port = serve("220 mx ESMTP\r\n", &["250-mx hello\r\n250 STARTTLS\r\n"]);
+        assert_eq!(check(&Network::default(), &format!("smtp://127.0.0.1:{}", port), true, t), Ok(250));
+
+        let port = serve("554 no service\r\n", &[]);
+        let err = check(&Network::default(), &format!("smtp://127.0.0.1:{}", port), false, t).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Protocol);
+
+        let port = serve("* OK IMAP4rev1 ready\r\n", &["* CAPABILITY IMAP4rev1\r\nsw1 OK done\r\n"]);
+        assert_eq!(check(&Network::default(), &format!("imap://127.0.0.1:{}", port), true, t), Ok(PROBE_OK));
+
+        let port = serve("* BYE too busy\r\n", &[]);
+        assert!(check(&Network::default(), &format!("imap://127.0.0.1:{}", port), false, t).is_err());
+    }
+}
@@ -1,129 +1,666 @@
 // imports
-use std::io;
-use std::sync::{mpsc, Arc, Mutex};
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use std::time::{Duration, Instant, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::{env, fs};
 
-//allows struct signature for time
-#[allow(non_camel_case_types)]
-mod chrono_shim {
-    use std::marker::PhantomData;
+#[cfg(feature = "bench")]
+mod bench;
+mod console;
 
-    #[derive(Clone, Copy, Debug)]
-    pub struct Utc;
+use sitewatch::alerts::{Alert, Alerter, Channel, Silences};
+use sitewatch::checklog::{self, CheckLog, FsyncPolicy, LogFormat};
+use sitewatch::db::Db;
+use sitewatch::filter::{Only, ResultFilter};
+use sitewatch::hosts::HostPolicy;
+use sitewatch::json::{self, JsonCheck};
+use sitewatch::manifest::Manifest;
+use sitewatch::oauth::OAuth;
+use sitewatch::transaction::Transaction;
+use sitewatch::change::ChangeDetector;
+use sitewatch::memory::{self, MemoryWatch};
+use sitewatch::incident::{Incident, IncidentEvent, IncidentTracker};
+use sitewatch::cron::CronExpr;
+use sitewatch::scheduler::{self, Schedule, Scheduler};
+use sitewatch::pattern::{BodyCheck, Regex};
+use sitewatch::statsd::StatsdSink;
+use sitewatch::ab::Comparison;
+use sitewatch::{canary, circuit, conf, duration, encoding, hook, gantt, html, influx, report, summary, template, text, trace, urlfile};
+use sitewatch::{fleet_summary_json, fleet_uptime, path_report, run_once_with, weighted_uptime, wilson_interval, z_for_confidence};
+use sitewatch::{retry_budget_warning, retry_pct, retry_usage};
+use sitewatch::{Config, ErrorKind, ExpectStatus, HttpVersion, RetryTiming, TlsFiles, OutputFormat, RunError, Stats, WebsiteStatus, DEFAULT_CACHE_BUST_PARAM, PROBE_OK};
 
-    //keep generic type
-    #[derive(Clone, Copy, Debug)]
-    pub struct DateTime<T> {
-        pub(crate) inner: std::time::SystemTime,
-        pub(crate) _marker: PhantomData<T>,
-    }
-
-    //get current time
-    impl<T> DateTime<T> {
-        pub fn now() -> Self {
-            Self { inner: std::time::SystemTime::now(), _marker: PhantomData }
-        }
-        pub fn as_system_time(&self) -> std::time::SystemTime { self.inner }
-    }
+//command-line flags to configuration
+fn parse_args() -> Result<Config, String> {
+    parse_args_from(env::args().skip(1))
+}
 
-    impl<T> From<std::time::SystemTime> for DateTime<T> {
-        fn from(st: std::time::SystemTime) -> Self {
-            Self { inner: st, _marker: PhantomData }
+//--config files become flags placed before the command line, so later flags win;
+//--profile picks the [profile.NAME] overlay of every file
+fn with_config_files(args: Vec<String>) -> Result<Vec<String>, String> {
+    let profile = args.iter().position(|a| a == "--profile")
+        .map(|i| args.get(i + 1).cloned().ok_or("--profile requires a name"))
+        .transpose()?;
+    let mut from_files = Vec::new();
+    let mut rest = Vec::new();
+    let mut files = 0;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg != "--config" {
+            rest.push(arg);
+            continue;
         }
+        let path = args.next().ok_or("--config requires a path")?;
+        let text = fs::read_to_string(&path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+        let entries = conf::parse(&text)
+            .and_then(|entries| conf::select_profile(&entries, profile.as_deref()))
+            .map_err(|e| format!("{}: {}", path, e))?;
+        from_files.extend(conf::to_args(&entries).map_err(|e| format!("{}: {}", path, e))?);
+        files += 1;
     }
+    if profile.is_some() && files == 0 { return Err("--profile needs a --config file".into()); }
+    from_files.extend(rest);
+    Ok(from_files)
 }
-use chrono_shim::{DateTime, Utc};
-
-//runtime from flags
-#[derive(Debug, Clone)]
-struct Config {
-    workers: usize,
-    timeout: Duration,
-    retries: u32,
-    period_secs: u64, 
-    header_checks: Vec<(String, String)>, 
-    urls: Vec<String>,
-}
-
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            workers: 50,
-            timeout: Duration::from_millis(5000),
-            retries: 0,
-            period_secs: 0,
-            header_checks: Vec::new(),
-            urls: Vec::new(),
-        }
-    }
+
+//--oauth-* flags, checked together once all are read
+#[derive(Default)]
+struct OAuthFlags {
+    token_url: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    scope: Option<String>,
+    hosts: Option<String>,
 }
 
-//command-line flags to configuration
-fn parse_args() -> Result<Config, String> {
+fn parse_args_from(args: impl Iterator<Item = String>) -> Result<Config, String> {
     let mut cfg = Config::default();
-    let mut args = env::args().skip(1);
+    let mut header_checks = Vec::new();
+    let mut request_headers = Vec::new();
+    let mut hosts = HostPolicy::default();
+    let mut oauth = OAuthFlags::default();
+    let mut circuit_after: Option<u32> = None;
+    let mut circuit_cooldown = circuit::DEFAULT_COOLDOWN;
+    let mut tags: Vec<String> = Vec::new();
+    let mut env_proxy = true;
+    let mut user_agent: Option<String> = None;
+    let effective = with_config_files(args.collect())?;
+    let mut want_manifest = false;
+    let mut args = effective.clone().into_iter();
 
     while let Some(arg) = args.next() {
         match arg.as_str() {
+            //only left over when a config file names another one
+            "--config" => return Err("--config cannot be used inside a config file".into()),
+            //already applied to the config files
+            "--profile" => { args.next(); }
             //set worker count
             "--workers" => {
                 let n = args.next().ok_or("--workers requires a value")?;
                 cfg.workers = n.parse().map_err(|_| "invalid --workers value")?;
             }
-            //set request timeout
-            "--timeout-ms" => {
-                let n = args.next().ok_or("--timeout-ms requires a value")?;
-                let ms: u64 = n.parse().map_err(|_| "invalid --timeout-ms value")?;
-                cfg.timeout = Duration::from_millis(ms);
+            //ci gate: end the run at the first down check
+            "--stop-on-first-failure" => cfg.stop_on_failure = true,
+            //one more look at failed urls before a round counts, so a momentary blip is not downtime
+            "--reverify-failures" => cfg.reverify_failures = true,
+            //cap on concurrent requests, whatever the worker count
+            "--max-inflight" => {
+                let n = args.next().ok_or("--max-inflight requires a value")?;
+                cfg.max_inflight = Some(n.parse().ok().filter(|n| *n > 0).ok_or("invalid --max-inflight value")?);
+            }
+            //politeness towards one domain with many paths in the url file
+            "--per-host-limit" => {
+                let n = args.next().ok_or("--per-host-limit requires a value")?;
+                cfg.per_host_limit = Some(n.parse().ok().filter(|n| *n > 0).ok_or("invalid --per-host-limit value")?);
             }
+            "--per-host-rps" => {
+                let n = args.next().ok_or("--per-host-rps requires a value")?;
+                let r: f64 = n.parse().map_err(|_| "invalid --per-host-rps value")?;
+                sitewatch::rps_spacing(r).map_err(|e| format!("--per-host-rps: {}", e))?;
+                cfg.per_host_rps = Some(r);
+            }
+            //set request timeout
+            "--timeout" | "--timeout-ms" => cfg.timeout = duration_arg(&arg, args.next(), MS)?,
             //set transport retries
             "--retries" => {
                 let n = args.next().ok_or("--retries requires a value")?;
                 cfg.retries = n.parse().map_err(|_| "invalid --retries value")?;
             }
-            //allows for periodic mode
-            "--period" => {
-                let n = args.next().ok_or("--period requires seconds")?;
-                cfg.period_secs = n.parse().map_err(|_| "invalid --period value")?;
+            //statuses worth another try, a single 502 from a load balancer blip is not downtime
+            "--retry-on" => {
+                let codes = args.next().ok_or("--retry-on requires status codes, e.g. 502,503,504")?;
+                let statuses = ExpectStatus::parse(&codes).map_err(|e| format!("--retry-on: {}", e))?;
+                if (100..400).any(|c| statuses.contains(c)) { return Err("--retry-on takes 4xx and 5xx statuses".into()); }
+                cfg.retry_on = Some(statuses);
+            }
+            //hundreds of urls on one dead host should not each wait out the timeout
+            "--circuit-breaker" => {
+                let n = args.next().ok_or("--circuit-breaker requires a number of failures")?;
+                circuit_after = Some(n.parse().ok().filter(|n| *n > 0).ok_or("invalid --circuit-breaker value")?);
+            }
+            "--circuit-cooldown" => circuit_cooldown = duration_arg(&arg, args.next(), SECS)?,
+            //first wait between retries, doubled for each further retry up to --backoff-max
+            "--backoff" => cfg.backoff.base = duration_arg(&arg, args.next(), MS)?,
+            "--backoff-max" => cfg.backoff.max = duration_arg(&arg, args.next(), MS)?,
+            //statuses that are known noise, kept out of uptime
+            "--ignore-status" => {
+                let codes = args.next().ok_or("--ignore-status requires status codes, e.g. 401,418")?;
+                cfg.ignore_status = Some(ExpectStatus::parse(&codes).map_err(|e| format!("--ignore-status: {}", e))?);
             }
+            //latency sla, slower up checks are reported as degraded
+            "--max-latency" | "--max-latency-ms" => cfg.max_latency = Some(duration_arg(&arg, args.next(), MS)?),
+            //allows for periodic mode
+            "--period" => cfg.period_secs = whole_secs(&arg, duration_arg(&arg, args.next(), SECS)?)?,
             //header validation
             "--header" => {
                 let kv = args.next().ok_or("--header requires KEY=VALUE")?;
                 let (k, v) = parse_header_kv(&kv).map_err(|e| format!("--header: {}", e))?;
-                cfg.header_checks.push((k, v));
+                header_checks.push((k, v));
+            }
+            //request header sent with every http check
+            "--send-header" => {
+                let kv = args.next().ok_or("--send-header requires KEY=VALUE")?;
+                request_headers.push(parse_header_kv(&kv).map_err(|e| format!("--send-header: {}", e))?);
+            }
+            //cdns and wafs that block ureq's default agent string answer 403
+            "--user-agent" => user_agent = Some(args.next().ok_or("--user-agent requires a string")?),
+            //content-delivery audit mode
+            "--encoding-audit" => cfg.encoding_audit = true,
+            //record the effective configuration at run start
+            "--manifest" => want_manifest = true,
+            //a/b mode: the b side is these flags on top of the rest
+            "--ab" => {
+                cfg.ab_flags = Some(args.next().ok_or("--ab requires the B flags, e.g. --ab '--send-header X-Cache=off'")?);
+            }
+            "--ab-rounds" => {
+                let n = args.next().ok_or("--ab-rounds requires a value")?;
+                cfg.ab_rounds = n.parse().ok().filter(|n| *n > 0).ok_or("invalid --ab-rounds value")?;
+            }
+            //fail on duplicate or oddly cased response headers
+            "--strict-headers" => cfg.strict_headers = true,
+            //negotiated tls details per https check
+            "--tls-info" => cfg.tls_info = true,
+            //dns/connect/tls/ttfb breakdown per http(s) check
+            "--timing" => cfg.timing = true,
+            //catch redirects to an error portal instead of following them silently
+            "--max-redirects" => {
+                let n = args.next().ok_or("--max-redirects requires a value")?;
+                cfg.max_redirects = n.parse().map_err(|_| "invalid --max-redirects value")?;
+            }
+            "--no-follow-redirects" => cfg.max_redirects = 0,
+            //retried checks: latency of the answering try, or the whole check
+            "--retry-timing" => {
+                cfg.retry_timing = Some(RetryTiming::parse(&args.next().ok_or("--retry-timing requires last or total")?)?);
+            }
+            //post-only health endpoints (graphql, rpc)
+            "--method" => {
+                cfg.method = sitewatch::parse_method(&args.next().ok_or("--method requires a method")?)?;
+            }
+            "--body" => {
+                let spec = args.next().ok_or("--body requires TEXT or @FILE")?;
+                cfg.body = Some(sitewatch::load_body(&spec)?);
+            }
+            "--content-type" => {
+                cfg.content_type = Some(args.next().ok_or("--content-type requires a media type")?);
+            }
+            //login-then-dashboard style flows, checked as txn://NAME
+            "--transaction" => {
+                let spec = args.next().ok_or("--transaction requires NAME=FILE")?;
+                let (name, path) = spec.split_once('=').ok_or("--transaction requires NAME=FILE")?;
+                if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '/') {
+                    return Err(format!("invalid transaction name '{}'", name));
+                }
+                let text = fs::read_to_string(path).map_err(|e| format!("failed to read transaction {}: {}", path, e))?;
+                let txn = Transaction::parse(&text).map_err(|e| format!("transaction {}: {}", path, e))?;
+                if cfg.transactions.insert(name.to_string(), Arc::new(txn)).is_some() {
+                    return Err(format!("transaction '{}' defined twice", name));
+                }
+                cfg.urls.push(format!("txn://{}", name).into());
+            }
+            //user enrichment: each check as json on the command's stdin, its json object merged back
+            "--on-check-hook" => {
+                cfg.check_hook = Some(args.next().ok_or("--on-check-hook requires a command")?);
+            }
+            //oauth2 client credentials for checks behind a token gateway
+            "--oauth-token-url" => oauth.token_url = Some(args.next().ok_or("--oauth-token-url requires a URL")?),
+            "--oauth-client-id" => oauth.client_id = Some(args.next().ok_or("--oauth-client-id requires an id")?),
+            "--oauth-client-secret" => oauth.client_secret = Some(args.next().ok_or("--oauth-client-secret requires a secret")?),
+            "--oauth-scope" => oauth.scope = Some(args.next().ok_or("--oauth-scope requires a scope")?),
+            "--oauth-hosts" => oauth.hosts = Some(args.next().ok_or("--oauth-hosts requires a comma separated list of hosts")?),
+            //legacy servers that break on http/1.1
+            "--http-version" => {
+                let v = args.next().ok_or("--http-version requires 1.0 or 1.1")?;
+                cfg.http_version = Some(HttpVersion::parse(&v)?);
+            }
+            //fail https checks whose certificate is about to expire
+            "--cert-warn-days" => {
+                let d = duration_arg(&arg, args.next(), Duration::from_secs(86_400))?;
+                //36h would quietly become 1 day, 12h would switch the warning off
+                if d.as_nanos() % 86_400_000_000_000 != 0 { return Err(format!("{} takes whole days, e.g. 3 or 2w", arg)); }
+                cfg.cert_warn_days = Some((d.as_secs() / 86_400) as u32);
+            }
+            //soft redirects in html count as failures
+            "--meta-refresh" => cfg.meta_refresh = true,
+            //body has to contain the text / match the pattern
+            "--expect-body" => {
+                let text = args.next().ok_or("--expect-body requires text")?;
+                if text.is_empty() { return Err("--expect-body text is empty".into()); }
+                cfg.body_checks.push(BodyCheck::Contains(text));
+            }
+            "--expect-json" => {
+                let spec = args.next().ok_or("--expect-json requires PATH=VALUE")?;
+                cfg.json_checks.push(JsonCheck::parse(&spec).map_err(|e| format!("invalid --expect-json: {}", e))?);
+            }
+            "--expect-body-regex" => {
+                let re = args.next().ok_or("--expect-body-regex requires a pattern")?;
+                cfg.body_checks.push(BodyCheck::Matches(Regex::new(&re).map_err(|e| format!("invalid --expect-body-regex: {}", e))?));
+            }
+            //stream:// limits
+            "--stream-first-byte" | "--stream-first-byte-ms" => cfg.stream_first_byte = duration_arg(&arg, args.next(), MS)?,
+            "--stream-duration" | "--stream-duration-ms" => cfg.stream_read_for = duration_arg(&arg, args.next(), MS)?,
+            "--stream-min-bps" => {
+                let n = args.next().ok_or("--stream-min-bps requires a value")?;
+                cfg.stream_min_bps = Some(n.parse().map_err(|_| "invalid --stream-min-bps value")?);
+            }
+            //how old the newest item of a feed:// check may be
+            "--feed-max-age" => cfg.feed_max_age = duration_arg(&arg, args.next(), Duration::from_secs(3600))?,
+            //EHLO / CAPABILITY after the smtp:// and imap:// greeting
+            "--mail-handshake" => cfg.mail_handshake = true,
+            //ping/pong after the ws:// upgrade
+            "--ws-ping" => cfg.ws_ping = true,
+            //log in to ftp:// servers, anonymously unless the url has credentials
+            "--ftp-login" => cfg.ftp_login = true,
+            //allowed clock drift for ntp://
+            "--ntp-max-offset" | "--ntp-max-offset-ms" => cfg.ntp_max_offset = duration_arg(&arg, args.next(), MS)?,
+            //measure raw tcp connect time per url
+            "--tcp-latency" => cfg.tcp_latency = true,
+            //record html/xml page titles
+            "--titles" => cfg.titles = true,
+            //random query parameter so caches are bypassed
+            "--cache-bust" => {
+                cfg.cache_bust.get_or_insert_with(|| DEFAULT_CACHE_BUST_PARAM.to_string());
+            }
+            "--cache-bust-param" => {
+                cfg.cache_bust = Some(args.next().ok_or("--cache-bust-param requires a name")?);
+            }
+            //time the first bytes of the body, not just the headers
+            "--sample-bytes" => {
+                let n = args.next().ok_or("--sample-bytes requires a value")?;
+                cfg.sample_bytes = Some(n.parse().map_err(|_| "invalid --sample-bytes value")?);
+            }
+            //large downloads checked often: status and headers without the payload
+            "--head-only" => cfg.head_only = true,
+            //defacement or an unannounced deploy: bodies hashed, a different hash than last round is reported
+            "--detect-changes" => cfg.detect_changes = true,
+            //gzip keeps regressing on the cdn and only the bandwidth bill tells
+            "--expect-compression" => cfg.expect_compression = true,
+            //consecutive failed rounds before an incident opens
+            "--incident-after" => {
+                let n = args.next().ok_or("--incident-after requires a value")?;
+                cfg.incident_after = n.parse().map_err(|_| "invalid --incident-after value")?;
+            }
+            //probe the network path when an incident opens
+            "--traceroute" => cfg.traceroute = true,
+            //local remediation without a webhook relay
+            "--on-down" => cfg.on_down = Some(args.next().ok_or("--on-down requires a command")?),
+            "--on-recover" => cfg.on_recover = Some(args.next().ok_or("--on-recover requires a command")?),
+            "--traceroute-hops" => {
+                let n = args.next().ok_or("--traceroute-hops requires a value")?;
+                cfg.traceroute_hops = n.parse().map_err(|_| "invalid --traceroute-hops value")?;
+            }
+            //per-url weight for uptime scoring
+            "--weight" => {
+                let kv = args.next().ok_or("--weight requires URL=WEIGHT")?;
+                let (url, w) = parse_weight(&kv).map_err(|e| format!("--weight: {}", e))?;
+                cfg.weights.insert(url, w);
+            }
+            //statuses that count as up for one url
+            "--expect-status" => {
+                let kv = args.next().ok_or("--expect-status requires URL=CODES")?;
+                let (url, codes) = kv.rsplit_once('=').ok_or("--expect-status requires URL=CODES")?;
+                let expect = ExpectStatus::parse(codes).map_err(|e| format!("--expect-status: {}", e))?;
+                cfg.url_options.entry(url.trim().to_string()).or_default().expect = Some(expect);
+            }
+            //checks too custom for the flags: a condition on status, headers, latency and body
+            "--assert" => {
+                let url = args.next().ok_or("--assert requires a URL and an expression")?;
+                let expr = args.next().ok_or("--assert requires a URL and an expression")?;
+                let script = sitewatch::script::Script::parse(&expr).map_err(|e| format!("--assert: {}", e))?;
+                cfg.url_options.entry(url.trim().to_string()).or_default().asserts.push(script);
+            }
+            //the spa breaks on a refused preflight while plain GETs still answer 200
+            "--cors" => {
+                let url = args.next().ok_or("--cors requires a URL and an origin")?;
+                let origin = args.next().ok_or("--cors requires a URL and an origin")?;
+                let opts = cfg.url_options.entry(url.trim().to_string()).or_default();
+                opts.cors.get_or_insert_with(Default::default).origin = origin.trim().to_string();
+            }
+            //table (default) or json on stdout
+            "--output" => {
+                let f = args.next().ok_or("--output requires table or json")?;
+                cfg.output = OutputFormat::parse(&f)?;
+            }
+            //json rounds appended to a file
+            "--output-file" => {
+                cfg.output_file = Some(args.next().ok_or("--output-file requires a path")?);
+            }
+            //weeks-long unattended runs: the monitor's own memory, logged so slow growth shows
+            "--memory-log" => {
+                cfg.memory_log = Some(args.next().ok_or("--memory-log requires a path")?);
+            }
+            "--memory-interval" => {
+                cfg.memory_interval = Some(duration_arg(&arg, args.next(), SECS)?).filter(|d| !d.is_zero()).ok_or("invalid --memory-interval value")?;
+            }
+            //worker schedule of each round, as text or an html page
+            "--gantt" => cfg.gantt = true,
+            //a slow url no longer holds back every row before it
+            "--live" => cfg.live = true,
+            "--final-table" => {
+                cfg.live = true;
+                cfg.final_table = true;
+            }
+            //long query strings and idn urls wrap the table otherwise
+            "--max-url-width" => {
+                let n = args.next().ok_or("--max-url-width requires a value")?;
+                let n = n.parse().ok().filter(|n| *n >= text::MIN_URL_WIDTH).ok_or_else(|| format!("invalid --max-url-width value, at least {}", text::MIN_URL_WIDTH))?;
+                cfg.max_url_width = Some(n);
+            }
+            //self-contained html report for stakeholders
+            "--report" => {
+                cfg.report_file = Some(args.next().ok_or("--report requires a path")?);
+            }
+            "--gantt-html" => {
+                cfg.gantt_html = Some(args.next().ok_or("--gantt-html requires a path")?);
+            }
+            //print only failing, degraded and/or changed checks
+            "--only" => {
+                let v = args.next().ok_or("--only requires failures, degraded or changed")?;
+                cfg.only = Only::parse_list(&v)?;
+            }
+            //binomial confidence intervals on uptime
+            "--confidence" => {
+                let v = args.next().ok_or("--confidence requires a level")?;
+                let level: f64 = v.parse().map_err(|_| "invalid --confidence level")?;
+                if z_for_confidence(level).is_none() { return Err("--confidence must be one of 80, 90, 95, 98, 99, 99.9".into()); }
+                cfg.confidence = Some(level);
+            }
+            //warn when a round's retries exceed this fraction of its requests
+            "--retry-budget" => {
+                let v = args.next().ok_or("--retry-budget requires a fraction")?;
+                cfg.retry_budget = Some(v.parse().ok().filter(|f| (0.0..=1.0).contains(f)).ok_or("--retry-budget must be a fraction between 0 and 1")?);
+            }
+            //compact current state, replaced after every round
+            "--summary-file" => {
+                cfg.summary_file = Some(args.next().ok_or("--summary-file requires a path")?);
+            }
+            //one-line fleet summary per round for wallboards
+            "--fleet-file" => {
+                cfg.fleet_file = Some(args.next().ok_or("--fleet-file requires a path")?);
+            }
+            "--fleet-url" => {
+                cfg.fleet_url = Some(args.next().ok_or("--fleet-url requires a URL")?);
+            }
+            //influxdb line-protocol sink, written after each round
+            "--influx-url" => {
+                cfg.influx_url = Some(args.next().ok_or("--influx-url requires a URL")?);
+            }
+            "--influx-bucket" => {
+                cfg.influx_bucket = args.next().ok_or("--influx-bucket requires a name")?;
+            }
+            "--influx-token" => {
+                cfg.influx_token = Some(args.next().ok_or("--influx-token requires a token")?);
+            }
+            //a trace per check, with a span per attempt
+            "--otlp-url" => {
+                cfg.otlp_url = Some(args.next().ok_or("--otlp-url requires a URL")?);
+            }
+            "--trace-file" => {
+                cfg.trace_file = Some(args.next().ok_or("--trace-file requires a path")?);
+            }
+            //corporate networks only reach the internet through a proxy
+            "--proxy" => cfg.proxy = Some(sitewatch::parse_proxy(&args.next().ok_or("--proxy requires a URL")?)?),
+            "--no-proxy" => env_proxy = false,
+            //dns overrides in /etc/hosts format
+            "--hosts-file" => {
+                cfg.hosts_file = Some(args.next().ok_or("--hosts-file requires a path")?);
+            }
+            //trust internal cas on top of the bundled roots
+            "--ca-bundle" => {
+                cfg.ca_bundle = Some(args.next().ok_or("--ca-bundle requires a pem file")?);
+            }
+            //mutual tls: certificate (pem, may hold the key too) and its key
+            "--client-cert" => {
+                cfg.client_cert = Some(args.next().ok_or("--client-cert requires a pem file")?);
+            }
+            "--client-key" => {
+                cfg.client_key = Some(args.next().ok_or("--client-key requires a pem file")?);
+            }
+            //no certificate verification, lab use only
+            "--insecure" => cfg.insecure = true,
+            //safety rails: hosts every check target has to match, and hosts none may
+            "--allow-hosts" => {
+                hosts.allow(&args.next().ok_or("--allow-hosts requires a comma separated list of hosts")?)?;
+            }
+            "--deny-hosts" => {
+                hosts.deny(&args.next().ok_or("--deny-hosts requires a comma separated list of hosts")?)?;
+            }
+            //dogstatsd metrics per check over udp
+            "--statsd" => {
+                cfg.statsd = Some(args.next().ok_or("--statsd requires host:port")?);
+            }
+            "--statsd-prefix" => {
+                cfg.statsd_prefix = args.next().ok_or("--statsd-prefix requires a name")?;
+            }
+            //per-check log, batched
+            "--log" => {
+                cfg.log_file = Some(args.next().ok_or("--log requires a path")?);
+            }
+            //csv rows, header once per file
+            "--csv" => {
+                cfg.csv_file = Some(args.next().ok_or("--csv requires a path")?);
+            }
+            //sqlite history of checks and rounds
+            "--db" => {
+                cfg.db_file = Some(args.next().ok_or("--db requires a path")?);
+            }
+            //durable jsonl of every result, flushed each round
+            "--history" => {
+                cfg.history_file = Some(args.next().ok_or("--history requires a path")?);
+            }
+            "--log-flush" | "--log-flush-ms" => cfg.log.flush_interval = duration_arg(&arg, args.next(), MS)?,
+            "--log-flush-bytes" => {
+                let n = args.next().ok_or("--log-flush-bytes requires a value")?;
+                cfg.log.flush_bytes = n.parse().map_err(|_| "invalid --log-flush-bytes value")?;
+            }
+            "--fsync" => {
+                let p = args.next().ok_or("--fsync requires never, flush or always")?;
+                cfg.log.fsync = FsyncPolicy::parse(&p).map_err(|e| format!("--fsync: {}", e))?;
+            }
+            //alert channels and rule thresholds
+            "--alert-console" => cfg.alert_channels.push(Channel::Console),
+            "--alert-webhook" => {
+                let url = args.next().ok_or("--alert-webhook requires a URL")?;
+                cfg.alert_channels.push(Channel::Webhook(url));
+            }
+            "--test-alerts" => cfg.test_alerts = true,
+            "--alert-latency" | "--alert-latency-ms" => cfg.alert_rules.latency_ms = Some(duration_arg(&arg, args.next(), MS)?.as_millis() as u64),
+            //canary judged against its prod counterpart
+            "--canary" => {
+                let prod = args.next().ok_or("--canary requires a prod URL and a canary URL")?;
+                let canary = args.next().ok_or("--canary requires a prod URL and a canary URL")?;
+                cfg.alert_rules.canaries.push(canary::pair(&prod, &canary).map_err(|e| format!("--canary: {}", e))?);
+            }
+            "--canary-window" => {
+                let n = args.next().ok_or("--canary-window requires a value")?;
+                cfg.alert_rules.canary.window = n.parse().map_err(|_| "invalid --canary-window value")?;
+            }
+            "--canary-error-margin" => {
+                let n = args.next().ok_or("--canary-error-margin requires a value")?;
+                cfg.alert_rules.canary.error_margin_pct = n.parse().map_err(|_| "invalid --canary-error-margin value")?;
+            }
+            "--canary-latency-margin" => {
+                let n = args.next().ok_or("--canary-latency-margin requires a value")?;
+                cfg.alert_rules.canary.latency_margin_pct = n.parse().map_err(|_| "invalid --canary-latency-margin value")?;
+            }
+            //one-off check at a future time
+            "--at" => {
+                let when = args.next().ok_or("--at requires a time and a URL")?;
+                let url = args.next().ok_or("--at requires a time and a URL")?;
+                let at = scheduler::parse_timestamp(&when).map_err(|e| format!("--at: {}", e))?;
+                for url in template::expand(&url)? { cfg.one_off.push((at, url)); }
+            }
+            //cron schedule for one url
+            "--cron" => {
+                let expr = args.next().ok_or("--cron requires an expression and a URL")?;
+                let url = args.next().ok_or("--cron requires an expression and a URL")?;
+                let expr = CronExpr::parse(&expr)?;
+                for url in template::expand(&url)? { cfg.cron.push((expr.clone(), url)); }
+            }
+            //tcp checks against local ports
+            "--ports" => {
+                let list = args.next().ok_or("--ports requires a comma separated list")?;
+                for port in list.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                    let port: u16 = port.parse().map_err(|_| format!("--ports: invalid port '{}'", port))?;
+                    cfg.urls.push(format!("tcp://localhost:{}", port).into());
+                }
             }
             //reads url from file
             "--file" => {
                 let path = args.next().ok_or("--file requires a path")?;
                 let content = fs::read_to_string(&path).map_err(|e| format!("failed to read {}: {}", path, e))?;
-                for line in content.lines() {
-                    let url = line.trim();
-                    if !url.is_empty() && !url.starts_with('#') {
-                        cfg.urls.push(url.to_string());
+                for entry in urlfile::parse(&content).map_err(|e| format!("{} {}", path, e))? {
+                    let before = cfg.urls.len();
+                    push_urls(&mut cfg.urls, &entry.url)?;
+                    for u in &cfg.urls[before..] {
+                        if let Some(note) = &entry.note { cfg.notes.insert(u.to_string(), note.clone()); }
+                        if let Some(opts) = &entry.options { cfg.url_options.insert(u.to_string(), opts.clone()); }
+                    }
+                    //schedule= entries run like --cron ones, not every round
+                    if let Some(expr) = entry.options.as_ref().and_then(|o| o.schedule.as_ref()) {
+                        let scheduled: Vec<_> = cfg.urls.drain(before..).collect();
+                        cfg.cron.extend(scheduled.into_iter().map(|u| (expr.clone(), u.to_string())));
                     }
                 }
             }
+            //one url file for every environment, tag=prod lines picked out
+            "--tags" => {
+                let list = args.next().ok_or("--tags requires a comma separated list of tags")?;
+                tags.extend(list.split(',').map(str::trim).filter(|t| !t.is_empty()).map(String::from));
+            }
             _ => {
                 if arg.starts_with('-') {
                     return Err(format!("unknown flag: {}", arg));
                 } else {
-                    cfg.urls.push(arg);
+                    push_urls(&mut cfg.urls, &arg)?;
                 }
             }
         }
     }
 
-    if cfg.urls.is_empty() {
+    cfg.header_checks = header_checks.into();
+    cfg.request_headers = request_headers.into();
+    if let Some(ua) = &user_agent { cfg.request_headers = sitewatch::with_user_agent(&cfg.request_headers, ua); }
+    if !tags.is_empty() {
+        let options = &cfg.url_options;
+        let tagged = |u: &str| options.get(u).is_some_and(|o| o.tags.iter().any(|t| tags.contains(t)));
+        cfg.urls.retain(|u| tagged(u));
+        cfg.cron.retain(|(_, u)| tagged(u));
+        if cfg.urls.is_empty() && cfg.cron.is_empty() { return Err(format!("--tags {}: no URL carries these tags", tags.join(","))); }
+    }
+
+    //both sides of a canary pair are checked every round
+    for pair in &cfg.alert_rules.canaries {
+        for url in [&pair.prod, &pair.canary] {
+            if !cfg.urls.iter().any(|u| **u == **url) { cfg.urls.push(url.as_str().into()); }
+        }
+    }
+    if !cfg.alert_rules.canaries.is_empty() && cfg.alert_channels.is_empty() {
+        return Err("--canary needs at least one alert channel (--alert-console, --alert-webhook)".into());
+    }
+
+    //a url file edited to point elsewhere stops the run here, before anything is requested
+    let scheduled = cfg.one_off.iter().map(|(_, u)| u.as_str()).chain(cfg.cron.iter().map(|(_, u)| u.as_str()));
+    let steps = cfg.transactions.values().flat_map(|t| t.steps.iter().map(|s| s.url.as_str()));
+    for url in cfg.urls.iter().map(|u| &**u).chain(scheduled).filter(|u| !u.starts_with("txn://")).chain(steps) {
+        hosts.check(url)?;
+    }
+    check_body(&cfg.method, cfg.body.is_some(), cfg.content_type.is_some()).map_err(|e| format!("--body: {}", e))?;
+    for (url, o) in &cfg.url_options {
+        let method = o.method.as_deref().unwrap_or(&cfg.method);
+        let body = o.body.is_some() || cfg.body.is_some();
+        check_body(method, body, o.content_type.is_some() || cfg.content_type.is_some()).map_err(|e| format!("{}: {}", url, e))?;
+    }
+    if cfg.head_only {
+        let body_flags = [
+            (cfg.titles, "--titles"), (cfg.sample_bytes.is_some(), "--sample-bytes"), (!cfg.body_checks.is_empty(), "--expect-body"),
+            (!cfg.json_checks.is_empty(), "--expect-json"), (cfg.meta_refresh, "--meta-refresh"), (cfg.http_version.is_some(), "--http-version"),
+            (cfg.method != "GET" || cfg.body.is_some() || cfg.url_options.values().any(|o| o.method.is_some() || o.body.is_some()), "--method/--body"),
+            (cfg.url_options.values().flat_map(|o| &o.asserts).any(|s| s.uses_body()), "an assertion reading body"),
+            (cfg.detect_changes, "--detect-changes"),
+            (cfg.expect_compression, "--expect-compression"),
+        ];
+        if let Some((_, flag)) = body_flags.iter().find(|(on, _)| *on) {
+            return Err(format!("--head-only reads no body, it cannot be combined with {}", flag));
+        }
+    }
+    if cfg.stop_on_failure && cfg.reverify_failures {
+        return Err("--reverify-failures cannot be combined with --stop-on-first-failure".into());
+    }
+    if cfg.stop_on_failure && (cfg.period_secs > 0 || cfg.scheduled_count() > 0 || cfg.ab_flags.is_some()) {
+        return Err("--stop-on-first-failure is for single runs, not --period, --at, --cron or --ab".into());
+    }
+    if cfg.live && cfg.output == OutputFormat::Json {
+        return Err("--live prints table rows, it cannot be combined with --output json".into());
+    }
+    if cfg.client_key.is_some() && cfg.client_cert.is_none() {
+        return Err("--client-key needs --client-cert".into());
+    }
+    if cfg.influx_token.is_none() { cfg.influx_token = env::var("INFLUX_TOKEN").ok(); }
+    if cfg.proxy.is_none() && env_proxy { cfg.env_proxy = proxy_from_env(|name| env::var(name).ok())?.map(Arc::new); }
+    match oauth.token_url {
+        Some(url) => {
+            let id = oauth.client_id.ok_or("--oauth-token-url needs --oauth-client-id")?;
+            let secret = oauth.client_secret.or_else(|| env::var("OAUTH_CLIENT_SECRET").ok()).ok_or("--oauth-token-url needs --oauth-client-secret or $OAUTH_CLIENT_SECRET")?;
+            cfg.oauth = Some(Arc::new(OAuth::new(&url, &id, &secret, oauth.scope.as_deref(), oauth.hosts.as_deref())?));
+        }
+        None if oauth.client_id.is_some() || oauth.client_secret.is_some() || oauth.scope.is_some() || oauth.hosts.is_some() => {
+            return Err("--oauth-client-id, --oauth-client-secret, --oauth-scope and --oauth-hosts need --oauth-token-url".into());
+        }
+        None => {}
+    }
+    cfg.circuit = circuit_after.map(|n| Arc::new(circuit::Breaker::new(n, circuit_cooldown)));
+    if cfg.urls.is_empty() && cfg.scheduled_count() == 0 && !cfg.test_alerts {
         return Err("no URLs provided. Pass them as args or with --file path".into());
     }
+    if cfg.test_alerts && cfg.alert_channels.is_empty() {
+        return Err("--test-alerts needs at least one alert channel (--alert-console, --alert-webhook)".into());
+    }
 
-    cfg.workers = cfg.workers.max(1).min(cfg.urls.len().max(1));
+    cfg.workers = cfg.workers.max(1).min((cfg.urls.len() + cfg.scheduled_count()).max(1));
+    if want_manifest { cfg.manifest = Some(Manifest::new(&effective, cfg.urls.len() + cfg.scheduled_count())); }
     Ok(cfg)
 }
 
+//a body goes with a method that carries one, a content type with a body
+fn check_body(method: &str, body: bool, content_type: bool) -> Result<(), String> {
+    if body && matches!(method, "GET" | "HEAD") {
+        return Err(format!("a request body needs a method like POST or PUT, not {}", method));
+    }
+    if content_type && !body { return Err("a content type needs a request body".into()); }
+    Ok(())
+}
+
+//url entry after {a,b} / {01..16} expansion
+fn push_urls(urls: &mut Vec<Arc<str>>, entry: &str) -> Result<(), String> {
+    for url in template::expand(entry)? {
+        urls.push(url.into());
+    }
+    Ok(())
+}
+
 //header specification
 fn parse_header_kv(s: &str) -> Result<(String, String), &'static str> {
     let mut split = s.splitn(2, '=');
@@ -133,358 +670,941 @@ fn parse_header_kv(s: &str) -> Result<(String, String), &'static str> {
     Ok((k.to_string(), v.to_string()))
 }
 
-//result types and statistic collection
-#[derive(Debug, Clone)]
-struct WebsiteStatus {
-    url: String,
-    status: Result<u16, String>,
-    response_time: Duration,
-    timestamp: DateTime<Utc>,
-}
-
-#[derive(Debug, Clone)]
-struct Stats {
-    samples: u64,
-    ok: u64,
-    total_response: Duration,
-}
-
-impl Stats {
-    fn new() -> Self { Self { samples: 0, ok: 0, total_response: Duration::from_millis(0) } }
-    //update stats
-    fn record(&mut self, s: &WebsiteStatus) {
-        self.samples += 1;
-        if let Ok(code) = s.status { if (200..=399).contains(&code) { self.ok += 1; } }
-        self.total_response += s.response_time;
-    }
-    //average response time
-    fn avg_ms(&self) -> u128 {
-        if self.samples == 0 { 0 } else { (self.total_response.as_millis()) / (self.samples as u128) }
-    }
-    //percentage of good
-    fn uptime_pct(&self) -> f64 {
-        if self.samples == 0 { 0.0 } else { (self.ok as f64) * 100.0 / (self.samples as f64) }
-    }
-}
-
-//job type
-#[derive(Debug)]
-enum Job {
-    Check(String),
-}
-
-//wroker pool
-fn spawn_workers(
-    n: usize,
-    job_rx: Arc<Mutex<mpsc::Receiver<Job>>>,
-    result_tx: mpsc::Sender<WebsiteStatus>,
-    timeout: Duration,
-    retries: u32,
-    header_checks: Vec<(String, String)>,
-    shutdown: Arc<AtomicBool>,
-) -> Vec<thread::JoinHandle<()>> {
-    let mut handles = Vec::with_capacity(n);
-
-    for _ in 0..n {
-        let job_rx = job_rx.clone();
-        let result_tx = result_tx.clone();
-        let header_checks = header_checks.clone();
-        let shutdown = shutdown.clone();
-
-        //clocking http w/ timeouts
-        let agent = ureq::AgentBuilder::new()
-            .timeout_connect(timeout)
-            .timeout_read(timeout)
-            .timeout_write(timeout)
-            .build();
-
-        //recv job then run check then send result
-        let handle = thread::spawn(move || {
-            loop {
-                if shutdown.load(Ordering::Relaxed) { break; }
-                let job_opt = {
-                    let rx = job_rx.lock().unwrap();
-                    rx.recv().ok()
-                };
-                match job_opt {
-                    Some(Job::Check(url)) => {
-                        let status = check_once_with_retries(&agent, &url, retries, &header_checks);
-                        let _ = result_tx.send(status);
-                    }
-                    None => break, 
-                }
+//HTTP_PROXY for http:// urls, HTTPS_PROXY for https:// ones, NO_PROXY hosts direct; the lowercase names as curl takes them
+fn proxy_from_env(var: impl Fn(&str) -> Option<String>) -> Result<Option<sitewatch::EnvProxy>, String> {
+    let var = |names: [&'static str; 2]| names.into_iter().find_map(|n| var(n).filter(|v| !v.trim().is_empty()).map(|v| (n, v)));
+    let proxy = |names| var(names).map(|(name, value)| sitewatch::parse_proxy(value.trim()).map_err(|e| format!("${}: {}", name, e))).transpose();
+    let mut env = sitewatch::EnvProxy::default();
+    env.http = proxy(["HTTP_PROXY", "http_proxy"])?;
+    env.https = proxy(["HTTPS_PROXY", "https_proxy"])?;
+    if env.http.is_none() && env.https.is_none() { return Ok(None); }
+    if let Some((name, list)) = var(["NO_PROXY", "no_proxy"]) {
+        env.bypass(&list).map_err(|e| format!("${}: {}", name, e))?;
+    }
+    Ok(Some(env))
+}
+
+//bare numbers of the time flags, kept as they were before durations took units
+const MS: Duration = Duration::from_millis(1);
+const SECS: Duration = Duration::from_secs(1);
+
+//the value of a time flag: "2s", "5m", "1h30m", or a bare number in the flag's own unit
+fn duration_arg(flag: &str, value: Option<String>, bare: Duration) -> Result<Duration, String> {
+    let value = value.ok_or_else(|| format!("{} requires a duration, e.g. 30s or 5m", flag))?;
+    duration::parse(&value, bare).map_err(|e| format!("{}: {}", flag, e))
+}
+
+fn whole_secs(flag: &str, d: Duration) -> Result<u64, String> {
+    if d.subsec_nanos() != 0 { return Err(format!("{} takes whole seconds", flag)); }
+    Ok(d.as_secs())
+}
+
+//weight specification, split on the last '=' so query strings survive
+fn parse_weight(s: &str) -> Result<(String, f64), &'static str> {
+    let (url, w) = s.rsplit_once('=').ok_or("missing weight")?;
+    let url = url.trim();
+    if url.is_empty() { return Err("empty url"); }
+    let w: f64 = w.trim().parse().map_err(|_| "invalid weight")?;
+    if !(w >= 0.0 && w.is_finite()) { return Err("weight must be a non-negative number"); }
+    Ok((url.to_string(), w))
+}
+
+//result table; periodic runs pass their aggregates for the smoothed latency column
+fn print_results(results: &[WebsiteStatus], total: usize, agg: Option<&HashMap<Arc<str>, Stats>>, url_width: Option<usize>) {
+    let show_tcp = results.iter().any(|r| r.tcp_connect.is_some());
+    let ema = |r: &WebsiteStatus| agg.map(|a| {
+        let ms = a.get(&r.url).and_then(|s| s.ema_ms).map(|ms| format!("{:.0}", ms)).unwrap_or_else(|| "-".into());
+        format!("{:<7} | ", ms)
+    }).unwrap_or_default();
+    let ema_head = if agg.is_some() { format!("{:<7} | ", "ema ms") } else { String::new() };
+    if results.len() == total {
+        println!("\nResults ({} checks):", total);
+    } else {
+        println!("\nResults ({} of {} checks shown):", results.len(), total);
+    }
+    print_header(show_tcp, &ema_head);
+    for (i, r) in results.iter().enumerate() {
+        print_row(i + 1, r, show_tcp, &ema(r), url_width);
+    }
+}
+
+fn print_header(show_tcp: bool, ema_head: &str) {
+    if show_tcp {
+        println!("{:<5} | {:<8} | {:<7} | {}{:<7} | {:<13} | URL", "#", "Status", "ms", ema_head, "tcp ms", "ts(ms)");
+    } else {
+        println!("{:<5} | {:<8} | {:<7} | {}{:<13} | URL", "#", "Status", "ms", ema_head, "ts(ms)");
+    }
+    println!("{}", "-".repeat(100));
+}
+
+//a port that accepts reads as open, any other probe that passed as ok
+fn probe_ok(url: &str) -> &'static str {
+    if url.starts_with("tcp://") { "open" } else { "ok" }
+}
+
+//one check of the table, with its detail lines
+fn print_row(n: usize, r: &WebsiteStatus, show_tcp: bool, ema: &str, url_width: Option<usize>) {
+    let code_str = match r.status {
+        _ if r.is_degraded() => "DEGRADED".to_string(),
+        Err(ref e) if e.kind == ErrorKind::Circuit => "SKIPPED".to_string(),
+        Ok(PROBE_OK) => probe_ok(&r.url).to_string(),
+        Ok(c) => c.to_string(),
+        Err(_) => "ERR".to_string(),
+    };
+    let ts_ms = r.timestamp.as_system_time()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    if show_tcp {
+        let tcp_str = r.tcp_connect.map(|d| d.as_millis().to_string()).unwrap_or_else(|| "-".into());
+        println!("{:<5} | {:<8} | {:<7} | {}{:<7} | {:<13} | {}", n, code_str, r.response_time.as_millis(), ema, tcp_str, ts_ms, text::url_cell(&r.url, url_width));
+    } else {
+        println!("{:<5} | {:<8} | {:<7} | {}{:<13} | {}", n, code_str, r.response_time.as_millis(), ema, ts_ms, text::url_cell(&r.url, url_width));
+    }
+    if let Err(ref e) = r.status { println!("        ↳ error: {}", e); }
+    if r.ignored { println!("        ↳ ignored: status excluded from uptime (--ignore-status)"); }
+    if let (true, Some(max)) = (r.is_degraded(), r.latency_limit) {
+        let status = match r.status { Ok(PROBE_OK) => probe_ok(&r.url).to_string(), Ok(c) => c.to_string(), Err(_) => "ERR".to_string() };
+        println!("        ↳ degraded: status {}, {}ms over the {}ms limit", status, r.response_time.as_millis(), max.as_millis());
+    }
+    if let Some(ms) = r.clock_offset_ms { println!("        ↳ clock offset: {:+}ms", ms); }
+    match &r.tls {
+        Some(Ok(info)) => println!("        ↳ tls: {}", info),
+        Some(Err(e)) => println!("        ↳ tls: inspection failed: {}", e),
+        None => {}
+    }
+    if let Some(v) = r.http_version { println!("        ↳ pinned to {}", v); }
+    if let Some(to) = &r.final_url { println!("        ↳ redirected: {} -> {}", r.redirects.join(" -> "), to); }
+    if let Some(c) = &r.content { println!("        ↳ body: {}", c); }
+    match &r.compression {
+        Some(Ok(c)) => println!("        ↳ compression: {}", c),
+        Some(Err(e)) => println!("        ↳ compression: not measured: {}", e),
+        None => {}
+    }
+    if !r.meta.is_empty() {
+        let fields: Vec<String> = r.meta.iter().map(|(k, v)| format!("{}={}", k, json::text(v))).collect();
+        println!("        ↳ meta: {}", fields.join(", "));
+    }
+    match &r.phases {
+        Some(Ok(p)) => println!("        ↳ timing: {}", p),
+        Some(Err(e)) => println!("        ↳ timing: not measured: {}", e),
+        None => {}
+    }
+    if let Some(ref t) = r.title {
+        println!("        ↳ title: {}", t);
+        if let Some(why) = html::suspicious_title(t) { println!("        ↳ warning: title looks like an error page ({})", why.trim()); }
+    }
+}
+
+//live rows came in finishing order, the table after them follows the url list
+fn in_table_order(shown: &[WebsiteStatus], cfg: &Config) -> Vec<WebsiteStatus> {
+    let mut rows = shown.to_vec();
+    if cfg.live {
+        let order: HashMap<&str, usize> = cfg.urls.iter().enumerate().rev().map(|(i, u)| (&**u, i)).collect();
+        rows.sort_by_key(|r| order.get(&*r.url).copied().unwrap_or(usize::MAX));
+    }
+    rows
+}
+
+//(up, avg ms, uptime %) of a round, ignored checks left out of up and uptime
+fn round_stats(results: &[WebsiteStatus]) -> (usize, u128, f64) {
+    let total = counted(results) as f64;
+    let successes = results.iter().filter(|r| r.is_up() && !r.ignored).count();
+    let total_duration: Duration = results.iter().map(|r| r.response_time).sum();
+    let avg_ms = if results.is_empty() { 0 } else { total_duration.as_millis() / (results.len() as u128) };
+    let uptime = if total == 0.0 { 0.0 } else { (successes as f64) * 100.0 / total };
+    (successes, avg_ms, uptime)
+}
+
+//checks the uptime math uses
+fn counted(results: &[WebsiteStatus]) -> usize {
+    results.iter().filter(|r| !r.ignored).count()
+}
+
+//round statistics 
+fn print_round_stats(results: &[WebsiteStatus], cfg: &Config) {
+    let (successes, avg_ms, uptime) = round_stats(results);
+    let total = counted(results);
+    let ci = confidence_note(cfg, successes as u64, total as u64).map(|c| format!(", {}", c)).unwrap_or_default();
+    let (retries, checks) = retry_usage(results);
+    let mut retried = if retries > 0 { format!(", retries={} ({:.1}% of requests)", retries, retry_pct(retries, checks)) } else { String::new() };
+    let degraded = results.iter().filter(|r| r.is_degraded()).count();
+    if degraded > 0 { retried.push_str(&format!(", degraded={}", degraded)); }
+    if total < results.len() { retried.push_str(&format!(", ignored={}", results.len() - total)); }
+    if cfg.weights.is_empty() {
+        println!("\nRound stats: avg={}ms, uptime={:.2}% ({}/{}{}){}", avg_ms, uptime, successes, total, ci, retried);
+    } else {
+        println!("\nRound stats: avg={}ms, uptime={:.2}% ({}/{}{}), weighted uptime={:.2}%{}",
+            avg_ms, uptime, successes, total, ci, weighted_uptime(results, cfg), retried);
+    }
+}
+
+//"95% CI lo-hi%" when --confidence is set
+fn confidence_note(cfg: &Config, ok: u64, n: u64) -> Option<String> {
+    let level = cfg.confidence?;
+    let (lo, hi) = wilson_interval(ok, n, z_for_confidence(level)?);
+    Some(format!("{}% CI {:.1}-{:.1}%", level, lo, hi))
+}
+
+//shown checks of a round plus the summary of all of them as one json object
+fn round_json(results: &[WebsiteStatus], shown: &[WebsiteStatus], cfg: &Config) -> String {
+    let (up, avg_ms, uptime) = round_stats(results);
+    let checks: Vec<String> = shown.iter().map(|r| checklog::record(r, LogFormat::Jsonl)).collect();
+    let weighted = if cfg.weights.is_empty() { "null".to_string() } else { format!("{:.2}", weighted_uptime(results, cfg)) };
+    let (retries, _) = retry_usage(results);
+    //degraded checks count toward uptime but not as plain up
+    let degraded = results.iter().filter(|r| r.is_degraded()).count();
+    let ts_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+    format!(
+        "{{\"ts_ms\":{},\"checks\":[{}],\"summary\":{{\"total\":{},\"up\":{},\"degraded\":{},\"down\":{},\"ignored\":{},\"avg_ms\":{},\"uptime_pct\":{:.2},\"weighted_uptime_pct\":{},\"retries\":{},\"retry_pct\":{:.2}}}}}",
+        ts_ms,
+        checks.join(","),
+        results.len(),
+        up - degraded,
+        degraded,
+        counted(results) - up,
+        results.len() - counted(results),
+        avg_ms,
+        uptime,
+        weighted,
+        retries,
+        retry_pct(retries, results.len() as u64),
+    )
+}
+
+//a round's results in the chosen format, json rounds also to --output-file; --only trims the checks, not the stats
+fn report_round(results: &[WebsiteStatus], agg: Option<&HashMap<Arc<str>, Stats>>, cfg: &Config, filter: &mut ResultFilter) {
+    let shown = filter.apply(results, cfg);
+    match cfg.output {
+        OutputFormat::Table => {
+            if (!cfg.live || cfg.final_table) && (!shown.is_empty() || !filter.is_active()) {
+                print_results(&in_table_order(&shown, cfg), results.len(), agg, cfg.max_url_width);
             }
-        });
-        handles.push(handle);
-    }
-
-    handles
-}
-
-//url check w/ few retries
-fn check_once_with_retries(
-    agent: &ureq::Agent,
-    url: &str,
-    retries: u32,
-    header_checks: &[(String, String)],
-) -> WebsiteStatus {
-    let mut attempt = 0;
-    let start_all = Instant::now();
-
-    loop {
-        let start = Instant::now();
-        let ts: DateTime<Utc> = DateTime::now();
-        match agent.get(url).call() {
-            Ok(resp) => {
-                let code = resp.status();
-                //validate headers 
-                for (k, expected) in header_checks.iter() {
-                    match resp.header(k) {
-                        Some(v) if v == expected => {},
-                        Some(v) => {
-                            return WebsiteStatus {
-                                url: url.to_string(),
-                                status: Err(format!("header {} mismatch: got '{}', expected '{}'", k, v, expected)),
-                                response_time: start.elapsed(),
-                                timestamp: ts,
-                            }
-                        }
-                        None => {
-                            return WebsiteStatus {
-                                url: url.to_string(),
-                                status: Err(format!("missing header {}", k)),
-                                response_time: start.elapsed(),
-                                timestamp: ts,
-                            }
-                        }
-                    }
-                }
-                //return http status
-                return WebsiteStatus {
-                    url: url.to_string(),
-                    status: Ok(code as u16),
-                    response_time: start.elapsed(),
-                    timestamp: ts,
-                };
-            }
-            //server returned an http error
-            Err(ureq::Error::Status(code, _resp)) => {
-                return WebsiteStatus {
-                    url: url.to_string(),
-                    status: Ok(code as u16),
-                    response_time: start.elapsed(),
-                    timestamp: DateTime::now(),
-                };
-            }
-            //transport error
-            Err(e) => {
-                attempt += 1;
-                if attempt > retries {
-                    return WebsiteStatus {
-                        url: url.to_string(),
-                        status: Err(format!("transport error: {}", e)),
-                        response_time: start_all.elapsed(),
-                        timestamp: DateTime::now(),
-                    };
-                }
-                thread::sleep(Duration::from_millis(200));
+            if cfg.gantt {
+                println!();
+                for line in gantt::text(results) { println!("{}", line); }
             }
+            print_round_stats(results, cfg);
         }
+        OutputFormat::Json => println!("{}", round_json(results, &shown, cfg)),
+    }
+    if let Some(path) = &cfg.output_file {
+        let res = fs::OpenOptions::new().append(true).create(true).open(path)
+            .and_then(|mut f| writeln!(f, "{}", round_json(results, &shown, cfg)));
+        if let Err(e) = res { eprintln!("warning: json output write to {} failed: {}", path, e); }
+    }
+    if let Some(budget) = cfg.retry_budget && let Some(w) = retry_budget_warning(results, budget) {
+        eprintln!("warning: {}", w);
+    }
+    //rewritten every round, so it shows the latest one
+    if let Some(path) = &cfg.gantt_html && let Err(e) = fs::write(path, gantt::html(results)) {
+        eprintln!("warning: gantt write to {} failed: {}", path, e);
     }
 }
 
-//run one full sweep 
-fn run_once(cfg: &Config) -> Vec<WebsiteStatus> {
-    let (job_tx, job_rx) = mpsc::channel::<Job>();
-    let (result_tx, result_rx) = mpsc::channel::<WebsiteStatus>();
-    let shutdown = Arc::new(AtomicBool::new(false));
+//the run manifest, once, ahead of any results
+fn emit_manifest(cfg: &Config, rec: &mut Recorders) {
+    let Some(m) = &cfg.manifest else { return };
+    let line = m.json(SystemTime::now());
+    match cfg.output {
+        OutputFormat::Table => for l in m.lines() { println!("{}", l); },
+        OutputFormat::Json => println!("{}", line),
+    }
+    if let Some(path) = &cfg.output_file {
+        let res = fs::OpenOptions::new().append(true).create(true).open(path)
+            .and_then(|mut f| writeln!(f, "{}", line));
+        if let Err(e) = res { eprintln!("warning: manifest write to {} failed: {}", path, e); }
+    }
+    for l in &mut rec.logs {
+        if let Err(e) = l.write_extra(&line) { eprintln!("warning: check log write failed: {}", e); }
+    }
+}
+
+//append the summary line and/or post it
+fn emit_fleet_summary(results: &[WebsiteStatus], cfg: &Config) {
+    if cfg.fleet_file.is_none() && cfg.fleet_url.is_none() { return; }
+    let line = fleet_summary_json(results);
+    if let Some(path) = &cfg.fleet_file {
+        let res = fs::OpenOptions::new().append(true).create(true).open(path)
+            .and_then(|mut f| writeln!(f, "{}", line));
+        if let Err(e) = res { eprintln!("warning: fleet summary write to {} failed: {}", path, e); }
+    }
+    if let Some(url) = &cfg.fleet_url {
+        let res = ureq::post(url)
+            .timeout(cfg.timeout)
+            .set("Content-Type", "application/json")
+            .send_string(&line);
+        if let Err(e) = res { eprintln!("warning: fleet summary post to {} failed: {}", url, e); }
+    }
+}
+
+//round as line protocol to influxdb
+fn emit_influx(results: &[WebsiteStatus], cfg: &Config) {
+    let Some(base) = &cfg.influx_url else { return };
+    let res = influx::write_url(base, &cfg.influx_bucket)
+        .and_then(|endpoint| influx::write(&endpoint, cfg.influx_token.as_deref(), &influx::lines(results), cfg.timeout));
+    if let Err(e) = res { eprintln!("warning: influx write to {} failed: {}", base, e); }
+}
 
-    //share receiver
-    let job_rx_arc = Arc::new(Mutex::new(job_rx));
+//html report of the latest round and the aggregates so far
+fn write_report(results: &[WebsiteStatus], agg: &HashMap<Arc<str>, Stats>, cfg: &Config) {
+    let Some(path) = &cfg.report_file else { return };
+    if let Err(e) = fs::write(path, report::render(results, agg, cfg, SystemTime::now())) {
+        eprintln!("warning: report write to {} failed: {}", path, e);
+    }
+}
 
-    let workers = spawn_workers(
-        cfg.workers,
-        job_rx_arc,
-        result_tx,
-        cfg.timeout,
-        cfg.retries,
-        cfg.header_checks.clone(),
-        shutdown.clone(),
-    );
+//latest state per url, swapped in whole so readers never see a partial file
+fn write_summary(current: &summary::Current, agg: &HashMap<Arc<str>, Stats>, cfg: &Config) {
+    let Some(path) = &cfg.summary_file else { return };
+    if let Err(e) = summary::write_atomic(path, &current.json(agg, SystemTime::now())) {
+        eprintln!("warning: summary write to {} failed: {}", path, e);
+    }
+}
 
-    //one job per url
-    for url in &cfg.urls {
-        job_tx.send(Job::Check(url.clone())).expect("send job");
+//round as otlp/json spans, posted and/or appended as one line
+fn emit_traces(results: &[WebsiteStatus], cfg: &Config) {
+    if cfg.otlp_url.is_none() && cfg.trace_file.is_none() { return; }
+    let body = trace::export_json(results);
+    if let Some(path) = &cfg.trace_file {
+        let res = fs::OpenOptions::new().append(true).create(true).open(path)
+            .and_then(|mut f| writeln!(f, "{}", body));
+        if let Err(e) = res { eprintln!("warning: trace write to {} failed: {}", path, e); }
     }
+    if let Some(url) = &cfg.otlp_url {
+        let res = trace::traces_url(url).and_then(|endpoint| trace::post(&endpoint, &body, cfg.timeout));
+        if let Err(e) = res { eprintln!("warning: trace export to {} failed: {}", url, e); }
+    }
+}
 
-    drop(job_tx);
+//per-check files and the database, fed from every round
+struct Recorders {
+    logs: Vec<CheckLog>,
+    history: Option<CheckLog>,
+    db: Option<Db>,
+    statsd: Option<StatsdSink>,
+}
 
-    //collect results
-    let mut results = Vec::with_capacity(cfg.urls.len());
-    for _ in 0..cfg.urls.len() {
-        match result_rx.recv() {
-            Ok(r) => results.push(r),
-            Err(_) => break,
+impl Recorders {
+    fn open(cfg: &Config) -> Result<Self, String> {
+        let mut logs = Vec::new();
+        if let Some(path) = &cfg.log_file {
+            logs.push(CheckLog::open(path, cfg.log.clone()).map_err(|e| format!("failed to open log {}: {}", path, e))?);
         }
+        if let Some(path) = &cfg.csv_file {
+            logs.push(CheckLog::open_as(path, LogFormat::Csv, cfg.log.clone()).map_err(|e| format!("failed to open csv {}: {}", path, e))?);
+        }
+        let history = match &cfg.history_file {
+            Some(path) => Some(CheckLog::open_as(path, LogFormat::Jsonl, cfg.log.clone()).map_err(|e| format!("failed to open history {}: {}", path, e))?),
+            None => None,
+        };
+        let db = match &cfg.db_file {
+            Some(path) => Some(Db::open(path).map_err(|e| format!("failed to open database {}: {}", path, e))?),
+            None => None,
+        };
+        let statsd = match &cfg.statsd {
+            Some(addr) => Some(StatsdSink::connect(addr, &cfg.statsd_prefix).map_err(|e| format!("failed to set up statsd {}: {}", addr, e))?),
+            None => None,
+        };
+        Ok(Self { logs, history, db, statsd })
     }
 
-    //stop workers and join
-    shutdown.store(true, Ordering::Relaxed);
-    for h in workers { let _ = h.join(); }
+    //one sweep, each result appended to the history as it arrives and flushed with the round
+    fn run_round(&mut self, cfg: &Config, filter: &ResultFilter) -> Result<Vec<WebsiteStatus>, RunError> {
+        let history = &mut self.history;
+        let mut done = 0;
+        let results = run_once_with(cfg, |r| {
+            if let Some(h) = history.as_mut() && let Err(e) = h.write_one(r) {
+                eprintln!("warning: history write failed: {}", e);
+            }
+            if cfg.live && filter.shows(r, cfg) {
+                if done == 0 {
+                    println!("\nResults as they finish ({} checks):", cfg.urls.len());
+                    print_header(cfg.tcp_latency, "");
+                }
+                done += 1;
+                print_row(done, r, cfg.tcp_latency, "", cfg.max_url_width);
+            }
+        })?;
+        if let Some(h) = history && let Err(e) = h.flush() {
+            eprintln!("warning: history write failed: {}", e);
+        }
+        Ok(results)
+    }
+
+    //everything else once the round is complete
+    fn record(&mut self, results: &[WebsiteStatus]) {
+        for l in &mut self.logs {
+            if let Err(e) = l.write(results) { eprintln!("warning: check log write failed: {}", e); }
+        }
+        if let Some(db) = &self.db && let Err(e) = db.record_round(results) {
+            eprintln!("warning: database write failed: {}", e);
+        }
+        if let Some(statsd) = &self.statsd && let Err(e) = statsd.send(results) {
+            eprintln!("warning: statsd send failed: {}", e);
+        }
+    }
 
-    results
+    //interval flushes between rounds
+    fn tick(&mut self) {
+        for l in &mut self.logs {
+            if let Err(e) = l.tick() { eprintln!("warning: check log write failed: {}", e); }
+        }
+    }
 }
 
-//result table
-fn print_results(results: &[WebsiteStatus]) {
-    println!("\nResults ({} checks):", results.len());
-    println!("{:<5} | {:<8} | {:<7} | {:<13} | {}", "#", "Status", "ms", "ts(ms)", "URL");
-    println!("{}", "-".repeat(100));
-    for (i, r) in results.iter().enumerate() {
-        let code_str = match r.status {
-            Ok(c) => c.to_string(),
-            Err(_) => "ERR".to_string(),
-        };
-        let ts_ms = r.timestamp.as_system_time()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis();
-        println!("{:<5} | {:<8} | {:<7} | {:<13} | {}", i + 1, code_str, r.response_time.as_millis(), ts_ms, r.url);
-        if let Err(ref e) = r.status { println!("        ↳ error: {}", e); }
+//send this round's coalesced alerts
+fn dispatch_alerts(alerter: &mut Alerter, results: &[WebsiteStatus], silences: &Mutex<Silences>, cfg: &Config) {
+    for mut alert in alerter.process_round(results) {
+        alert.note = cfg.note_for(&alert.url).map(str::to_string);
+        if silences.lock().unwrap_or_else(|e| e.into_inner()).suppress(&alert) { continue; }
+        for (channel, res) in alerter.notify(&alert) {
+            if let Err(e) = res { eprintln!("warning: alert for {} via {} failed: {}", alert.url, channel, e); }
+        }
     }
 }
 
-//round statistics 
-fn print_round_stats(results: &[WebsiteStatus]) {
-    let total = results.len() as f64;
-    let successes = results.iter().filter(|r| matches!(r.status, Ok(c) if (200..=399).contains(&c))).count();
-    let total_duration: Duration = results.iter().map(|r| r.response_time).sum();
-    let avg_ms = if results.is_empty() { 0 } else { (total_duration.as_millis() / (results.len() as u128)) as u128 };
-    let uptime = if total == 0.0 { 0.0 } else { (successes as f64) * 100.0 / total };
-    println!("\nRound stats: avg={}ms, uptime={:.2}% ({}/{})", avg_ms, uptime, successes, results.len());
+//--test-alerts with nothing to check afterwards, neither now nor scheduled
+fn self_test_only(cfg: &Config) -> bool {
+    cfg.urls.is_empty() && cfg.scheduled_count() == 0
+}
+
+//fire a synthetic alert through every channel, true when all delivered
+fn run_alert_self_test(cfg: &Config) -> bool {
+    let alerter = Alerter::new(cfg.alert_rules.clone(), cfg.alert_channels.clone(), cfg.timeout);
+    println!("Testing {} alert channel(s)...", cfg.alert_channels.len());
+    let mut all_ok = true;
+    for (channel, res) in alerter.notify(&Alert::self_test()) {
+        match res {
+            Ok(()) => println!("  {:<40} ok", channel),
+            Err(e) => {
+                all_ok = false;
+                println!("  {:<40} FAILED: {}", channel, e);
+            }
+        }
+    }
+    all_ok
+}
+
+//open/resolve incidents from a round
+fn track_incidents(incidents: &mut IncidentTracker, results: &[WebsiteStatus], cfg: &Config) {
+    for r in results {
+        match incidents.observe(r) {
+            Some(IncidentEvent::Opened(url)) => {
+                let report = if cfg.traceroute { path_report(&url, cfg) } else { Vec::new() };
+                if let Some(inc) = incidents.get_mut(&url) {
+                    inc.path_report = report;
+                    inc.note = cfg.note_for(&url).map(str::to_string);
+                    println!("\nINCIDENT opened: {} ({} failed rounds, last: {})", inc.url, inc.failures, inc.last_error);
+                    if let Some(note) = &inc.note { println!("    note: {}", note); }
+                    for line in &inc.path_report { println!("    {}", line); }
+                    if let Some(cmd) = &cfg.on_down { run_state_hook(cmd, "down", inc, r); }
+                }
+            }
+            Some(IncidentEvent::Resolved(inc)) => {
+                let down_for = inc.opened.as_system_time().elapsed().unwrap_or_default().as_secs();
+                println!("\nINCIDENT resolved: {} (down ~{}s, {} failed rounds)", inc.url, down_for, inc.failures);
+                if let Some(note) = &inc.note { println!("    note: {}", note); }
+                if let Some(cmd) = &cfg.on_recover { run_state_hook(cmd, "recover", &inc, r); }
+            }
+            None => {}
+        }
+    }
+}
+
+//urls answering with other content than their last up check
+fn track_changes(changes: &mut ChangeDetector, results: &[WebsiteStatus], cfg: &Config) {
+    for change in results.iter().filter_map(|r| changes.observe(r)) {
+        println!("\nCONTENT changed: {}", change);
+        if let Some(note) = cfg.note_for(&change.url) { println!("    note: {}", note); }
+    }
+}
+
+//estimated sizes of what run_periodic keeps across rounds
+fn memory_parts(agg: &HashMap<Arc<str>, Stats>, current: &summary::Current, incidents: &IncidentTracker, changes: &ChangeDetector, alerter: &Alerter, filter: &ResultFilter, circuit: Option<&circuit::Breaker>) -> Vec<(&'static str, usize)> {
+    vec![
+        ("aggregates", memory::table_bytes(agg)),
+        ("latest_results", current.heap_bytes()),
+        ("incidents", incidents.heap_bytes()),
+        ("content_hashes", changes.heap_bytes()),
+        ("alert_state", alerter.heap_bytes()),
+        ("only_filter", filter.heap_bytes()),
+        ("circuit_breaker", circuit.map_or(0, circuit::Breaker::heap_bytes)),
+    ]
+}
+
+//every memory_interval: a reading to --memory-log, and a warning when rss kept growing
+fn watch_memory(watch: &mut MemoryWatch, parts: impl FnOnce() -> Vec<(&'static str, usize)>, cfg: &Config) {
+    if !watch.due(Instant::now()) { return; }
+    let sample = memory::Sample::take(parts());
+    if let Some(path) = &cfg.memory_log {
+        let res = fs::OpenOptions::new().append(true).create(true).open(path)
+            .and_then(|mut f| writeln!(f, "{}", sample.json(SystemTime::now())));
+        if let Err(e) = res { eprintln!("warning: memory log write to {} failed: {}", path, e); }
+    }
+    if let Some(w) = watch.record(&sample) { eprintln!("warning: {}", w); }
+}
+
+//--on-down/--on-recover, started in the background so a slow script never holds up the next round
+fn run_state_hook(cmd: &str, event: &str, inc: &Incident, r: &WebsiteStatus) {
+    let status = match r.status { Ok(PROBE_OK) | Err(_) => String::new(), Ok(code) => code.to_string() };
+    let env = [
+        ("SITEWATCH_EVENT", event.to_string()),
+        ("SITEWATCH_URL", inc.url.to_string()),
+        ("SITEWATCH_STATUS", status),
+        ("SITEWATCH_ERROR", r.status.as_ref().err().map(|e| e.message.clone()).unwrap_or_default()),
+        ("SITEWATCH_LAST_ERROR", inc.last_error.clone()),
+        ("SITEWATCH_FAILURES", inc.failures.to_string()),
+        ("SITEWATCH_DOWN_SECS", inc.opened.as_system_time().elapsed().unwrap_or_default().as_secs().to_string()),
+        ("SITEWATCH_RESPONSE_MS", r.response_time.as_millis().to_string()),
+        ("SITEWATCH_NOTE", inc.note.clone().unwrap_or_default()),
+    ];
+    if let Err(e) = hook::spawn(cmd, &env) { eprintln!("warning: --on-{} failed: {}", event, e); }
 }
 
-//periodic loop until exit(enter)
-fn run_periodic(cfg: Config) {
-    assert!(cfg.period_secs > 0);
+//scheduled loop until exit(enter) or until no checks remain
+fn run_periodic(cfg: Config, mut rec: Recorders) -> Result<(), RunError> {
     let shutdown = Arc::new(AtomicBool::new(false));
+    let silences = Arc::new(Mutex::new(Silences::default()));
+    console::spawn(shutdown.clone(), silences.clone());
 
-    {
-        let sd = shutdown.clone();
-        thread::spawn(move || {
-            let mut _dummy = String::new();
-            let _ = io::stdin().read_line(&mut _dummy); 
-            sd.store(true, Ordering::Relaxed);
-        });
+    //plain urls repeat every period, or run once now without one
+    let mut sched = Scheduler::new();
+    let now = SystemTime::now();
+    for url in &cfg.urls {
+        let schedule = if cfg.period_secs > 0 { Schedule::Every(Duration::from_secs(cfg.period_secs)) } else { Schedule::Once(now) };
+        sched.add(url.clone(), schedule, now);
+    }
+    for (at, url) in &cfg.one_off {
+        sched.add(url.as_str().into(), Schedule::Once(*at), now);
+    }
+    for (expr, url) in &cfg.cron {
+        sched.add(url.as_str().into(), Schedule::Cron(expr.clone()), now);
     }
 
     //collect stats while running
-    use std::collections::HashMap;
-    let mut agg: HashMap<String, Stats> = HashMap::new();
+    let mut agg: HashMap<Arc<str>, Stats> = HashMap::new();
+    let mut current = summary::Current::new();
+    let mut incidents = IncidentTracker::new(cfg.incident_after);
+    let mut changes = ChangeDetector::new();
+    let mut mem = MemoryWatch::new(cfg.memory_interval);
+    let mut filter = ResultFilter::new(cfg.only.clone());
+    let mut alerter = Alerter::new(cfg.alert_rules.clone(), cfg.alert_channels.clone(), cfg.timeout);
 
-    println!("Periodic monitoring every {}s. Press ENTER to stop...", cfg.period_secs);
+    if cfg.period_secs > 0 {
+        println!("Periodic monitoring every {}. Press ENTER to stop...", duration::format(Duration::from_secs(cfg.period_secs)));
+    } else {
+        println!("Waiting for {} scheduled check(s). Press ENTER to stop...", cfg.scheduled_count());
+    }
+    for (expr, url) in &cfg.cron {
+        println!("  cron '{}' (UTC): {}", expr.as_str(), url);
+    }
+    if !cfg.alert_channels.is_empty() { println!("{}", console::HELP); }
 
     while !shutdown.load(Ordering::Relaxed) {
-        let results = run_once(&cfg);
-        print_results(&results);
-        print_round_stats(&results);
+        let due = sched.due(SystemTime::now());
+        if !due.is_empty() {
+            let round_cfg = Config { workers: cfg.workers.min(due.len()), urls: due, ..cfg.clone() };
+            let results = rec.run_round(&round_cfg, &filter)?;
+            for r in &results {
+                agg.entry(r.url.clone()).or_default().record(r);
+                if !r.is_up() { sched.record_failure(&r.url, r.timestamp.as_system_time()); }
+            }
+            report_round(&results, Some(&agg), &cfg, &mut filter);
+            emit_fleet_summary(&results, &cfg);
+            emit_influx(&results, &cfg);
+            emit_traces(&results, &cfg);
+            rec.record(&results);
 
-        for r in &results {
-            agg.entry(r.url.clone()).or_insert_with(Stats::new).record(r);
+            write_report(&results, &agg, &cfg);
+            current.record(&results);
+            write_summary(&current, &agg, &cfg);
+            track_incidents(&mut incidents, &results, &cfg);
+            if cfg.detect_changes { track_changes(&mut changes, &results, &cfg); }
+            dispatch_alerts(&mut alerter, &results, &silences, &cfg);
         }
+        watch_memory(&mut mem, || memory_parts(&agg, &current, &incidents, &changes, &alerter, &filter, cfg.circuit.as_deref()), &cfg);
 
-        let period = Duration::from_secs(cfg.period_secs);
-        let start = Instant::now();
-        while start.elapsed() < period {
+        //sleep until the next due check, waking for shutdown
+        let Some(next) = sched.next_due() else { break };
+        while SystemTime::now() < next {
             if shutdown.load(Ordering::Relaxed) { break; }
+            //interval flushes happen between rounds too
+            rec.tick();
+            watch_memory(&mut mem, || memory_parts(&agg, &current, &incidents, &changes, &alerter, &filter, cfg.circuit.as_deref()), &cfg);
             thread::sleep(Duration::from_millis(100));
         }
     }
 
-    //aggregate stats per url
+    //aggregate stats per url, least healthy first
     println!("\nAggregate statistics:");
-    println!("{:<7} | {:<7} | {:<7} | {}", "samples", "uptime%", "avg ms", "URL");
+    let z = cfg.confidence.and_then(z_for_confidence);
+    match cfg.confidence {
+        Some(level) if z.is_some() => println!("{:<6} | {:<7} | {:<7} | {:<11} | {:<7} | {:<7} | {:<6} | URL", "health", "samples", "uptime%", format!("{}% CI", level), "avg ms", "tcp ms", "retry%"),
+        _ => println!("{:<6} | {:<7} | {:<7} | {:<7} | {:<7} | {:<6} | URL", "health", "samples", "uptime%", "avg ms", "tcp ms", "retry%"),
+    }
     println!("{}", "-".repeat(80));
     let mut keys: Vec<_> = agg.keys().cloned().collect();
-    keys.sort();
-    for url in keys {
-        let s = &agg[&url];
-        println!("{:<7} | {:<7.2} | {:<7} | {}", s.samples, s.uptime_pct(), s.avg_ms(), url);
+    keys.sort_by(|a, b| agg[a].health_score().cmp(&agg[b].health_score()).then_with(|| a.cmp(b)));
+    for key in keys {
+        let s = &agg[&key];
+        let url = text::url_cell(&key, cfg.max_url_width);
+        let tcp_str = s.avg_tcp_ms().map(|ms| ms.to_string()).unwrap_or_else(|| "-".into());
+        match z {
+            Some(z) => {
+                let (lo, hi) = s.uptime_interval(z);
+                println!("{:<6} | {:<7} | {:<7.2} | {:<11} | {:<7} | {:<7} | {:<6.1} | {}", s.health_score(), s.samples, s.uptime_pct(), format!("{:.1}-{:.1}", lo, hi), s.avg_ms(), tcp_str, s.retry_pct(), url);
+            }
+            None => println!("{:<6} | {:<7} | {:<7.2} | {:<7} | {:<7} | {:<6.1} | {}", s.health_score(), s.samples, s.uptime_pct(), s.avg_ms(), tcp_str, s.retry_pct(), url),
+        }
+    }
+    let (plain, weighted) = fleet_uptime(&agg, &cfg);
+    let samples: u64 = agg.values().map(|s| s.samples).sum();
+    let counted: u64 = agg.values().map(|s| s.counted()).sum();
+    let ok: u64 = agg.values().map(|s| s.ok).sum();
+    let mut notes = Vec::new();
+    if !cfg.weights.is_empty() { notes.push(format!("weighted {:.2}%", weighted)); }
+    notes.extend(confidence_note(&cfg, ok, counted));
+    let retries: u64 = agg.values().map(|s| s.retries).sum();
+    if retries > 0 { notes.push(format!("retries {:.1}% of requests", retry_pct(retries, samples))); }
+    let degraded: u64 = agg.values().map(|s| s.degraded).sum();
+    if degraded > 0 { notes.push(format!("degraded {:.1}% of checks", degraded as f64 * 100.0 / samples as f64)); }
+    let ignored: u64 = agg.values().map(|s| s.ignored).sum();
+    if ignored > 0 { notes.push(format!("{} ignored check(s) left out", ignored)); }
+    if notes.is_empty() {
+        println!("\nFleet uptime: {:.2}%", plain);
+    } else {
+        println!("\nFleet uptime: {:.2}% ({})", plain, notes.join(", "));
     }
+
+    //still open at exit
+    let mut open: Vec<_> = incidents.open_incidents().collect();
+    open.sort_by(|a, b| a.url.cmp(&b.url));
+    if !open.is_empty() {
+        println!("\nOpen incidents:");
+        for inc in open {
+            println!("  {} ({} failed rounds, last: {})", inc.url, inc.failures, inc.last_error);
+            if let Some(note) = &inc.note { println!("    note: {}", note); }
+            for line in &inc.path_report { println!("    {}", line); }
+        }
+    }
+    Ok(())
 }
 
 //entry point
 fn main() {
-    match parse_args() {
-        Ok(cfg) => {
-            if cfg.period_secs == 0 {
-                let results = run_once(&cfg);
-                print_results(&results);
-                print_round_stats(&results);
-            } else {
-                run_periodic(cfg);
-            }
+    #[cfg(feature = "bench")]
+    if env::args().nth(1).as_deref() == Some("--bench") {
+        if let Err(e) = bench::run_cli(env::args().skip(2)) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
         }
-        //basic help on error
+        return;
+    }
+
+    //0 ok, 1 failed alert self-test or a down check with --stop-on-first-failure, 2 bad flags or config, 3 pipeline failure
+    let code = match run() {
+        Ok(code) => code,
         Err(e) => {
             eprintln!("error: {}", e);
-            eprintln!("\nUsage: sitewatch [FLAGS] <url> [<url> ...]\n");
-            eprintln!("Flags:");
-            eprintln!("  --workers <N>        Number of worker threads (default 50)");
-            eprintln!("  --timeout-ms <MS>    Request timeout in milliseconds (default 5000)");
-            eprintln!("  --retries <N>        Max retries per website on transport errors (default 0)");
-            eprintln!("  --period <SECS>      Periodic monitoring interval in seconds (0 = single run)");
-            eprintln!("  --header K=V         Require exact HTTP header K=V (repeatable)");
-            eprintln!("  --file <PATH>        Read URLs (one per line) from PATH");
-            eprintln!("\nExamples:");
-            eprintln!("  sitewatch --workers 50 --timeout-ms 5000 https://example.org https://httpbin.org/status/500");
-            eprintln!("  sitewatch --period 10 --retries 1 --header 'Content-Type=text/plain' --file urls.txt");
+            if let RunError::Usage(_) = e { print_usage(); }
+            e.exit_code()
         }
+    };
+    std::process::exit(code);
+}
+
+fn run() -> Result<i32, RunError> {
+    let mut cfg = parse_args().map_err(RunError::Usage)?;
+    if let Some(path) = &cfg.hosts_file {
+        let text = fs::read_to_string(path).map_err(|e| RunError::Config(format!("failed to read hosts file {}: {}", path, e)))?;
+        let hosts = sitewatch::parse_hosts(&text).map_err(|e| RunError::Config(format!("hosts file {}: {}", path, e)))?;
+        cfg.network = cfg.network.with_hosts(hosts);
+    }
+    install_tls(&mut cfg)?;
+    let mut rec = Recorders::open(&cfg).map_err(RunError::Config)?;
+    emit_manifest(&cfg, &mut rec);
+    if let Some(flags) = &cfg.ab_flags {
+        let b_args = env::args().skip(1).chain(split_words(flags)?);
+        let b = parse_args_from(b_args).map_err(|e| RunError::Usage(format!("--ab: {}", e)))?;
+        run_ab(&cfg, &b, &mut rec)?;
+        return Ok(0);
     }
+    if cfg.encoding_audit {
+        run_encoding_audit(&cfg);
+        return Ok(0);
+    }
+    if cfg.test_alerts {
+        let ok = run_alert_self_test(&cfg);
+        if self_test_only(&cfg) { return Ok(if ok { 0 } else { 1 }); }
+    }
+    if cfg.period_secs == 0 && cfg.scheduled_count() == 0 {
+        let mut filter = ResultFilter::new(cfg.only.clone());
+        let results = rec.run_round(&cfg, &filter)?;
+        report_round(&results, None, &cfg, &mut filter);
+        emit_fleet_summary(&results, &cfg);
+        emit_influx(&results, &cfg);
+        emit_traces(&results, &cfg);
+        rec.record(&results);
+        if cfg.report_file.is_some() || cfg.summary_file.is_some() {
+            let mut agg: HashMap<Arc<str>, Stats> = HashMap::new();
+            for r in &results { agg.entry(r.url.clone()).or_default().record(r); }
+            write_report(&results, &agg, &cfg);
+            let mut current = summary::Current::new();
+            current.record(&results);
+            write_summary(&current, &agg, &cfg);
+        }
+        let mut alerter = Alerter::new(cfg.alert_rules.clone(), cfg.alert_channels.clone(), cfg.timeout);
+        dispatch_alerts(&mut alerter, &results, &Mutex::new(Silences::default()), &cfg);
+        if cfg.stop_on_failure && let Some(failed) = results.iter().find(|r| !r.is_up()) {
+            eprintln!("stopped at first failure: {} ({} of {} checks not run)", failed.url, cfg.urls.len() - results.len(), cfg.urls.len());
+            return Ok(1);
+        }
+    } else {
+        run_periodic(cfg, rec)?;
+    }
+    Ok(0)
 }
 
-//tests
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::net::{TcpListener, TcpStream};
-    use std::io::{Read, Write};
-
-    //blocking http server for tests
-    fn spawn_simple_http_server(port: u16) -> thread::JoinHandle<()> {
-        thread::spawn(move || {
-            let listener = TcpListener::bind(("127.0.0.1", port)).expect("bind test server");
-            for stream in listener.incoming() {
-                let mut stream = match stream { Ok(s) => s, Err(_) => continue };
-                handle_conn(&mut stream);
-            }
-        })
-    }
-
-    //handle one conenction
-    fn handle_conn(stream: &mut TcpStream) {
-        let mut buf = [0u8; 1024];
-        let _ = stream.read(&mut buf);
-        let req = String::from_utf8_lossy(&buf);
-        let path = req.split_whitespace().nth(1).unwrap_or("/");
-        match path {
-            "/ok" => respond(stream, 200, "OK", "text/plain"),
-            "/slow" => { thread::sleep(Duration::from_millis(300)); respond(stream, 200, "SLOW", "text/plain") }
-            "/err" => respond(stream, 503, "ERR", "text/plain"),
-            _ => respond(stream, 404, "NOPE", "text/plain"),
+//--ca-bundle, --client-cert/--client-key and --insecure, for every check
+fn install_tls(cfg: &mut Config) -> Result<(), RunError> {
+    if cfg.ca_bundle.is_none() && cfg.client_cert.is_none() && !cfg.insecure { return Ok(()); }
+    let read = |path: &Option<String>| match path {
+        Some(p) => fs::read_to_string(p).map(Some).map_err(|e| RunError::Config(format!("failed to read {}: {}", p, e))),
+        None => Ok(None),
+    };
+    let files = TlsFiles { ca_bundle: read(&cfg.ca_bundle)?, client_cert: read(&cfg.client_cert)?, client_key: read(&cfg.client_key)? };
+    cfg.network = cfg.network.clone().with_tls(&files, cfg.insecure).map_err(RunError::Config)?;
+    if cfg.insecure { eprintln!("warning: --insecure: tls certificates are not verified"); }
+    Ok(())
+}
+
+//raw responses per accept-encoding, urls one after another to keep the comparison fair
+fn run_encoding_audit(cfg: &Config) {
+    println!("Accept-Encoding audit of {} URL(s), sizes as sent on the wire:", cfg.urls.len());
+    for url in &cfg.urls {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            println!("{}\n  skipped, not an http(s) url", url);
+            continue;
+        }
+        for line in encoding::lines(url, &encoding::audit(&cfg.network, url, &cfg.request_headers, cfg.timeout)) {
+            println!("{}", line);
         }
     }
+}
 
-    //compose an http response
-    fn respond(stream: &mut TcpStream, code: u16, body: &str, ctype: &str) {
-        let status_line = match code { 200 => "HTTP/1.1 200 OK", 404 => "HTTP/1.1 404 Not Found", 503 => "HTTP/1.1 503 Service Unavailable", _ => "HTTP/1.1 500 Internal Server Error" };
-        let resp = format!(
-            "{}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-            status_line, ctype, body.len(), body
-        );
-        let _ = stream.write_all(resp.as_bytes());
-        let _ = stream.flush();
+//alternate a and b rounds, then compare them pairwise
+fn run_ab(a: &Config, b: &Config, rec: &mut Recorders) -> Result<(), RunError> {
+    let mut cmp = Comparison::new();
+    println!("A/B comparison over {} pair(s), B adds: {}", a.ab_rounds, a.ab_flags.as_deref().unwrap_or_default());
+    for i in 0..a.ab_rounds {
+        if i > 0 && a.period_secs > 0 { thread::sleep(Duration::from_secs(a.period_secs)); }
+        let ra = rec.run_round(a, &ResultFilter::new(a.only.clone()))?;
+        rec.record(&ra);
+        let rb = rec.run_round(b, &ResultFilter::new(b.only.clone()))?;
+        rec.record(&rb);
+        let (up_a, ms_a, _) = round_stats(&ra);
+        let (up_b, ms_b, _) = round_stats(&rb);
+        println!("  pair {:>3}: A avg={}ms up={}/{}  B avg={}ms up={}/{}", i + 1, ms_a, up_a, ra.len(), ms_b, up_b, rb.len());
+        cmp.record_pair(&ra, &rb);
+    }
+    println!();
+    for line in cmp.report() { println!("{}", line); }
+    Ok(())
+}
+
+//whitespace-separated words, single or double quotes group
+fn split_words(s: &str) -> Result<Vec<String>, RunError> {
+    let mut words = Vec::new();
+    let mut cur = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    for c in s.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => cur.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word { words.push(std::mem::take(&mut cur)); }
+                in_word = false;
+            }
+            (None, c) => {
+                cur.push(c);
+                in_word = true;
+            }
+        }
     }
+    if quote.is_some() { return Err(RunError::Usage("--ab: unterminated quote".into())); }
+    if in_word { words.push(cur); }
+    Ok(words)
+}
+
+//basic help on error
+fn print_usage() {
+    eprintln!("\nUsage: sitewatch [FLAGS] <url> [<url> ...]\n");
+    eprintln!("Flags (DUR is a duration like 500ms, 30s, 5m, 24h or 1h30m; the old -ms spellings still work):");
+    eprintln!("  --workers <N>        Number of worker threads (default 50)");
+    eprintln!("  --stop-on-first-failure  Single runs: stop at the first down check and exit 1 (CI gates)");
+    eprintln!("  --reverify-failures  Check failed URLs once more at the end of each round; the second result is recorded");
+    eprintln!("  --max-inflight <N>   At most N requests in flight at once, independent of --workers");
+    eprintln!("  --per-host-limit <N> At most N checks at once against one host (host:port)");
+    eprintln!("  --per-host-rps <X>   At most X checks started per second against one host, e.g. 2 or 0.5");
+    eprintln!("  --timeout <DUR>      Request timeout, e.g. 2s or 500ms (default 5s; bare numbers are ms, also --timeout-ms)");
+    eprintln!("  --retries <N>        Max retries per website on transport errors and --retry-on statuses (default 0)");
+    eprintln!("  --circuit-breaker <N> Skip a host's URLs for --circuit-cooldown after N transport failures in a row,");
+    eprintln!("                       reported as skipped (circuit open); one check probes the host when the cooldown ends");
+    eprintln!("  --circuit-cooldown <DUR> How long an open circuit skips its host (default 60s)");
+    eprintln!("  --retry-on <CODES>   HTTP statuses retried like transport errors, e.g. 502,503,504 or 5xx (default 429,503)");
+    eprintln!("  --backoff <DUR>      Wait before the first retry, doubled per retry with jitter (default 200ms)");
+    eprintln!("  --backoff-max <DUR>  Longest wait between retries; a longer Retry-After is not retried (default 10s)");
+    eprintln!("  --retry-timing <M>   Response time of retried checks: last (answering try) or total (all tries and waits);");
+    eprintln!("                       default: last when a try answered, total when all failed. JSON has both");
+    eprintln!("  --ignore-status <CODES> Leave these statuses (e.g. 401,418 or 4xx) out of uptime, counted as ignored");
+    eprintln!("  --max-latency <DUR>  Report up checks slower than DUR as DEGRADED (per URL: max_latency=DUR in --file)");
+    eprintln!("  --period <DUR>       Periodic monitoring interval, e.g. 30s or 5m (0 = single run; bare numbers are seconds)");
+    eprintln!("  --header K=V         Require exact HTTP header K=V (repeatable)");
+    eprintln!("  --config <PATH>      Read flags from a TOML file (key_name = value for --key-name, urls = [...],");
+    eprintln!("                       [headers]/[send_headers]/[weights]/[expect_status] tables, [cron]/[at] tables of");
+    eprintln!("                       \"URL\" = \"WHEN\"); command-line flags override it");
+    eprintln!("  --profile <NAME>     Overlay [profile.NAME] (and [profile.NAME.headers] etc.) of the config on its base;");
+    eprintln!("                       inherits = \"OTHER\" in a profile builds on another profile");
+    eprintln!("  --file <PATH>        Read URLs (one per line) from PATH; \"URL  # note\" attaches a note to alerts and incidents");
+    eprintln!("                       \"URL timeout=DUR retries=N expect=CODES max_latency=DUR http=1.0 method=M body=@F header:NAME=VALUE user_agent=UA tag=T assert=EXPR\"");
+    eprintln!("                       schedule=\"CRON\" checks the entry on that schedule (as --cron) instead of every round");
+    eprintln!("                       cors_origin=ORIGIN cors_method=M cors_headers=A,B send an OPTIONS preflight and check Access-Control-Allow-*");
+    eprintln!("                       overrides settings for that URL; indented lines add more settings, \"...\" keeps spaces in a value");
+    eprintln!("  --tags <T,T,...>     Check only --file URLs carrying one of these tag= values");
+    eprintln!("  --ports <P,P,...>    TCP connect checks against localhost ports (same as tcp://localhost:P URLs)");
+    eprintln!("  --at <TIME> <URL>    One-off check of URL at TIME (e.g. 2024-07-01T00:05Z, repeatable)");
+    eprintln!("  --cron <EXPR> <URL>  Check URL on a cron schedule in UTC (e.g. '*/5 9-17 * * MON-FRI', repeatable)");
+    eprintln!("  --output <FORMAT>    Result format on stdout: table or json (one object per round, default table)");
+    eprintln!("  --output-file <PATH> Also append each round as one JSON object to PATH");
+    eprintln!("  --memory-log <PATH>  Append own RSS and size estimates of per-URL state as JSON lines to PATH every interval;");
+    eprintln!("                       steady RSS growth over 6 readings is warned about either way (periodic runs)");
+    eprintln!("  --memory-interval <DUR> Time between memory readings (default 1h)");
+    eprintln!("  --max-url-width <N>  Shorten URLs in the result tables to N columns with … in the middle (at least 10)");
+    eprintln!("  --live               Print each result row as its check finishes (table output)");
+    eprintln!("  --final-table        --live, plus the full table in URL-list order once the round is done");
+    eprintln!("  --gantt              Print a Gantt-style chart of which worker ran each check and when");
+    eprintln!("  --gantt-html <PATH>  Write the latest round's worker schedule as an HTML page to PATH");
+    eprintln!("  --only <LIST>        Print only failures, degraded and/or changed checks (comma-separated); stats cover all");
+    eprintln!("  --retry-budget <F>   Warn when retries exceed fraction F (0-1) of a round's requests");
+    eprintln!("  --confidence <PCT>   Show Wilson confidence intervals on uptime (80, 90, 95, 98, 99 or 99.9)");
+    eprintln!("  --fleet-file <PATH>  Append a one-line JSON fleet summary to PATH after each round");
+    eprintln!("  --fleet-url <URL>    POST the fleet summary JSON to URL after each round");
+    eprintln!("  --report <PATH>      Write a self-contained HTML report (results, uptime bars, aggregates) after each round");
+    eprintln!("  --summary-file <PATH> Atomically rewrite a compact JSON summary of each URL's current state after each round");
+    eprintln!("  --ca-bundle <PEM>    Trust the CA certificates in PEM on top of the bundled roots (internal CAs)");
+    eprintln!("  --client-cert <PEM>  Present this client certificate for mutual TLS (the key may be in the same file)");
+    eprintln!("  --client-key <PEM>   Private key for --client-cert (PKCS#8, PKCS#1 RSA or SEC1 EC)");
+    eprintln!("  --insecure           Skip TLS certificate verification (lab environments only)");
+    eprintln!("  --allow-hosts <LIST> Refuse to start unless every URL's host matches one of LIST (comma separated:");
+    eprintln!("                       example.com, *.example.com for subdomains, 10.0.0.0/8); repeatable");
+    eprintln!("  --deny-hosts <LIST>  Refuse to start if any URL's host matches LIST (same patterns, wins over --allow-hosts)");
+    eprintln!("  --proxy <URL>        Send HTTP(S) checks through an HTTP proxy (http://[user:pass@]host:port, HTTPS via CONNECT)");
+    eprintln!("                       or a SOCKS5 one (socks5://[user:pass@]host[:port], socks5h:// to resolve names there);");
+    eprintln!("                       default $HTTP_PROXY for http:// and $HTTPS_PROXY for https:// URLs, hosts in $NO_PROXY");
+    eprintln!("                       direct; tcp:// and raw probes connect directly");
+    eprintln!("  --no-proxy           Ignore $HTTP_PROXY/$HTTPS_PROXY/$NO_PROXY");
+    eprintln!("  --hosts-file <PATH>  Resolve hostnames from an /etc/hosts-style file before DNS (TLS still uses the name)");
+    eprintln!("  --otlp-url <URL>     Export a span per check (attempts as child spans) to an OTLP/HTTP collector");
+    eprintln!("  --trace-file <PATH>  Append each round's spans as one OTLP/JSON line");
+    eprintln!("  --statsd <HOST:PORT> Send DogStatsD latency histograms and success/failure counters per check");
+    eprintln!("  --statsd-prefix <P>  Metric name prefix (default sitewatch)");
+    eprintln!("  --influx-url <URL>   Write each round to InfluxDB (v2 write API) in line protocol");
+    eprintln!("  --influx-bucket <B>  InfluxDB bucket (default sitewatch)");
+    eprintln!("  --influx-token <T>   InfluxDB API token (default $INFLUX_TOKEN)");
+    eprintln!("  --log <PATH>         Append one line per check to PATH (CSV for .csv, else JSONL)");
+    eprintln!("  --csv <PATH>         Append one CSV row per check to PATH (header written once), any extension");
+    eprintln!("  --history <PATH>     Append every result to PATH as a JSON line as it arrives, flushed each round");
+    eprintln!("  --db <PATH>          Record every check and round aggregate in a SQLite database (needs the sqlite3 shell)");
+    eprintln!("  --log-flush <DUR>    Flush buffered log lines at least every DUR (default 1s)");
+    eprintln!("  --log-flush-bytes <N> Flush once N bytes are buffered (default 65536)");
+    eprintln!("  --fsync <POLICY>     Log fsync policy: never, flush (after each batch) or always (default never)");
+    eprintln!("  --alert-console      Print alerts (coalesced per URL per round) to stdout");
+    eprintln!("  --alert-webhook <URL> POST alert JSON to URL (repeatable)");
+    eprintln!("  --test-alerts        Send a test notification to every alert channel at startup");
+    eprintln!("  --alert-latency <DUR> Alert when a response is slower than DUR");
+    eprintln!("  --canary <PROD> <CANARY> Alert when CANARY's error rate or latency deviates from PROD (repeatable)");
+    eprintln!("  --canary-window <N>  Checks per URL compared for --canary (default 10)");
+    eprintln!("  --canary-error-margin <PCT> Extra canary error rate tolerated, in percentage points (default 5)");
+    eprintln!("  --canary-latency-margin <PCT> Canary slowdown over prod tolerated, in percent (default 50)");
+    eprintln!("  --weight URL=W       Business weight of URL for weighted uptime (repeatable, default 1)");
+    eprintln!("  --expect-status <URL=CODES> Statuses that count as up for URL instead of 2xx/3xx: 401, 200,301, 2xx or 200-299; 3xx codes stop redirect following");
+    eprintln!("  --assert <URL> <EXPR> Fail URL unless EXPR holds (repeatable), e.g. 'status == 200 && latency < 300 && \"ok\" in body';");
+    eprintln!("                       status, latency (ms), body, headers[\"name\"], in, len/lower/int/contains/starts_with/ends_with/matches");
+    eprintln!("  --cors <URL> <ORIGIN> Fail URL when its CORS preflight from ORIGIN is refused (method and headers: cors_method=, cors_headers= in --file)");
+    eprintln!("  --send-header K=V    Send request header K: V with every HTTP check (repeatable)");
+    eprintln!("  --user-agent <UA>    User-Agent of HTTP checks instead of ureq's (per URL: user_agent=\"UA\" in --file)");
+    eprintln!("  --ab '<FLAGS>'       A/B mode: alternate rounds without and with FLAGS, then report paired differences");
+    eprintln!("  --ab-rounds <N>      A/B pairs to run (default 10, --period sets the pause between pairs)");
+    eprintln!("  --manifest           Start the run by writing a manifest (version, config hash, URL count, effective flags)");
+    eprintln!("                       to the output, --output-file and --log");
+    eprintln!("  --encoding-audit     Request each http(s) URL with every Accept-Encoding (identity, gzip, deflate, br, zstd) and report which are honored and their savings");
+    eprintln!("  --strict-headers     Fail checks whose response repeats headers or mixes header casing (extra raw request)");
+    eprintln!("  --tls-info           Show TLS version, cipher suite, certificate subject/issuer and whether TLS 1.0/1.1");
+    eprintln!("                       is still accepted for https checks (extra handshakes)");
+    eprintln!("  --head-only          Check with HEAD, never downloading bodies (GET with the body left unread if HEAD is refused)");
+    eprintln!("  --expect-compression Fetch each up http(s) URL raw offering gzip, br (and as identity) to report the encoding and saving;");
+    eprintln!("                       text-like bodies of 1 KiB or more that come back uncompressed fail the check");
+    eprintln!("  --detect-changes     Read whole bodies for their size and hash; report URLs whose content changed since their last up check");
+    eprintln!("  --max-redirects <N>  Follow at most N redirects per check, more is a redirect error (default 5)");
+    eprintln!("  --no-follow-redirects Take the first 3xx as the answer; same as --max-redirects 0");
+    eprintln!("  --method <METHOD>    Request method for http(s) checks (default GET; per URL: method=POST in --file)");
+    eprintln!("  --body <TEXT|@FILE>  Request body sent with --method POST/PUT/...; @FILE reads it from a file (per URL: body=@FILE)");
+    eprintln!("  --content-type <T>   Content-Type of --body (per URL: content_type=T)");
+    eprintln!("  --transaction <NAME=FILE> Check the steps in FILE in order as txn://NAME, sharing cookies (repeatable);");
+    eprintln!("                       one step per line: METHOD URL [expect=CODES] [contains=TEXT] [body=TEXT|@FILE] [content_type=T]");
+    eprintln!("  --on-check-hook <CMD> Pipe each finished check as a JSON line to CMD (run with sh -c) and merge the JSON object");
+    eprintln!("                       it prints into the result's meta (shown, logged; hook_error on failure, 10s limit)");
+    eprintln!("  --oauth-token-url <URL> Fetch an OAuth2 client-credentials token from URL and send it as a bearer token,");
+    eprintln!("                       cached and refreshed before expiry (needs --oauth-client-id and --oauth-client-secret)");
+    eprintln!("  --oauth-client-id <ID> / --oauth-client-secret <S>  Client credentials (secret default $OAUTH_CLIENT_SECRET)");
+    eprintln!("  --oauth-scope <S>    Scope requested with the token");
+    eprintln!("  --oauth-hosts <LIST> Hosts that get the token, --allow-hosts patterns (default: the token URL's host)");
+    eprintln!("  --http-version <V>   Send checks as HTTP/1.0 or HTTP/1.1 over a raw connection, redirects not followed");
+    eprintln!("                       (per URL: http=1.0 in --file)");
+    eprintln!("  --timing             Show DNS, connect, TLS and time-to-first-byte per http(s) check (extra request)");
+    eprintln!("  --cert-warn-days <N> Fail https checks whose certificate expires within N days, or e.g. 2w (extra tls handshake)");
+    eprintln!("  --meta-refresh       Fail HTML pages that redirect with <meta http-equiv=\"refresh\"> (redirect loops always fail as redirect errors)");
+    eprintln!("  --expect-body <TEXT> Fail 2xx/3xx checks whose body (first 1 MiB) lacks TEXT; repeatable");
+    eprintln!("  --expect-body-regex <RE> Same with a regex (. [] \\d \\w \\s ^ $ | () * + ? {{m,n}}, (?i) prefix ignores case)");
+    eprintln!("  --expect-json <PATH=VALUE> Fail 2xx/3xx checks unless the JSON body has VALUE at PATH (status=ok, data.items[0].health=green); repeatable");
+    eprintln!("  --stream-first-byte <DUR> stream:// URLs must send data within DUR (default 2s)");
+    eprintln!("  --stream-duration <DUR> How long stream:// URLs are read (default 5s)");
+    eprintln!("  --stream-min-bps <N>  Minimum stream:// throughput in bytes/s over the read window");
+    eprintln!("  --feed-max-age <DUR> Max age of the newest item for feed:// URLs (default 7d; bare numbers are hours)");
+    eprintln!("  --mail-handshake     Send EHLO (smtp://) or CAPABILITY (imap://) after the greeting");
+    eprintln!("  --ws-ping            Exchange a ping/pong after the ws:// or wss:// upgrade handshake");
+    eprintln!("  --ftp-login          Log in to ftp:// servers (anonymous unless the URL has user:pass@)");
+    eprintln!("  --ntp-max-offset <DUR> Fail ntp:// checks whose clock offset exceeds DUR (default 1s)");
+    eprintln!("  --tcp-latency        Also measure raw TCP connect time (SYN to ACK) per URL");
+    eprintln!("  --titles             Record the <title> of HTML/XML responses (reads up to 64 KiB of body)");
+    eprintln!("  --cache-bust         Append a random query parameter to every request to bypass caches");
+    eprintln!("  --cache-bust-param <NAME> Name of the --cache-bust parameter (default _sw, implies --cache-bust)");
+    eprintln!("  --sample-bytes <N>   Read and time only the first N body bytes (large objects stay cheap)");
+    eprintln!("  --incident-after <N> Failed rounds in a row before an incident opens (default 3)");
+    eprintln!("  --on-down <CMD>      Run CMD (sh -c, in the background) when an incident opens; SITEWATCH_EVENT, _URL, _STATUS,");
+    eprintln!("                       _ERROR, _LAST_ERROR, _FAILURES, _DOWN_SECS, _RESPONSE_MS and _NOTE describe it");
+    eprintln!("  --on-recover <CMD>   Run CMD the same way when the incident resolves");
+    eprintln!("  --traceroute         Probe the network path (TTL-stepped TCP connects) when an incident opens");
+    eprintln!("  --traceroute-hops <N> Max hops for --traceroute (default 16)");
+    #[cfg(feature = "bench")]
+    eprintln!("  --bench [--checks N] [--workers N] [--rounds N] Benchmark the check pipeline against a loopback server");
+    eprintln!("\nBesides http(s):// URLs: tcp://HOST:PORT (connect only), smtp://HOST[:PORT] and imap://HOST[:PORT] (greeting), ws(s)://... (upgrade handshake), ftp://HOST[/DIR] (banner, DIR listing), sftp://HOST (ssh banner), ntp://HOST (clock offset), feed://HOST/PATH or feed:http(s)://... (RSS/Atom freshness), stream://HOST/PATH or stream:http(s)://... (SSE/long-poll data flow)");
+    eprintln!("URLs may use brace expansion: https://shard-{{01..16}}.example.com/health, https://{{eu,us,ap}}.example.com/ping");
+    eprintln!("\nExamples:");
+    eprintln!("  sitewatch --workers 50 --timeout-ms 5000 https://example.org https://httpbin.org/status/500");
+    eprintln!("  sitewatch --period 10 --retries 1 --header 'Content-Type=text/plain' --file urls.txt");
+}
+
+//tests
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
     fn test_parse_header_kv() {
@@ -493,65 +1613,172 @@ mod tests {
         assert!(parse_header_kv("=B").is_err());
     }
 
+    fn check(url: &str, status: Result<u16, sitewatch::CheckError>) -> WebsiteStatus {
+        WebsiteStatus {
+            url: url.into(), status, response_time: Duration::from_millis(10), tcp_connect: None, title: None, clock_offset_ms: None, slot: None, retries: 0, expect: None, latency_limit: None, ignored: false, tls: None, phases: None, http_version: None, redirects: Vec::new(), final_url: None, content: None, compression: None, meta: Vec::new(), attempts: Vec::new(), timestamp: SystemTime::now().into(),
+        }
+    }
+
     #[test]
-    fn test_run_once_ok_and_err() {
-        let port = 34567;
-        let _server = spawn_simple_http_server(port);
-        thread::sleep(Duration::from_millis(50));
-
-        let cfg = Config {
-            workers: 4,
-            timeout: Duration::from_millis(2000),
-            retries: 0,
-            period_secs: 0,
-            header_checks: vec![("Content-Type".into(), "text/plain".into())],
-            urls: vec![
-                format!("http://127.0.0.1:{}/ok", port),
-                format!("http://127.0.0.1:{}/err", port),
-            ],
-        };
+    fn test_round_json() {
+        let results = [check("https://a.test", Ok(200)), check("https://b.test", Ok(503))];
+        let line = round_json(&results, &results, &Config::default());
+        assert!(line.contains("\"checks\":[{"));
+        assert!(line.contains("\"url\":\"https://b.test\",\"status\":503"));
+        assert!(line.ends_with("\"summary\":{\"total\":2,\"up\":1,\"degraded\":0,\"down\":1,\"ignored\":0,\"avg_ms\":10,\"uptime_pct\":50.00,\"weighted_uptime_pct\":null,\"retries\":0,\"retry_pct\":0.00}}"));
+    }
 
-        let res = run_once(&cfg);
-        assert_eq!(res.len(), 2);
-        let ok = res.iter().find(|r| r.url.ends_with("/ok")).unwrap();
-        assert!(matches!(ok.status, Ok(c) if c == 200));
-        let err = res.iter().find(|r| r.url.ends_with("/err")).unwrap();
-        assert!(matches!(err.status, Ok(c) if c == 503));
+    #[test]
+    fn test_split_words() {
+        assert_eq!(split_words("--send-header 'X-A=b c' --retries  2").unwrap(), ["--send-header", "X-A=b c", "--retries", "2"]);
+        assert_eq!(split_words("\"\"").unwrap(), [""]);
+        assert!(split_words("'open").is_err());
     }
 
     #[test]
-    fn test_header_check() {
-        let port = 34568;
-        let _server = spawn_simple_http_server(port);
-        thread::sleep(Duration::from_millis(50));
-        let cfg = Config {
-            workers: 1,
-            timeout: Duration::from_millis(2000),
-            retries: 0,
-            period_secs: 0,
-            header_checks: vec![("Content-Type".into(), "text/plain".into())],
-            urls: vec![format!("http://127.0.0.1:{}/ok", port)],
-        };
-        let res = run_once(&cfg);
-        let r = &res[0];
-        assert!(matches!(r.status, Ok(200)));
+    fn test_config_file_overridden_by_flags() {
+        let path = env::temp_dir().join(format!("sitewatch-{}.toml", std::process::id()));
+        fs::write(&path, "workers = 20\ntimeout_ms = 2500\nurls = [\"https://a.test/\"]\n[headers]\nX-A = \"1\"\n").unwrap();
+        let args = ["--config", path.to_str().unwrap(), "--workers", "1", "https://b.test/"].map(String::from);
+        let cfg = parse_args_from(args.into_iter()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cfg.workers, 1);
+        assert_eq!(cfg.timeout, Duration::from_millis(2500));
+        assert_eq!(cfg.urls.len(), 2);
+        assert_eq!(&*cfg.header_checks, [("X-A".to_string(), "1".to_string())]);
     }
 
     #[test]
-    fn test_timeout_and_retry() {
-        let port = 34569;
-        let _server = spawn_simple_http_server(port);
-        thread::sleep(Duration::from_millis(50));
-        let cfg = Config {
-            workers: 2,
-            timeout: Duration::from_millis(50),
-            retries: 1,
-            period_secs: 0,
-            header_checks: vec![],
-            urls: vec![format!("http://127.0.0.1:{}/slow", port)],
+    fn test_config_profile() {
+        let path = env::temp_dir().join(format!("sitewatch-profile-{}.toml", std::process::id()));
+        fs::write(&path, "timeout_ms = 2500\nurls = [\"https://a.test/\"]\n[profile.prod]\nurls = [\"https://a.example/\"]\ntimeout_ms = 900\n").unwrap();
+        let parse = |extra: &[&str]| {
+            let args = ["--config", path.to_str().unwrap()].into_iter().chain(extra.iter().copied()).map(String::from);
+            parse_args_from(args)
         };
-        let res = run_once(&cfg);
-        let r = &res[0];
-        assert!(matches!(r.status, Err(_)));
+        let base = parse(&[]).unwrap();
+        let prod = parse(&["--profile", "prod"]).unwrap();
+        let unknown = parse(&["--profile", "dev"]);
+        let manifest = parse(&["--profile", "prod", "--manifest"]).unwrap().manifest.unwrap();
+        fs::remove_file(&path).unwrap();
+        //the manifest holds what the config file expanded to, not the --config path
+        assert_eq!(manifest.urls, 1);
+        assert_eq!(manifest.flags, ["--timeout-ms", "900", "https://a.example/", "--profile", "prod", "--manifest"]);
+        assert!(base.manifest.is_none());
+        assert_eq!((base.timeout, &*base.urls[0]), (Duration::from_millis(2500), "https://a.test/"));
+        assert_eq!((prod.timeout, prod.urls.len(), &*prod.urls[0]), (Duration::from_millis(900), 1, "https://a.example/"));
+        assert!(unknown.unwrap_err().contains("unknown profile 'dev'"));
+        assert!(parse_args_from(["--profile", "prod", "https://a.test/"].map(String::from).into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_url_file_settings() {
+        let path = env::temp_dir().join(format!("sitewatch-urls-{}.txt", std::process::id()));
+        fs::write(&path, "https://a.test/{1..2} timeout=10000 retries=3 expect=401  # auth wall\nhttps://b.test/\n").unwrap();
+        let cfg = parse_args_from(["--file", path.to_str().unwrap()].map(String::from).into_iter());
+        fs::write(&path, "https://a.test/ retries=x\n").unwrap();
+        let bad = parse_args_from(["--file", path.to_str().unwrap()].map(String::from).into_iter());
+        //an edited url file cannot leave the allowed hosts
+        fs::write(&path, "https://a.test/\nhttps://169.254.169.254/latest/meta-data\n").unwrap();
+        let railed = parse_args_from(["--allow-hosts", "a.test,*.a.test", "--file", path.to_str().unwrap()].map(String::from).into_iter());
+        fs::write(&path, "https://a.test/ schedule=\"*/5 * * * *\" tag=prod\nhttps://b.test/{1..2}\n    schedule=\"0 9 * * MON\"\nhttps://c.test/\n").unwrap();
+        let scheduled = parse_args_from(["--file", path.to_str().unwrap()].map(String::from).into_iter());
+        fs::write(&path, "https://a.test/ schedule=\"*/5 * *\"\n").unwrap();
+        let bad_schedule = parse_args_from(["--file", path.to_str().unwrap()].map(String::from).into_iter());
+        fs::write(&path, "https://a.test/ tag=prod\nhttps://b.test/\n    tag=staging\nhttps://c.test/\n").unwrap();
+        let tagged = parse_args_from(["--tags", "prod,eu", "--file", path.to_str().unwrap()].map(String::from).into_iter());
+        let untagged = parse_args_from(["--tags", "eu", "--file", path.to_str().unwrap()].map(String::from).into_iter());
+        fs::remove_file(&path).unwrap();
+        let cfg = cfg.unwrap();
+        assert_eq!(cfg.urls.len(), 3);
+        let opts = &cfg.url_options["https://a.test/2"];
+        assert_eq!((opts.timeout, opts.retries), (Some(Duration::from_secs(10)), Some(3)));
+        assert_eq!(opts.expect.as_ref().unwrap().to_string(), "401");
+        assert_eq!(cfg.note_for("https://a.test/1"), Some("auth wall"));
+        assert!(!cfg.url_options.contains_key("https://b.test/"));
+        assert!(bad.unwrap_err().ends_with("line 1: invalid retries 'x'"));
+        assert_eq!(railed.unwrap_err(), "https://169.254.169.254/latest/meta-data: host 169.254.169.254 is not in --allow-hosts");
+        assert_eq!(tagged.unwrap().urls, vec![Arc::from("https://a.test/")]);
+        assert_eq!(untagged.unwrap_err(), "--tags eu: no URL carries these tags");
+        //schedule= entries leave the round for the cron list, settings kept
+        let scheduled = scheduled.unwrap();
+        assert_eq!(scheduled.urls, vec![Arc::from("https://c.test/")]);
+        let cron: Vec<(&str, &str)> = scheduled.cron.iter().map(|(e, u)| (e.as_str(), u.as_str())).collect();
+        assert_eq!(cron, [("*/5 * * * *", "https://a.test/"), ("0 9 * * MON", "https://b.test/1"), ("0 9 * * MON", "https://b.test/2")]);
+        assert_eq!(scheduled.url_options["https://a.test/"].tags, ["prod"]);
+        assert!(bad_schedule.unwrap_err().contains("line 1: schedule: "));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_request_body_flags() {
+        let parse = |s: &str| parse_args_from(s.split_whitespace().map(String::from));
+        let cfg = parse("--method post --body {} --content-type application/json https://a.test/").unwrap();
+        assert_eq!((cfg.method.as_str(), cfg.body.as_deref()), ("POST", Some(&b"{}"[..])));
+        assert!(parse("--body {} https://a.test/").unwrap_err().contains("not GET"));
+        assert!(parse("--content-type text/plain --method POST https://a.test/").unwrap_err().contains("needs a request body"));
+    }
+
+    #[test]
+    fn test_duration_flags() {
+        let parse = |s: &str| parse_args_from(s.split_whitespace().map(String::from));
+        let cfg = parse("--timeout 2s --period 5m --feed-max-age 24h --memory-interval 90s --alert-latency 1.5s https://a.test/").unwrap();
+        assert_eq!((cfg.timeout, cfg.period_secs, cfg.feed_max_age), (Duration::from_secs(2), 300, Duration::from_secs(86_400)));
+        assert_eq!((cfg.memory_interval, cfg.alert_rules.latency_ms), (Duration::from_secs(90), Some(1500)));
+        //bare numbers keep the unit each flag had
+        let cfg = parse("--timeout-ms 2500 --period 30 --feed-max-age 48 --max-latency-ms 750 --cert-warn-days 2w https://a.test/").unwrap();
+        assert_eq!((cfg.timeout, cfg.period_secs, cfg.feed_max_age), (Duration::from_millis(2500), 30, Duration::from_secs(48 * 3600)));
+        assert_eq!((cfg.max_latency, cfg.cert_warn_days), (Some(Duration::from_millis(750)), Some(14)));
+        assert_eq!(parse("--timeout soon https://a.test/").unwrap_err(), "--timeout: invalid duration 'soon', expected e.g. 500ms, 2s, 5m, 24h or 1h30m");
+        assert_eq!(parse("--period 1.5s https://a.test/").unwrap_err(), "--period takes whole seconds");
+        assert!(parse("--memory-interval 0s https://a.test/").is_err());
+        assert_eq!(parse("--cert-warn-days 36h https://a.test/").unwrap_err(), "--cert-warn-days takes whole days, e.g. 3 or 2w");
+        assert!(parse("--cert-warn-days 12h https://a.test/").is_err());
+        assert_eq!(parse("--cert-warn-days 48h https://a.test/").unwrap().cert_warn_days, Some(2));
+    }
+
+    #[test]
+    fn test_live_table_order() {
+        let parse = |s: &str| parse_args_from(s.split_whitespace().map(String::from)).unwrap();
+        let finished = [check("https://c.test/", Ok(200)), check("https://a.test/", Ok(200)), check("https://b.test/", Ok(500))];
+        let urls = |rows: Vec<WebsiteStatus>| rows.into_iter().map(|r| r.url).collect::<Vec<_>>();
+        //without --live the table keeps finishing order
+        let cfg = parse("https://a.test/ https://b.test/ https://c.test/");
+        assert_eq!(urls(in_table_order(&finished, &cfg)), urls(finished.to_vec()));
+        let cfg = parse("--final-table https://a.test/ https://b.test/ https://c.test/");
+        assert!(cfg.live);
+        assert_eq!(urls(in_table_order(&finished, &cfg)), [finished[1].url.clone(), finished[2].url.clone(), finished[0].url.clone()]);
+        //live rows go through --only as well
+        let cfg = parse("--live --only failures https://a.test/ https://b.test/ https://c.test/");
+        let filter = ResultFilter::new(cfg.only.clone());
+        assert_eq!(urls(finished.iter().filter(|r| filter.shows(r, &cfg)).cloned().collect()), [finished[2].url.clone()]);
+        let json = parse_args_from("--live --output json https://a.test/".split_whitespace().map(String::from));
+        assert_eq!(json.unwrap_err(), "--live prints table rows, it cannot be combined with --output json");
+    }
+
+    #[test]
+    fn test_self_test_only() {
+        let parse = |s: &str| parse_args_from(s.split_whitespace().map(String::from)).unwrap();
+        assert!(self_test_only(&parse("--test-alerts --alert-webhook http://hook.test/")));
+        assert!(!self_test_only(&parse("--test-alerts --alert-webhook http://hook.test/ https://a.test/")));
+        //scheduled checks still run after the self-test
+        assert!(!self_test_only(&parse("--test-alerts --alert-webhook http://hook.test/ --at 2030-01-01T00:00:00Z https://a.test/")));
+        let cron = ["--test-alerts", "--alert-webhook", "http://hook.test/", "--cron", "*/5 * * * *", "https://a.test/"];
+        assert!(!self_test_only(&parse_args_from(cron.map(String::from).into_iter()).unwrap()));
+    }
+
+    #[test]
+    fn test_proxy_from_env() {
+        let env = |vars: &'static [(&'static str, &'static str)]| move |name: &str| vars.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string());
+        assert!(proxy_from_env(env(&[("NO_PROXY", "localhost")])).unwrap().is_none());
+        let p = proxy_from_env(env(&[("https_proxy", "http://proxy:3128"), ("no_proxy", "internal.test")])).unwrap().unwrap();
+        assert!(p.http.is_none() && p.for_url("https://a.test/").is_some() && p.for_url("https://x.internal.test/").is_none());
+        assert!(proxy_from_env(env(&[("HTTP_PROXY", "socks4://h")])).unwrap_err().starts_with("$HTTP_PROXY: invalid proxy"));
+        assert!(proxy_from_env(env(&[("HTTP_PROXY", "proxy:3128"), ("NO_PROXY", "a..b")])).unwrap_err().starts_with("$NO_PROXY: "));
+    }
+
+    #[test]
+    fn test_parse_weight() {
+        assert_eq!(parse_weight("https://a/?x=1=3").unwrap(), ("https://a/?x=1".to_string(), 3.0));
+        assert!(parse_weight("https://a=-1").is_err());
+    }
+}
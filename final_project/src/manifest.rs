@@ -0,0 +1,98 @@
+//run manifest: what produced a run's results, written once at start so logs can be traced back to it
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::json;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+//flags whose value is shown as <redacted>
+const SECRET_FLAGS: [&str; 2] = ["--influx-token", "--oauth-client-secret"];
+//--send-header names whose value is shown as <redacted>
+const SECRET_HEADERS: [&str; 4] = ["authorization", "proxy-authorization", "cookie", "x-api-key"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub version: &'static str,
+    //fnv-1a over the effective flags, config files already expanded
+    pub config_hash: String,
+    pub urls: usize,
+    //effective flags in order, secrets redacted
+    pub flags: Vec<String>,
+}
+
+fn fnv1a(words: &[String]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for w in words {
+        //the 0 separator keeps ["ab"] and ["a", "b"] apart
+        for b in w.bytes().chain([0]) {
+            h ^= b as u64;
+            h = h.wrapping_mul(0x100000001b3);
+        }
+    }
+    h
+}
+
+fn redact(flags: &[String]) -> Vec<String> {
+    let mut out = Vec::with_capacity(flags.len());
+    let mut prev = "";
+    for f in flags {
+        let secret = SECRET_FLAGS.contains(&prev)
+            || (prev == "--send-header" && f.split_once('=').is_some_and(|(k, _)| SECRET_HEADERS.contains(&k.trim().to_ascii_lowercase().as_str())));
+        out.push(match f.split_once('=') {
+            Some((k, _)) if secret && prev == "--send-header" => format!("{}=<redacted>", k),
+            _ if secret => "<redacted>".to_string(),
+            //the proxy stays visible, its password does not
+            _ if prev == "--proxy" => crate::redact_proxy(f),
+            _ => f.clone(),
+        });
+        prev = f;
+    }
+    out
+}
+
+impl Manifest {
+    //args are the effective ones, after --config files were expanded
+    pub fn new(args: &[String], urls: usize) -> Self {
+        Self { version: VERSION, config_hash: format!("{:016x}", fnv1a(args)), urls, flags: redact(args) }
+    }
+
+    pub fn json(&self, at: SystemTime) -> String {
+        let flags: Vec<String> = self.flags.iter().map(|f| json::string(f)).collect();
+        format!(
+            "{{\"ts_ms\":{},\"manifest\":{{\"version\":{},\"config_hash\":{},\"urls\":{},\"flags\":[{}]}}}}",
+            at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis(),
+            json::string(self.version),
+            json::string(&self.config_hash),
+            self.urls,
+            flags.join(","),
+        )
+    }
+
+    //for the table output
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("Run manifest: sitewatch {}, config {}, {} URL(s)", self.version, self.config_hash, self.urls),
+            format!("  flags: {}", self.flags.join(" ")),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_manifest() {
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+        let a = Manifest::new(&args("--retries 2 --influx-token abc --send-header Authorization=Bearer --send-header X-A=1 https://a.test/"), 1);
+        assert_eq!(a.flags, args("--retries 2 --influx-token <redacted> --send-header Authorization=<redacted> --send-header X-A=1 https://a.test/"));
+        assert_eq!(Manifest::new(&args("--proxy http://me:pw@proxy:3128"), 0).flags, args("--proxy http://me:<redacted>@proxy:3128"));
+        //same flags same hash, secrets included so a rotated token shows up
+        assert_eq!(a.config_hash, Manifest::new(&args("--retries 2 --influx-token abc --send-header Authorization=Bearer --send-header X-A=1 https://a.test/"), 1).config_hash);
+        assert_ne!(a.config_hash, Manifest::new(&args("--retries 2 --influx-token abd --send-header Authorization=Bearer --send-header X-A=1 https://a.test/"), 1).config_hash);
+        assert_ne!(fnv1a(&args("ab")), fnv1a(&args("a b")));
+        let line = Manifest::new(&args("--retries 2"), 3).json(UNIX_EPOCH + Duration::from_millis(5));
+        assert_eq!(line, format!("{{\"ts_ms\":5,\"manifest\":{{\"version\":\"{}\",\"config_hash\":\"{}\",\"urls\":3,\"flags\":[\"--retries\",\"2\"]}}}}", VERSION, format_args!("{:016x}", fnv1a(&args("--retries 2")))));
+    }
+}
@@ -0,0 +1,143 @@
+//the monitor's own footprint: rss plus rough sizes of what it keeps between rounds, for runs left alone for weeks
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::mem::size_of;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{json, Attempt, WebsiteStatus};
+
+//hourly unless --memory-interval says otherwise
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(3600);
+//samples in a row that all have to grow before it looks like a leak
+const LEAK_SAMPLES: usize = 6;
+//and by how much over those samples, in percent
+const LEAK_GROWTH_PCT: f64 = 20.0;
+
+//resident set size, from /proc (linux only)
+pub fn rss_bytes() -> Option<u64> {
+    let statm = fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    (page > 0).then(|| pages * page as u64)
+}
+
+//a hash map's table, keys and values as laid out; what they point to is not counted
+pub fn table_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+    map.capacity() * (size_of::<(K, V)>() + 1)
+}
+
+pub fn deque_bytes<T>(q: &VecDeque<T>) -> usize {
+    q.capacity() * size_of::<T>()
+}
+
+//heap a result owns (text, tries, meta), the struct itself not included
+pub fn status_bytes(r: &WebsiteStatus) -> usize {
+    let strings = r.title.as_ref().map_or(0, String::capacity)
+        + r.final_url.as_ref().map_or(0, String::capacity)
+        + r.redirects.iter().map(String::capacity).sum::<usize>()
+        + r.attempts.iter().map(|a| a.error.as_ref().map_or(0, String::capacity)).sum::<usize>()
+        + r.status.as_ref().err().map_or(0, |e| e.message.capacity());
+    strings + r.attempts.capacity() * size_of::<Attempt>() + r.meta.iter().map(|(k, v)| k.capacity() + v.to_string().len()).sum::<usize>()
+}
+
+//"41.2 MB", "512 KB", "300 B"
+pub fn human(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1u64 << 20) as f64),
+        b if b >= 1 << 10 => format!("{} KB", b >> 10),
+        b => format!("{} B", b),
+    }
+}
+
+//one reading
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub rss: Option<u64>,
+    //estimated bytes per structure, in the order given
+    pub parts: Vec<(&'static str, usize)>,
+}
+
+impl Sample {
+    pub fn take(parts: Vec<(&'static str, usize)>) -> Self {
+        Self { rss: rss_bytes(), parts }
+    }
+
+    pub fn line(&self) -> String {
+        let parts: Vec<String> = self.parts.iter().map(|(name, b)| format!("{} {}", name, human(*b as u64))).collect();
+        format!("rss {}; {}", self.rss.map(human).unwrap_or_else(|| "unknown".into()), parts.join(", "))
+    }
+
+    pub fn json(&self, at: SystemTime) -> String {
+        let parts: Vec<String> = self.parts.iter().map(|(name, b)| format!("{}:{}", json::string(name), b)).collect();
+        format!("{{\"ts_ms\":{},\"rss_bytes\":{},\"estimates\":{{{}}}}}", at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis(),
+            self.rss.map(|b| b.to_string()).unwrap_or_else(|| "null".into()), parts.join(","))
+    }
+}
+
+//when to take the next reading, and whether the last few say the process keeps growing
+#[derive(Debug)]
+pub struct MemoryWatch {
+    every: Duration,
+    last: Option<Instant>,
+    rss: VecDeque<u64>,
+}
+
+impl MemoryWatch {
+    pub fn new(every: Duration) -> Self {
+        Self { every, last: None, rss: VecDeque::new() }
+    }
+
+    //the first reading is one interval into the run, once startup allocations have settled
+    pub fn due(&mut self, now: Instant) -> bool {
+        let last = *self.last.get_or_insert(now);
+        if now.duration_since(last) < self.every { return false; }
+        self.last = Some(now);
+        true
+    }
+
+    //remembers the reading, returns a warning when rss grew at every one of the last LEAK_SAMPLES by LEAK_GROWTH_PCT in all
+    pub fn record(&mut self, sample: &Sample) -> Option<String> {
+        let rss = sample.rss?;
+        self.rss.push_back(rss);
+        while self.rss.len() > LEAK_SAMPLES { self.rss.pop_front(); }
+        if self.rss.len() < LEAK_SAMPLES { return None; }
+        let rising = self.rss.iter().zip(self.rss.iter().skip(1)).all(|(a, b)| b > a);
+        let (first, last) = (self.rss[0], rss);
+        let growth = (last - first) as f64 * 100.0 / first as f64;
+        if !rising || growth < LEAK_GROWTH_PCT { return None; }
+        let span = self.every * (LEAK_SAMPLES as u32 - 1);
+        Some(format!("memory grew at each of the last {} readings, {} -> {} (+{:.0}%) over {}s; possible leak ({})",
+            LEAK_SAMPLES, human(first), human(last), growth, span.as_secs(), sample.line()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_watch() {
+        let sample = |mb: u64| Sample { rss: Some(mb << 20), parts: vec![("aggregates", 2048), ("results", 300)] };
+        assert_eq!(sample(40).line(), "rss 40.0 MB; aggregates 2 KB, results 300 B");
+        assert_eq!(sample(1).json(UNIX_EPOCH + Duration::from_millis(7)), "{\"ts_ms\":7,\"rss_bytes\":1048576,\"estimates\":{\"aggregates\":2048,\"results\":300}}");
+
+        let mut w = MemoryWatch::new(Duration::from_secs(3600));
+        //steady with noise: never a warning
+        for mb in [40, 42, 41, 43, 42, 44, 43, 45] { assert_eq!(w.record(&sample(mb)), None); }
+        //rising, but slowly: 45 -> 50 is about 11%
+        for mb in [46, 47, 48, 49, 50] { assert_eq!(w.record(&sample(mb)), None); }
+        let mut w = MemoryWatch::new(Duration::from_secs(3600));
+        for mb in [40, 44, 48, 52, 56] { assert_eq!(w.record(&sample(mb)), None); }
+        let warning = w.record(&sample(60)).unwrap();
+        assert!(warning.starts_with("memory grew at each of the last 6 readings, 40.0 MB -> 60.0 MB (+50%) over 18000s"), "{}", warning);
+        //a drop ends the streak
+        assert_eq!(w.record(&sample(58)), None);
+        assert_eq!(w.record(&Sample { rss: None, parts: Vec::new() }), None);
+
+        let t = Instant::now();
+        let mut w = MemoryWatch::new(Duration::from_secs(60));
+        assert!(!w.due(t) && !w.due(t + Duration::from_secs(59)));
+        assert!(w.due(t + Duration::from_secs(60)) && !w.due(t + Duration::from_secs(61)));
+        assert!(rss_bytes().is_some_and(|b| b > 0));
+    }
+}
@@ -0,0 +1,223 @@
+//connect helpers shared by the probes that bypass ureq
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use base64::Engine;
+use ureq::rustls;
+use ureq::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use ureq::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs1KeyDer, PrivatePkcs8KeyDer, PrivateSec1KeyDer, ServerName, UnixTime};
+use url::Url;
+
+pub trait Stream: Read + Write {}
+impl<T: Read + Write> Stream for T {}
+
+//tls trust and dns overrides for every connection a check opens, ureq's and the raw probes' alike
+#[derive(Debug, Clone)]
+pub struct Network {
+    pub(crate) tls: Arc<rustls::ClientConfig>,
+    //hostname to address pairs from --hosts-file, consulted before dns
+    pub(crate) hosts: Arc<[(String, IpAddr)]>,
+}
+
+//bundled roots only, built once and shared by every default Network
+impl Default for Network {
+    fn default() -> Self {
+        static BUNDLED: OnceLock<Arc<rustls::ClientConfig>> = OnceLock::new();
+        let tls = BUNDLED.get_or_init(|| build_tls_config(&[], false, None).expect("bundled roots are valid")).clone();
+        Network { tls, hosts: Arc::from([]) }
+    }
+}
+
+//certificate chain (der, leaf first) and private key presented for mutual tls
+pub struct ClientIdentity {
+    pub chain: Vec<Vec<u8>>,
+    pub key: PrivateKeyDer<'static>,
+}
+
+//bundled web pki roots plus extra (der) ones; insecure skips chain and name checks, signatures are still verified
+pub fn build_tls_config(extra_roots: &[Vec<u8>], insecure: bool, client: Option<ClientIdentity>) -> Result<Arc<rustls::ClientConfig>, String> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(&[&rustls::version::TLS12, &rustls::version::TLS13])
+        .map_err(|e| format!("tls error: {}", e))?;
+    let builder = if insecure {
+        builder.dangerous().with_custom_certificate_verifier(Arc::new(NoVerify(provider.signature_verification_algorithms)))
+    } else {
+        let mut roots = rustls::RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+        for (i, der) in extra_roots.iter().enumerate() {
+            roots.add(CertificateDer::from(der.clone())).map_err(|e| format!("certificate {}: {}", i + 1, e))?;
+        }
+        builder.with_root_certificates(roots)
+    };
+    let config = match client {
+        Some(id) => {
+            let chain = id.chain.into_iter().map(CertificateDer::from).collect();
+            builder.with_client_auth_cert(chain, id.key).map_err(|e| format!("client certificate: {}", e))?
+        }
+        None => builder.with_no_client_auth(),
+    };
+    Ok(Arc::new(config))
+}
+
+//(label, der) of every pem block, in order
+fn pem_blocks(text: &str) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut blocks = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("-----BEGIN ") {
+        let after = &rest[start + "-----BEGIN ".len()..];
+        let label_end = after.find("-----").ok_or("malformed pem header")?;
+        let label = &after[..label_end];
+        let body = &after[label_end + 5..];
+        let end_marker = format!("-----END {}-----", label);
+        let end = body.find(&end_marker).ok_or_else(|| format!("unterminated {} block", label))?;
+        let b64: String = body[..end].chars().filter(|c| !c.is_whitespace()).collect();
+        let der = base64::engine::general_purpose::STANDARD.decode(b64)
+            .map_err(|e| format!("{} {}: invalid base64: {}", label.to_ascii_lowercase(), blocks.len() + 1, e))?;
+        blocks.push((label.to_string(), der));
+        rest = &body[end + end_marker.len()..];
+    }
+    Ok(blocks)
+}
+
+//der of every CERTIFICATE block in a pem file
+pub fn pem_certificates(text: &str) -> Result<Vec<Vec<u8>>, String> {
+    let certs: Vec<Vec<u8>> = pem_blocks(text)?.into_iter().filter(|(label, _)| label == "CERTIFICATE").map(|(_, der)| der).collect();
+    if certs.is_empty() { return Err("no CERTIFICATE blocks found".into()); }
+    Ok(certs)
+}
+
+//first private key in a pem file: pkcs#8, pkcs#1 rsa or sec1 ec
+pub fn pem_private_key(text: &str) -> Result<PrivateKeyDer<'static>, String> {
+    for (label, der) in pem_blocks(text)? {
+        match label.as_str() {
+            "PRIVATE KEY" => return Ok(PrivatePkcs8KeyDer::from(der).into()),
+            "RSA PRIVATE KEY" => return Ok(PrivatePkcs1KeyDer::from(der).into()),
+            "EC PRIVATE KEY" => return Ok(PrivateSec1KeyDer::from(der).into()),
+            "ENCRYPTED PRIVATE KEY" => return Err("encrypted private keys are not supported".into()),
+            _ => {}
+        }
+    }
+    Err("no PRIVATE KEY block found".into())
+}
+
+//--insecure: any certificate for any name
+#[derive(Debug)]
+struct NoVerify(rustls::crypto::WebPkiSupportedAlgorithms);
+
+impl ServerCertVerifier for NoVerify {
+    fn verify_server_cert(&self, _: &CertificateDer<'_>, _: &[CertificateDer<'_>], _: &ServerName<'_>, _: &[u8], _: UnixTime) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(&self, message: &[u8], cert: &CertificateDer<'_>, dss: &rustls::DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0)
+    }
+
+    fn verify_tls13_signature(&self, message: &[u8], cert: &CertificateDer<'_>, dss: &rustls::DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.0.supported_schemes()
+    }
+}
+
+//"/etc/hosts" lines: an address, then names; # starts a comment
+pub fn parse_hosts(text: &str) -> Result<Vec<(String, IpAddr)>, String> {
+    let mut hosts = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let mut words = line.split('#').next().unwrap_or_default().split_whitespace();
+        let Some(addr) = words.next() else { continue };
+        let ip: IpAddr = addr.parse().map_err(|_| format!("line {}: invalid address '{}'", i + 1, addr))?;
+        let before = hosts.len();
+        hosts.extend(words.map(|name| (name.to_string(), ip)));
+        if hosts.len() == before { return Err(format!("line {}: no hostname for {}", i + 1, addr)); }
+    }
+    Ok(hosts)
+}
+
+impl Network {
+    fn host_override(&self, host: &str) -> Option<IpAddr> {
+        let host = host.trim_matches(['[', ']']).trim_end_matches('.');
+        self.hosts.iter().find(|(name, _)| name.eq_ignore_ascii_case(host)).map(|(_, ip)| *ip)
+    }
+
+    //addresses of a url's host, overrides first
+    pub fn socket_addrs(&self, url: &Url, default_port: Option<u16>) -> io::Result<Vec<SocketAddr>> {
+        if let Some(ip) = url.host_str().and_then(|h| self.host_override(h)) {
+            let port = url.port_or_known_default().or(default_port)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no port"))?;
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+        url.socket_addrs(|| default_port)
+    }
+
+    //ureq resolver with the same overrides; netloc is host:port
+    pub fn resolve_netloc(&self, netloc: &str) -> io::Result<Vec<SocketAddr>> {
+        if let Some((host, port)) = netloc.rsplit_once(':')
+            && let Some(ip) = self.host_override(host)
+            && let Ok(port) = port.parse()
+        {
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+        netloc.to_socket_addrs().map(Iterator::collect)
+    }
+
+    //first resolved address that accepts, read/write timeouts set
+    pub fn connect_tcp(&self, url: &Url, default_port: Option<u16>, timeout: Duration) -> Result<TcpStream, String> {
+        let addrs = self.socket_addrs(url, default_port).map_err(|e| format!("dns error: {}", e))?;
+        let mut last_err = "dns returned no addresses".to_string();
+        for addr in addrs {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(tcp) => {
+                    tcp.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+                    tcp.set_write_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+                    return Ok(tcp);
+                }
+                Err(e) => last_err = format!("tcp connect to {} failed: {}", addr, e),
+            }
+        }
+        Err(last_err)
+    }
+
+    //tcp, wrapped in tls when asked
+    pub fn connect(&self, url: &Url, default_port: Option<u16>, timeout: Duration, tls: bool) -> Result<Box<dyn Stream>, String> {
+        let tcp = self.connect_tcp(url, default_port, timeout)?;
+        if !tls { return Ok(Box::new(tcp)); }
+        let conn = rustls::ClientConnection::new(self.tls.clone(), server_name(url)?).map_err(|e| format!("tls error: {}", e))?;
+        Ok(Box::new(rustls::StreamOwned::new(conn, tcp)))
+    }
+
+    //tls handshake only, no request sent
+    pub fn tls_handshake(&self, url: &Url, timeout: Duration) -> Result<Handshake, String> {
+        let mut tcp = self.connect_tcp(url, None, timeout)?;
+        let mut conn = rustls::ClientConnection::new(self.tls.clone(), server_name(url)?).map_err(|e| format!("tls error: {}", e))?;
+        while conn.is_handshaking() {
+            conn.complete_io(&mut tcp).map_err(|e| format!("tls handshake failed: {}", e))?;
+        }
+        let version = match conn.protocol_version() {
+            Some(rustls::ProtocolVersion::TLSv1_3) => "TLS 1.3".to_string(),
+            Some(rustls::ProtocolVersion::TLSv1_2) => "TLS 1.2".to_string(),
+            other => format!("{:?}", other),
+        };
+        let cipher = conn.negotiated_cipher_suite().map(|c| format!("{:?}", c.suite())).unwrap_or_else(|| "unknown".into());
+        let certs = conn.peer_certificates().ok_or("server sent no certificate")?;
+        Ok(Handshake { version, cipher, certs: certs.iter().map(|c| c.as_ref().to_vec()).collect() })
+    }
+}
+
+//ipv6 literals come bracketed from the url
+pub fn server_name(url: &Url) -> Result<ServerName<'static>, String> {
+    let host = url.host_str().ok_or("url has no host")?;
+    ServerName::try_from(host.trim_matches(['[', ']']).to_string()).map_err(|e| format!("invalid tls server name: {}", e))
+}
+
+//what a finished tls handshake settled on
+pub struct Handshake {
+    pub version: String,
+    pub cipher: String,
+    //der, leaf first
+    pub certs: Vec<Vec<u8>>,
+}
@@ -0,0 +1,106 @@
+//ntp:// checks: one sntp query, clock offset of this host against the server
+use std::net::UdpSocket;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use url::Url;
+
+use crate::{CheckError, Network};
+
+//seconds from 1900 (ntp era 0) to 1970
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+//64-bit ntp timestamp: seconds since 1900 and a 32-bit fraction
+fn to_ntp(t: SystemTime) -> u64 {
+    let d = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let frac = ((d.subsec_nanos() as u64) << 32) / 1_000_000_000;
+    ((d.as_secs() + NTP_UNIX_OFFSET) << 32) | frac
+}
+
+//nanoseconds since 1900, signed so timestamps can be subtracted
+fn ntp_nanos(ts: u64) -> i128 {
+    let secs = (ts >> 32) as i128;
+    let frac = (ts & 0xffff_ffff) as i128;
+    secs * 1_000_000_000 + ((frac * 1_000_000_000) >> 32)
+}
+
+fn read_ts(packet: &[u8], at: usize) -> u64 {
+    let mut b = [0u8; 8];
+    b.copy_from_slice(&packet[at..at + 8]);
+    u64::from_be_bytes(b)
+}
+
+//offset in ms of the server clock against ours, positive when we are behind
+pub fn offset_ms(net: &Network, url: &str, timeout: Duration) -> Result<i64, CheckError> {
+    let parsed = Url::parse(url).map_err(|e| CheckError::transport(format!("invalid url: {}", e)))?;
+    let addr = net.socket_addrs(&parsed, Some(123)).map_err(|e| CheckError::transport(format!("dns error: {}", e)))?
+        .into_iter().next().ok_or_else(|| CheckError::transport("dns returned no addresses"))?;
+    let bind = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+    let sock = UdpSocket::bind(bind).map_err(CheckError::transport)?;
+    sock.set_read_timeout(Some(timeout)).map_err(CheckError::transport)?;
+    sock.connect(addr).map_err(CheckError::transport)?;
+
+    //li 0, version 4, mode 3 (client); our transmit time comes back as the originate time
+    let mut req = [0u8; 48];
+    req[0] = 0x23;
+    let t1 = to_ntp(SystemTime::now());
+    req[40..48].copy_from_slice(&t1.to_be_bytes());
+    sock.send(&req).map_err(CheckError::transport)?;
+
+    let mut resp = [0u8; 68];
+    let n = sock.recv(&mut resp).map_err(|e| CheckError::transport(format!("no ntp reply from {}: {}", addr, e)))?;
+    let t4 = to_ntp(SystemTime::now());
+    if n < 48 {
+        return Err(CheckError::protocol(format!("short ntp reply ({} bytes)", n)));
+    }
+    if resp[0] & 0x07 != 4 {
+        return Err(CheckError::protocol(format!("ntp reply mode {} is not server", resp[0] & 0x07)));
+    }
+    //stratum 0 is a kiss-of-death, the code sits in the reference id
+    if resp[1] == 0 {
+        return Err(CheckError::protocol(format!("ntp kiss-of-death '{}'", String::from_utf8_lossy(&resp[12..16]))));
+    }
+    if read_ts(&resp, 24) != t1 {
+        return Err(CheckError::protocol("ntp reply does not answer our query"));
+    }
+    let (t2, t3) = (read_ts(&resp, 32), read_ts(&resp, 40));
+    let offset = ((ntp_nanos(t2) - ntp_nanos(t1)) + (ntp_nanos(t3) - ntp_nanos(t4))) / 2;
+    Ok((offset / 1_000_000) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    //server whose clock runs ahead by `ahead`
+    fn serve(ahead: Duration, stratum: u8) -> u16 {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = sock.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let mut buf = [0u8; 48];
+            let (_, from) = sock.recv_from(&mut buf).unwrap();
+            let now = to_ntp(SystemTime::now() + ahead);
+            let mut resp = [0u8; 48];
+            resp[0] = 0x24;
+            resp[1] = stratum;
+            resp[12..16].copy_from_slice(b"RATE");
+            resp[24..32].copy_from_slice(&buf[40..48]);
+            resp[32..40].copy_from_slice(&now.to_be_bytes());
+            resp[40..48].copy_from_slice(&now.to_be_bytes());
+            sock.send_to(&resp, from).unwrap();
+        });
+        port
+    }
+
+    #[test]
+    fn test_sntp_offset() {
+        let t = Duration::from_secs(2);
+        let port = serve(Duration::from_secs(5), 2);
+        let ms = offset_ms(&Network::default(), &format!("ntp://127.0.0.1:{}", port), t).unwrap();
+        assert!((4900..5100).contains(&ms), "offset {}", ms);
+
+        let port = serve(Duration::ZERO, 0);
+        let err = offset_ms(&Network::default(), &format!("ntp://127.0.0.1:{}", port), t).unwrap_err();
+        assert!(err.message.contains("RATE"));
+    }
+}
@@ -0,0 +1,191 @@
+//oauth2 client credentials: one access token shared by every check of the covered hosts, fetched again before it expires
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use crate::hosts::HostPolicy;
+use crate::json::{self, Value};
+
+//when the endpoint gives no expires_in
+const DEFAULT_EXPIRY: Duration = Duration::from_secs(3600);
+//longer lifetimes are refreshed daily anyway, so a bogus expires_in cannot overflow the clock
+const MAX_EXPIRY: Duration = Duration::from_secs(86_400);
+//refresh this long before expiry, at most; short lived tokens refresh at 90% of their life
+const MAX_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+struct Token {
+    access: String,
+    refresh_at: Instant,
+}
+
+pub struct OAuth {
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    //urls whose host is allowed here get the token
+    hosts: HostPolicy,
+    //held while fetching, so workers wait for one fetch instead of each starting their own
+    cached: Mutex<Option<Token>>,
+}
+
+//the secret and token stay out of debug output
+impl fmt::Debug for OAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth").field("token_url", &self.token_url).field("client_id", &self.client_id).field("scope", &self.scope).finish_non_exhaustive()
+    }
+}
+
+impl OAuth {
+    //hosts: comma separated --allow-hosts style patterns, None covers the token endpoint's host only
+    pub fn new(token_url: &str, client_id: &str, client_secret: &str, scope: Option<&str>, hosts: Option<&str>) -> Result<Self, String> {
+        let parsed = Url::parse(token_url).map_err(|e| format!("invalid oauth token url: {}", e))?;
+        if !matches!(parsed.scheme(), "http" | "https") { return Err("oauth token url must be http(s)".into()); }
+        let mut policy = HostPolicy::default();
+        match hosts {
+            Some(list) => policy.allow(list)?,
+            None => policy.allow(parsed.host_str().ok_or("oauth token url has no host")?)?,
+        }
+        Ok(Self {
+            token_url: token_url.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            scope: scope.map(String::from),
+            hosts: policy,
+            cached: Mutex::new(None),
+        })
+    }
+
+    //http(s) urls on a covered host
+    pub fn covers(&self, url: &str) -> bool {
+        (url.starts_with("http://") || url.starts_with("https://")) && self.hosts.check(url).is_ok()
+    }
+
+    //cached token, or a fresh one when it is about to expire
+    pub fn token(&self, agent: &ureq::Agent) -> Result<String, String> {
+        let mut cached = self.cached.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(t) = cached.as_ref().filter(|t| Instant::now() < t.refresh_at) {
+            return Ok(t.access.clone());
+        }
+        let fetched = self.fetch(agent)?;
+        let access = fetched.access.clone();
+        *cached = Some(fetched);
+        Ok(access)
+    }
+
+    //a 401 with the token means it was revoked or rotated early
+    pub fn invalidate(&self) {
+        *self.cached.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    fn fetch(&self, agent: &ureq::Agent) -> Result<Token, String> {
+        let mut form = vec![("grant_type", "client_credentials"), ("client_id", self.client_id.as_str()), ("client_secret", self.client_secret.as_str())];
+        if let Some(scope) = &self.scope { form.push(("scope", scope)); }
+        let body = match agent.post(&self.token_url).set("Accept", "application/json").send_form(&form) {
+            Ok(resp) => resp.into_string().map_err(|e| format!("oauth token response unreadable: {}", e))?,
+            Err(ureq::Error::Status(code, resp)) => {
+                let body = resp.into_string().unwrap_or_default();
+                let why = json::parse(&body).ok().and_then(|doc| json::path(&doc, "error_description").or(json::path(&doc, "error")).map(json::text));
+                return Err(format!("oauth token endpoint returned {}{}", code, why.map(|w| format!(": {}", w)).unwrap_or_default()));
+            }
+            Err(e) => return Err(format!("oauth token endpoint unreachable: {}", e)),
+        };
+        parse_token(&body, Instant::now())
+    }
+}
+
+fn parse_token(body: &str, now: Instant) -> Result<Token, String> {
+    let doc = json::parse(body).map_err(|e| format!("oauth token response is not json: {}", e))?;
+    let access = match json::path(&doc, "access_token") {
+        Some(Value::String(t)) if !t.is_empty() => t.clone(),
+        _ => return Err("oauth token response has no access_token".into()),
+    };
+    //some servers send the number as a string
+    let expires = match json::path(&doc, "expires_in") {
+        Some(Value::Number(n)) => n.as_f64().filter(|n| *n >= 0.0).map_or(DEFAULT_EXPIRY, |n| Duration::try_from_secs_f64(n).unwrap_or(MAX_EXPIRY)),
+        Some(Value::String(s)) => s.trim().parse().map(Duration::from_secs).map_err(|_| format!("invalid expires_in '{}'", s))?,
+        _ => DEFAULT_EXPIRY,
+    }
+    .min(MAX_EXPIRY);
+    let margin = (expires / 10).min(MAX_REFRESH_MARGIN);
+    let refresh_at = now.checked_add(expires - margin).ok_or("oauth token expiry out of range")?;
+    Ok(Token { access, refresh_at })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run_once, Config};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_client_credentials() {
+        //token endpoint and api on one server; the api takes only the newest token
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let counter = fetches.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut reader = BufReader::new(stream.unwrap());
+                let (mut head, mut len) = (String::new(), 0);
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" { break; }
+                    if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") { len = v.trim().parse().unwrap(); }
+                    head.push_str(&line);
+                }
+                let mut body = vec![0u8; len];
+                let _ = reader.read_exact(&mut body);
+                let body = String::from_utf8_lossy(&body);
+                let n = counter.load(Ordering::SeqCst);
+                let (status, reply) = if head.starts_with("POST /token ") {
+                    if !body.contains("grant_type=client_credentials") || !body.contains("client_secret=s3cret") || !body.contains("scope=read") {
+                        (401, r#"{"error":"invalid_client"}"#.to_string())
+                    } else {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        (200, format!(r#"{{"access_token":"t{}","token_type":"Bearer","expires_in":3600}}"#, n + 1))
+                    }
+                } else if head.contains(&format!("Authorization: Bearer t{}\r\n", n)) {
+                    (200, "{}".to_string())
+                } else {
+                    (401, "{}".to_string())
+                };
+                let _ = reader.get_mut().write_all(format!("HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, reply.len(), reply).as_bytes());
+            }
+        });
+        let base = format!("http://127.0.0.1:{}", port);
+        let oauth = Arc::new(OAuth::new(&format!("{}/token", base), "app", "s3cret", Some("read"), None).unwrap());
+        let cfg = Config { urls: vec![format!("{}/api/a", base).into(), format!("{}/api/b", base).into()], workers: 2, oauth: Some(oauth.clone()), ..Config::default() };
+        //one fetch serves both checks and the next round
+        assert!(run_once(&cfg).unwrap().iter().all(|r| r.status == Ok(200)));
+        assert!(run_once(&cfg).unwrap().iter().all(|r| r.status == Ok(200)));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        //a dropped token is fetched again
+        oauth.invalidate();
+        assert!(run_once(&cfg).unwrap().iter().all(|r| r.status == Ok(200)));
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        let bad = OAuth::new(&format!("{}/token", base), "app", "wrong", Some("read"), None).unwrap();
+        assert_eq!(bad.token(&ureq::agent()).unwrap_err(), "oauth token endpoint returned 401: invalid_client");
+        let scoped = OAuth::new("https://auth.test/token", "a", "b", None, Some("*.api.test")).unwrap();
+        assert!(scoped.covers("https://x.api.test/") && !scoped.covers("https://auth.test/") && !scoped.covers("tcp://x.api.test:1"));
+        assert!(OAuth::new("https://auth.test/token", "a", "b", None, None).unwrap().covers("https://auth.test/me"));
+
+        let now = Instant::now();
+        assert_eq!(parse_token(r#"{"access_token":"x","expires_in":"100"}"#, now).unwrap().refresh_at, now + Duration::from_secs(90));
+        assert_eq!(parse_token(r#"{"access_token":"x"}"#, now).unwrap().refresh_at, now + Duration::from_secs(3540));
+        assert!(parse_token(r#"{"token":"x"}"#, now).is_err());
+        //huge lifetimes from the server are capped, not a panic
+        let day = now + Duration::from_secs(86_400 - 60);
+        assert_eq!(parse_token(r#"{"access_token":"x","expires_in":1e300}"#, now).unwrap().refresh_at, day);
+        assert_eq!(parse_token(r#"{"access_token":"x","expires_in":"18446744073709551615"}"#, now).unwrap().refresh_at, day);
+        assert!(parse_token(r#"{"access_token":"x","expires_in":"soon"}"#, now).is_err());
+    }
+}
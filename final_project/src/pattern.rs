@@ -0,0 +1,326 @@
+//small backtracking regex for body checks: literals, ., [classes], \d \w \s, ^ $, groups with |,
+//* + ? {m,n} (lazy with a trailing ?), and a leading (?i) for case-insensitive matching
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Perl {
+    Digit,
+    Word,
+    Space,
+}
+
+impl Perl {
+    fn matches(self, c: char) -> bool {
+        match self {
+            Perl::Digit => c.is_ascii_digit(),
+            Perl::Word => c.is_alphanumeric() || c == '_',
+            Perl::Space => c.is_whitespace(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ClassItem {
+    Range(char, char),
+    Perl(Perl, bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Vec<ClassItem>, bool),
+    Start,
+    End,
+    //alternatives, each a sequence
+    Group(Vec<Vec<Node>>),
+    Repeat { node: Box<Node>, min: u32, max: Option<u32>, greedy: bool },
+}
+
+#[derive(Debug, Clone)]
+pub struct Regex {
+    source: String,
+    nodes: Vec<Node>,
+    icase: bool,
+}
+
+struct Parser {
+    s: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.s.get(self.pos).copied()
+    }
+
+    fn err(&self, msg: &str) -> String {
+        format!("{} at offset {}", msg, self.pos)
+    }
+
+    fn alternation(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alts = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alts.push(self.sequence()?);
+        }
+        Ok(alts)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut seq = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' { break; }
+            let atom = self.atom()?;
+            seq.push(self.quantified(atom)?);
+        }
+        Ok(seq)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let c = self.peek().ok_or_else(|| self.err("unexpected end"))?;
+        self.pos += 1;
+        match c {
+            '(' => {
+                if self.s[self.pos..].starts_with(&['?', ':']) { self.pos += 2; }
+                let alts = self.alternation()?;
+                if self.peek() != Some(')') { return Err(self.err("missing ')'")); }
+                self.pos += 1;
+                Ok(Node::Group(alts))
+            }
+            '[' => self.class(),
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '\\' => self.escape().map(|item| match item {
+                ClassItem::Range(c, _) => Node::Char(c),
+                perl => Node::Class(vec![perl], false),
+            }),
+            '*' | '+' | '?' => Err(self.err("nothing to repeat")),
+            c => Ok(Node::Char(c)),
+        }
+    }
+
+    //after a backslash, in or out of a class
+    fn escape(&mut self) -> Result<ClassItem, String> {
+        let c = self.peek().ok_or_else(|| self.err("trailing backslash"))?;
+        self.pos += 1;
+        Ok(match c {
+            'd' => ClassItem::Perl(Perl::Digit, false),
+            'D' => ClassItem::Perl(Perl::Digit, true),
+            'w' => ClassItem::Perl(Perl::Word, false),
+            'W' => ClassItem::Perl(Perl::Word, true),
+            's' => ClassItem::Perl(Perl::Space, false),
+            'S' => ClassItem::Perl(Perl::Space, true),
+            'n' => ClassItem::Range('\n', '\n'),
+            't' => ClassItem::Range('\t', '\t'),
+            'r' => ClassItem::Range('\r', '\r'),
+            c if c.is_ascii_alphanumeric() => return Err(self.err(&format!("unsupported escape \\{}", c))),
+            c => ClassItem::Range(c, c),
+        })
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.peek() == Some('^');
+        if negated { self.pos += 1; }
+        let mut items = Vec::new();
+        loop {
+            let c = self.peek().ok_or_else(|| self.err("missing ']'"))?;
+            self.pos += 1;
+            //a leading ] is literal
+            if c == ']' && !items.is_empty() { return Ok(Node::Class(items, negated)); }
+            let item = if c == '\\' { self.escape()? } else { ClassItem::Range(c, c) };
+            match item {
+                ClassItem::Range(lo, _) if self.peek() == Some('-') && self.s.get(self.pos + 1).is_some_and(|&n| n != ']') => {
+                    self.pos += 1;
+                    let mut hi = self.s[self.pos];
+                    self.pos += 1;
+                    if hi == '\\' {
+                        match self.escape()? {
+                            ClassItem::Range(h, _) => hi = h,
+                            _ => return Err(self.err("bad class range")),
+                        }
+                    }
+                    if hi < lo { return Err(self.err("bad class range")); }
+                    items.push(ClassItem::Range(lo, hi));
+                }
+                item => items.push(item),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) { self.pos += 1; }
+        self.s[start..self.pos].iter().collect::<String>().parse().ok()
+    }
+
+    fn quantified(&mut self, node: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => { self.pos += 1; (0, None) }
+            Some('+') => { self.pos += 1; (1, None) }
+            Some('?') => { self.pos += 1; (0, Some(1)) }
+            Some('{') => {
+                //not a valid {m,n} is a literal brace
+                let save = self.pos;
+                self.pos += 1;
+                let min = self.number();
+                let max = if self.peek() == Some(',') { self.pos += 1; self.number() } else { min };
+                match (min, self.peek()) {
+                    (Some(min), Some('}')) => {
+                        self.pos += 1;
+                        if max.is_some_and(|m| m < min) { return Err(self.err("bad repeat range")); }
+                        (min, max)
+                    }
+                    _ => { self.pos = save; return Ok(node); }
+                }
+            }
+            _ => return Ok(node),
+        };
+        if matches!(node, Node::Start | Node::End) { return Err(self.err("nothing to repeat")); }
+        let greedy = self.peek() != Some('?');
+        if !greedy { self.pos += 1; }
+        Ok(Node::Repeat { node: Box::new(node), min, max, greedy })
+    }
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let (icase, body) = match pattern.strip_prefix("(?i)") {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let mut p = Parser { s: body.chars().collect(), pos: 0 };
+        let alts = p.alternation()?;
+        if p.pos < p.s.len() { return Err(p.err("unmatched ')'")); }
+        Ok(Self { source: pattern.to_string(), nodes: vec![Node::Group(alts)], icase })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    //anywhere in the text, like a search
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = if self.icase { text.chars().flat_map(char::to_lowercase).collect() } else { text.chars().collect() };
+        (0..=text.len()).any(|start| self.seq(&self.nodes, &text, start, &mut |_| true))
+    }
+
+    fn same(&self, a: char, b: char) -> bool {
+        a == b || (self.icase && a.to_lowercase().eq(b.to_lowercase()))
+    }
+
+    fn in_class(&self, items: &[ClassItem], negated: bool, c: char) -> bool {
+        let hit = items.iter().any(|item| match *item {
+            ClassItem::Range(lo, hi) => {
+                (lo..=hi).contains(&c) || (self.icase && c.to_uppercase().any(|u| (lo..=hi).contains(&u)))
+            }
+            ClassItem::Perl(p, neg) => p.matches(c) != neg,
+        });
+        hit != negated
+    }
+
+    //position after a single-width node
+    fn step(&self, node: &Node, text: &[char], pos: usize) -> Option<usize> {
+        match node {
+            Node::Start => (pos == 0).then_some(pos),
+            Node::End => (pos == text.len()).then_some(pos),
+            _ => {
+                let &c = text.get(pos)?;
+                let ok = match node {
+                    Node::Char(want) => self.same(*want, c),
+                    Node::Any => c != '\n',
+                    Node::Class(items, negated) => self.in_class(items, *negated, c),
+                    _ => false,
+                };
+                ok.then_some(pos + 1)
+            }
+        }
+    }
+
+    //match nodes from pos, then hand the end position to k; true once k accepts
+    fn seq(&self, nodes: &[Node], text: &[char], pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+        let Some((first, rest)) = nodes.split_first() else { return k(pos) };
+        match first {
+            Node::Group(alts) => {
+                for alt in alts {
+                    if self.seq(alt, text, pos, &mut |p| self.seq(rest, text, p, k)) { return true; }
+                }
+                false
+            }
+            Node::Repeat { node, min, max, greedy } => {
+                self.repeat(node, (*min, *max, *greedy), 0, text, pos, &mut |p| self.seq(rest, text, p, k))
+            }
+            node => match self.step(node, text, pos) {
+                Some(p) => self.seq(rest, text, p, k),
+                None => false,
+            },
+        }
+    }
+
+    fn repeat(&self, node: &Node, q: (u32, Option<u32>, bool), count: u32, text: &[char], pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+        let (min, max, greedy) = q;
+        let one = std::slice::from_ref(node);
+        if count < min {
+            return self.seq(one, text, pos, &mut |p| self.repeat(node, q, count + 1, text, p, k));
+        }
+        if max.is_some_and(|m| count >= m) { return k(pos); }
+        //an empty iteration past the minimum would loop forever
+        let more = |k: &mut dyn FnMut(usize) -> bool| self.seq(one, text, pos, &mut |p| p != pos && self.repeat(node, q, count + 1, text, p, k));
+        if greedy { return more(k) || k(pos); }
+        k(pos) || more(k)
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+//one --expect-body or --expect-body-regex condition on a response body
+#[derive(Debug, Clone)]
+pub enum BodyCheck {
+    Contains(String),
+    Matches(Regex),
+}
+
+impl BodyCheck {
+    pub fn passes(&self, body: &str) -> bool {
+        match self {
+            BodyCheck::Contains(text) => body.contains(text.as_str()),
+            BodyCheck::Matches(re) => re.is_match(body),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            BodyCheck::Contains(text) => format!("body lacks \"{}\"", text),
+            BodyCheck::Matches(re) => format!("body does not match /{}/", re),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex() {
+        let m = |re: &str, text: &str| Regex::new(re).unwrap().is_match(text);
+        assert!(m("status: ok", "<p>status: ok</p>"));
+        assert!(m(r#""status":\s*"(ok|up)""#, r#"{"status": "up"}"#));
+        assert!(!m(r#""status":\s*"(ok|up)""#, r#"{"status": "down"}"#));
+        assert!(m(r"^\d{3}-\d{2,}$", "123-4567") && !m(r"^\d{3}-\d{2,}$", "123-4"));
+        assert!(m("colou?r", "color") && m("a[^0-9]c", "abc") && !m("a[^0-9]c", "a1c"));
+        assert!(m("(?i)welcome BACK", "Welcome back, user") && !m("welcome BACK", "Welcome back"));
+        assert!(m("(?i)[a-c]x", "BX"));
+        assert!(m("a.*?b", "axxb") && m("(a|)*c", "aac") && m("x{2}", "axxa") && m("a{,b", "a{,b"));
+        assert!(m("^$", "") && !m("a$", "ab"));
+        assert!(BodyCheck::Matches(Regex::new("v[0-9]+").unwrap()).passes("api v12"));
+        assert_eq!(BodyCheck::Contains("ok".into()).describe(), "body lacks \"ok\"");
+        for bad in ["(ab", "ab)", "*a", "[a-", r"a\", "a{3,1}", r"\q"] {
+            assert!(Regex::new(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//minimal http/1.x client that keeps the wire view ureq normalizes away (header casing, order, duplicates)
+use std::io::{BufRead, BufReader, Read, Write};
+use std::time::Duration;
+
+use url::Url;
+
+use crate::{HttpVersion, Network};
+
+//status and headers exactly as sent, plus up to body_limit bytes of body
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    pub status: u16,
+    //original casing and order, duplicates kept
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl RawResponse {
+    //first value of a header, case-insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+}
+
+//one request on a fresh connection, closed afterwards
+#[allow(clippy::too_many_arguments)]
+pub fn request(net: &Network, method: &str, url: &str, headers: &[(&str, &str)], body: &[u8], timeout: Duration, body_limit: u64, version: HttpVersion) -> Result<RawResponse, String> {
+    let url = Url::parse(url).map_err(|e| format!("invalid url: {}", e))?;
+    let tls = match url.scheme() {
+        "http" => false,
+        "https" => true,
+        other => return Err(format!("unsupported scheme '{}'", other)),
+    };
+    let mut stream = net.connect(&url, None, timeout, tls)?;
+
+    let mut target = url.path().to_string();
+    if let Some(q) = url.query() {
+        target.push('?');
+        target.push_str(q);
+    }
+    let host = match url.port() {
+        Some(p) => format!("{}:{}", url.host_str().unwrap_or_default(), p),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let mut head = format!("{} {} {}\r\nHost: {}\r\nAccept: */*\r\nConnection: close\r\n", method, target, version, host);
+    //a --user-agent among the headers replaces ours
+    if !headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("user-agent")) { head.push_str("User-Agent: sitewatch\r\n"); }
+    for (k, v) in headers {
+        head.push_str(&format!("{}: {}\r\n", k, v));
+    }
+    //servers want a length on anything that may carry a body
+    if !body.is_empty() || !matches!(method, "GET" | "HEAD") {
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    head.push_str("\r\n");
+    let mut out = head.into_bytes();
+    out.extend_from_slice(body);
+    stream.write_all(&out).map_err(|e| format!("write failed: {}", e))?;
+    stream.flush().map_err(|e| format!("write failed: {}", e))?;
+
+    let mut reader = BufReader::new(stream);
+    let mut resp = read_head(&mut reader)?;
+    //no body on HEAD, 204 or 304
+    if body_limit > 0 && method != "HEAD" && resp.status != 204 && resp.status != 304 {
+        resp.body = read_body(&mut reader, &resp, body_limit)?;
+    }
+    Ok(resp)
+}
+
+fn read_line(reader: &mut impl BufRead) -> Result<String, String> {
+    let mut line = String::new();
+    let n = reader.read_line(&mut line).map_err(|e| format!("read failed: {}", e))?;
+    if n == 0 { return Err("connection closed before the response head ended".into()); }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+//status line and headers, interim 1xx responses skipped (101 is final)
+pub fn read_head(reader: &mut impl BufRead) -> Result<RawResponse, String> {
+    loop {
+        let line = read_line(reader)?;
+        let mut parts = line.splitn(3, ' ');
+        let version = parts.next().unwrap_or_default();
+        if !version.starts_with("HTTP/") {
+            return Err(format!("not an http response: '{}'", line));
+        }
+        let status: u16 = parts.next().and_then(|s| s.parse().ok()).ok_or_else(|| format!("bad status line '{}'", line))?;
+
+        let mut headers = Vec::new();
+        loop {
+            let line = read_line(reader)?;
+            if line.is_empty() { break; }
+            match line.split_once(':') {
+                Some((k, v)) => headers.push((k.to_string(), v.trim().to_string())),
+                None => return Err(format!("malformed header line '{}'", line)),
+            }
+        }
+        if status == 101 || !(100..200).contains(&status) { return Ok(RawResponse { status, headers, body: Vec::new() }); }
+    }
+}
+
+fn read_body(reader: &mut impl BufRead, resp: &RawResponse, limit: u64) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    let chunked = resp.header("Transfer-Encoding").is_some_and(|v| v.to_ascii_lowercase().contains("chunked"));
+    if chunked {
+        while (body.len() as u64) < limit {
+            let size_line = read_line(reader)?;
+            let size = u64::from_str_radix(size_line.split(';').next().unwrap_or("").trim(), 16)
+                .map_err(|_| format!("bad chunk size '{}'", size_line))?;
+            if size == 0 { break; }
+            let want = size.min(limit - body.len() as u64);
+            reader.by_ref().take(want).read_to_end(&mut body).map_err(|e| format!("read failed: {}", e))?;
+            if want < size { break; }
+            read_line(reader)?;
+        }
+        return Ok(body);
+    }
+    let len = resp.header("Content-Length").and_then(|v| v.parse::<u64>().ok()).unwrap_or(u64::MAX);
+    reader.take(len.min(limit)).read_to_end(&mut body).map_err(|e| format!("read failed: {}", e))?;
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_raw_headers_and_chunked_body() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = s.read(&mut buf);
+            let _ = s.write_all(b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\ncontent-type: text/plain\r\nX-Dup: a\r\nx-dup: b\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n");
+        });
+        let resp = request(&Network::default(), "GET", &format!("http://127.0.0.1:{}/x?y=1", port), &[], &[], Duration::from_secs(2), 1024, HttpVersion::Http11).unwrap();
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.headers[0].0, "content-type");
+        assert_eq!(resp.headers.iter().filter(|(k, _)| k.eq_ignore_ascii_case("x-dup")).count(), 2);
+        assert_eq!(resp.header("CONTENT-TYPE"), Some("text/plain"));
+        assert_eq!(resp.body, b"abcde");
+    }
+}
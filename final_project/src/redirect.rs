@@ -0,0 +1,96 @@
+//redirect trouble: chains ureq gave up on are walked again hop by hop to tell loops from long chains
+use url::Url;
+
+use crate::{with_headers, CheckError, Config, ErrorKind};
+
+//hops retraced before calling it a long chain
+const MAX_HOPS: usize = 20;
+
+fn redirect(msg: String) -> CheckError {
+    CheckError::new(ErrorKind::Redirect, msg)
+}
+
+fn chain(hops: &[String]) -> String {
+    hops.join(" -> ")
+}
+
+//why following url ran out of redirects
+pub fn diagnose(url: &str, cfg: &Config) -> CheckError {
+    let agent = ureq::AgentBuilder::new()
+        .resolver({ let net = cfg.network.clone(); move |netloc: &str| net.resolve_netloc(netloc) })
+        .tls_config(cfg.network.tls.clone())
+        .redirects(0)
+        .timeout(cfg.timeout)
+        .build();
+    let mut hops = vec![url.to_string()];
+    while hops.len() <= MAX_HOPS {
+        let current = &hops[hops.len() - 1];
+        let resp = match with_headers(agent.get(current), &cfg.request_headers).call() {
+            Ok(resp) | Err(ureq::Error::Status(_, resp)) => resp,
+            Err(e) => return CheckError::new(ErrorKind::Transport, format!("transport error retracing redirects at {}: {}", current, e)),
+        };
+        let next = match resp.header("location") {
+            Some(loc) if (300..=399).contains(&resp.status()) => Url::parse(current).and_then(|base| base.join(loc)),
+            //the chain ended this time round, too long for the agent
+            _ => return redirect(format!("too many redirects: {}", chain(&hops))),
+        };
+        let next = match next {
+            Ok(next) => next.to_string(),
+            Err(e) => return redirect(format!("bad redirect location at {}: {}", current, e)),
+        };
+        let seen = hops.contains(&next);
+        hops.push(next);
+        if seen { return redirect(format!("redirect loop: {}", chain(&hops))); }
+    }
+    redirect(format!("too many redirects: more than {} hops from {}", MAX_HOPS, url))
+}
+
+//a page answering with <meta http-equiv="refresh">, the target resolved against the page url
+pub fn meta_refresh_error(page_url: &str, target: &str) -> CheckError {
+    let target = Url::parse(page_url).and_then(|base| base.join(target)).map(String::from).unwrap_or_else(|_| target.to_string());
+    redirect(format!("meta refresh redirect to {}", target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    //every path redirects as the table says
+    fn serve(routes: &'static [(&'static str, &'static str)]) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut s) = stream else { continue };
+                let mut buf = [0u8; 1024];
+                let n = s.read(&mut buf).unwrap_or(0);
+                let req = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = req.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let resp = match routes.iter().find(|(from, _)| *from == path) {
+                    Some((_, to)) => format!("HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", to),
+                    None => "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nOK".to_string(),
+                };
+                let _ = s.write_all(resp.as_bytes());
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let port = serve(&[("/a", "/b"), ("/b", "/c"), ("/c", "/a"), ("/1", "/2"), ("/2", "/3"), ("/3", "/done")]);
+        let cfg = Config::default();
+        let base = format!("http://127.0.0.1:{}", port);
+        let err = diagnose(&format!("{}/a", base), &cfg);
+        assert_eq!(err.kind, ErrorKind::Redirect);
+        assert_eq!(err.message, format!("redirect loop: {0}/a -> {0}/b -> {0}/c -> {0}/a", base));
+
+        let err = diagnose(&format!("{}/1", base), &cfg);
+        assert_eq!(err.message, format!("too many redirects: {0}/1 -> {0}/2 -> {0}/3 -> {0}/done", base));
+
+        assert_eq!(meta_refresh_error("https://a.test/x/y", "../z").message, "meta refresh redirect to https://a.test/z");
+    }
+}
@@ -0,0 +1,112 @@
+//self-contained html report of a run: latest results, health and uptime per url and the aggregates
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::scheduler::format_utc;
+use crate::{fleet_uptime, html, Config, Stats, WebsiteStatus, PROBE_OK};
+
+const STYLE: &str = "body{font:14px sans-serif;margin:24px;color:#222} h1{font-size:20px} h2{font-size:16px;margin-top:28px}
+table{border-collapse:collapse} th,td{padding:4px 10px;border-bottom:1px solid #ddd;text-align:left} td.num{text-align:right}
+.up{color:#2a7} .down{color:#c33} .bar{width:200px;height:12px;background:#e6b3b3;display:inline-block;vertical-align:middle}
+.bar span{display:block;height:12px;background:#4a8} .err{color:#c33;font-size:12px} .summary td{border:none;padding:2px 10px}";
+
+//scores below this are shown in red
+const HEALTHY_SCORE: u8 = 80;
+
+fn status_cell(r: &WebsiteStatus) -> String {
+    let (class, text) = match &r.status {
+        Ok(PROBE_OK) => ("up", "ok".to_string()),
+        Ok(code) => (if r.is_up() { "up" } else { "down" }, code.to_string()),
+        Err(_) => ("down", "ERR".to_string()),
+    };
+    format!("<td class=\"{}\">{}</td>", class, text)
+}
+
+//agg holds every round so far; the results table shows the latest round
+pub fn render(results: &[WebsiteStatus], agg: &HashMap<Arc<str>, Stats>, cfg: &Config, generated: SystemTime) -> String {
+    let mut out = format!("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>sitewatch report</title>\n<style>\n{}\n</style></head><body>\n", STYLE);
+    out.push_str(&format!("<h1>sitewatch report</h1>\n<p>Generated {}</p>\n", format_utc(generated)));
+
+    let (plain, weighted) = fleet_uptime(agg, cfg);
+    let samples: u64 = agg.values().map(|s| s.samples).sum();
+    let counted: u64 = agg.values().map(|s| s.counted()).sum();
+    let ok: u64 = agg.values().map(|s| s.ok).sum();
+    let ignored = if counted < samples { format!(", {} ignored", samples - counted) } else { String::new() };
+    out.push_str("<table class=\"summary\">\n");
+    out.push_str(&format!("<tr><td>URLs</td><td>{}</td></tr>\n<tr><td>Checks</td><td>{} ({} up, {} down{})</td></tr>\n", agg.len(), samples, ok, counted - ok, ignored));
+    out.push_str(&format!("<tr><td>Fleet uptime</td><td>{:.2}%</td></tr>\n", plain));
+    if !cfg.weights.is_empty() { out.push_str(&format!("<tr><td>Weighted uptime</td><td>{:.2}%</td></tr>\n", weighted)); }
+    out.push_str("</table>\n");
+
+    out.push_str(&format!("<h2>Latest results ({} checks)</h2>\n<table>\n<tr><th>Status</th><th>ms</th><th>EMA ms</th><th>Checked</th><th>URL</th></tr>\n", results.len()));
+    for r in results {
+        let mut url = html::escape(&r.url);
+        if let Err(e) = &r.status { url.push_str(&format!("<div class=\"err\">{}</div>", html::escape(&e.message))); }
+        //smoothed over every round so far
+        let ema = agg.get(&r.url).and_then(|s| s.ema_ms).map(|ms| format!("{:.0}", ms)).unwrap_or_else(|| "-".into());
+        out.push_str(&format!(
+            "<tr>{}<td class=\"num\">{}</td><td class=\"num\">{}</td><td>{}</td><td>{}</td></tr>\n",
+            status_cell(r),
+            r.response_time.as_millis(),
+            ema,
+            format_utc(r.timestamp.as_system_time()),
+            url,
+        ));
+    }
+    out.push_str("</table>\n");
+
+    //least healthy first
+    out.push_str("<h2>Health per URL</h2>\n<table>\n<tr><th>Health</th><th>Uptime</th><th></th><th>Samples</th><th>Avg ms</th><th>URL</th></tr>\n");
+    let mut urls: Vec<_> = agg.keys().collect();
+    urls.sort_by(|a, b| agg[*a].health_score().cmp(&agg[*b].health_score()).then_with(|| a.cmp(b)));
+    for url in urls {
+        let s = &agg[url];
+        let score = s.health_score();
+        out.push_str(&format!(
+            "<tr><td class=\"num {}\">{}</td><td><span class=\"bar\"><span style=\"width:{:.1}%\"></span></span></td><td class=\"num\">{:.2}%</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td>{}</td></tr>\n",
+            if score >= HEALTHY_SCORE { "up" } else { "down" },
+            score,
+            s.uptime_pct(),
+            s.uptime_pct(),
+            s.samples,
+            s.avg_ms(),
+            html::escape(url),
+        ));
+    }
+    out.push_str("</table>\n</body></html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+    use crate::{CheckError, ErrorKind};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_report_html() {
+        let results = [
+            status_for("http://a/?x=<1>", Ok(200), 12),
+            status_for("http://b/", Err(CheckError::new(ErrorKind::Transport, "refused & gone")), 3),
+        ];
+        let mut agg: HashMap<Arc<str>, Stats> = HashMap::new();
+        for r in results.iter().chain(&results[..1]) {
+            agg.entry(r.url.clone()).or_default().record(r);
+        }
+        let page = render(&results, &agg, &Config::default(), UNIX_EPOCH + Duration::from_secs(86_400));
+        assert!(page.contains("Generated 1970-01-02 00:00:00 UTC"));
+        assert!(page.contains("<td>3 (2 up, 1 down)</td>"));
+        assert!(page.contains("<td>Fleet uptime</td><td>66.67%</td>"));
+        assert!(page.contains("http://a/?x=&lt;1&gt;"));
+        assert!(page.contains("<div class=\"err\">refused &amp; gone</div>"));
+        assert!(page.contains("<span style=\"width:0.0%\"></span></span></td><td class=\"num\">0.00%</td><td class=\"num\">1</td>"));
+        assert!(!page.contains("Weighted uptime"));
+        //b failed its only check, so it leads the health table
+        let b = page.find("<td class=\"num down\">46</td>").unwrap();
+        assert!(b < page.find("<td class=\"num up\">100</td>").unwrap());
+        //a was 12ms in both rounds, b only seen once
+        assert!(page.contains("<td class=\"num\">12</td><td class=\"num\">12</td>"));
+    }
+}
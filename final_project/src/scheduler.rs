@@ -0,0 +1,208 @@
+//per-url schedules driving periodic, cron and one-off checks
+use std::cmp::Reverse;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::cron::CronExpr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schedule {
+    Every(Duration),
+    Once(SystemTime),
+    Cron(CronExpr),
+}
+
+#[derive(Debug)]
+struct Entry {
+    url: Arc<str>,
+    schedule: Schedule,
+    next: Option<SystemTime>,
+    //most recent failed check, for ordering the due list
+    last_failure: Option<SystemTime>,
+}
+
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    entries: Vec<Entry>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //periodic entries run immediately, one-offs at their time, cron at the next match
+    pub fn add(&mut self, url: Arc<str>, schedule: Schedule, now: SystemTime) {
+        let next = match &schedule {
+            Schedule::Every(_) => Some(now),
+            Schedule::Once(at) => Some(*at),
+            Schedule::Cron(expr) => expr.next_after(now),
+        };
+        self.entries.push(Entry { url, schedule, next, last_failure: None });
+    }
+
+    //failed checks move their url to the front of later due lists
+    pub fn record_failure(&mut self, url: &str, at: SystemTime) {
+        for e in self.entries.iter_mut().filter(|e| &*e.url == url) {
+            e.last_failure = Some(at);
+        }
+    }
+
+    //urls due at `now`, advancing each to its next run; the most recently failed come first,
+    //so when a round is longer than the period a recovering outage is seen early
+    pub fn due(&mut self, now: SystemTime) -> Vec<Arc<str>> {
+        let mut due = Vec::new();
+        for e in self.entries.iter_mut() {
+            let Some(next) = e.next else { continue };
+            if next > now { continue; }
+            due.push((e.url.clone(), e.last_failure));
+            e.next = match &e.schedule {
+                Schedule::Every(period) => {
+                    //skip runs missed while a long round was in flight
+                    let mut n = next + *period;
+                    if n <= now { n = now + *period; }
+                    Some(n)
+                }
+                Schedule::Once(_) => None,
+                Schedule::Cron(expr) => expr.next_after(now),
+            };
+        }
+        self.entries.retain(|e| e.next.is_some());
+        //stable, never-failed urls keep their order after the failed ones
+        due.sort_by_key(|(_, failed)| Reverse(*failed));
+        due.into_iter().map(|(url, _)| url).collect()
+    }
+
+    //earliest pending run, None once everything has finished
+    pub fn next_due(&self) -> Option<SystemTime> {
+        self.entries.iter().filter_map(|e| e.next).min()
+    }
+}
+
+//utc timestamp like 2024-07-01T00:05Z, seconds and +hh:mm offsets optional
+pub fn parse_timestamp(s: &str) -> Result<SystemTime, String> {
+    let bad = || format!("invalid time '{}', expected YYYY-MM-DDTHH:MM[:SS]Z", s);
+    let s = s.trim();
+    let (date, rest) = s.split_once(['T', ' ']).ok_or_else(bad)?;
+
+    let mut d = date.splitn(3, '-');
+    let year: i64 = d.next().and_then(|v| v.parse().ok()).ok_or_else(bad)?;
+    let month: u32 = d.next().and_then(|v| v.parse().ok()).ok_or_else(bad)?;
+    let day: u32 = d.next().and_then(|v| v.parse().ok()).ok_or_else(bad)?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) { return Err(bad()); }
+
+    //split time of day from the zone suffix
+    let (clock, offset_secs) = if let Some(c) = rest.strip_suffix(['Z', 'z']) {
+        (c, 0i64)
+    } else if let Some(pos) = rest.rfind(['+', '-']) {
+        let (c, zone) = rest.split_at(pos);
+        let sign = if zone.starts_with('-') { -1 } else { 1 };
+        let (hh, mm) = zone[1..].split_once(':').ok_or_else(bad)?;
+        let hh: i64 = hh.parse().map_err(|_| bad())?;
+        let mm: i64 = mm.parse().map_err(|_| bad())?;
+        (c, sign * (hh * 3600 + mm * 60))
+    } else {
+        return Err(format!("time '{}' needs a timezone, e.g. a trailing Z for UTC", s));
+    };
+
+    let parts: Vec<&str> = clock.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 { return Err(bad()); }
+    let hour: i64 = parts[0].parse().map_err(|_| bad())?;
+    let minute: i64 = parts[1].parse().map_err(|_| bad())?;
+    let second: i64 = match parts.get(2) { Some(v) => v.parse().map_err(|_| bad())?, None => 0 };
+    if hour > 23 || minute > 59 || second > 59 { return Err(bad()); }
+
+    let secs = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second - offset_secs;
+    if secs < 0 { return Err(bad()); }
+    Ok(UNIX_EPOCH + Duration::from_secs(secs as u64))
+}
+
+//"YYYY-MM-DD HH:MM:SS UTC"
+pub fn format_utc(t: SystemTime) -> String {
+    let secs = t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+    let (y, m, d) = civil_from_days(secs.div_euclid(86_400));
+    let rem = secs.rem_euclid(86_400);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", y, m, d, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+//days since 1970-01-01 for a proleptic gregorian date
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+//(year, month, day) for days since 1970-01-01
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        _ if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        _ => 28,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let t = parse_timestamp("2024-07-01T00:05Z").unwrap();
+        assert_eq!(t.duration_since(UNIX_EPOCH).unwrap().as_secs(), 1_719_792_300);
+        assert_eq!(parse_timestamp("2024-07-01T02:05:00+02:00").unwrap(), t);
+        assert!(parse_timestamp("2024-07-01T00:05").is_err());
+        assert!(parse_timestamp("2023-02-29T00:00Z").is_err());
+        assert_eq!(civil_from_days(days_from_civil(2024, 2, 29)), (2024, 2, 29));
+        assert_eq!(format_utc(t + Duration::from_secs(7)), "2024-07-01 00:05:07 UTC");
+    }
+
+    #[test]
+    fn test_scheduler_once_and_every() {
+        let t0 = UNIX_EPOCH + Duration::from_secs(1000);
+        let mut s = Scheduler::new();
+        s.add("p".into(), Schedule::Every(Duration::from_secs(10)), t0);
+        s.add("o".into(), Schedule::Once(t0 + Duration::from_secs(15)), t0);
+        assert_eq!(s.due(t0), vec!["p".into()]);
+        assert_eq!(s.next_due(), Some(t0 + Duration::from_secs(10)));
+        assert!(s.due(t0 + Duration::from_secs(5)).is_empty());
+        assert_eq!(s.due(t0 + Duration::from_secs(16)), vec!["p".into(), "o".into()]);
+        assert_eq!(s.next_due(), Some(t0 + Duration::from_secs(20)));
+
+        let mut s = Scheduler::new();
+        s.add("c".into(), Schedule::Cron(CronExpr::parse("*/2 * * * *").unwrap()), t0);
+        assert_eq!(s.next_due(), Some(UNIX_EPOCH + Duration::from_secs(1080)));
+        assert_eq!(s.due(UNIX_EPOCH + Duration::from_secs(1085)), vec!["c".into()]);
+        assert_eq!(s.next_due(), Some(UNIX_EPOCH + Duration::from_secs(1200)));
+    }
+
+    #[test]
+    fn test_recent_failures_first() {
+        let t0 = UNIX_EPOCH + Duration::from_secs(1000);
+        let mut s = Scheduler::new();
+        for url in ["a", "b", "c", "d"] {
+            s.add(url.into(), Schedule::Every(Duration::from_secs(10)), t0);
+        }
+        assert_eq!(s.due(t0), ["a", "b", "c", "d"].map(Arc::from));
+        s.record_failure("b", t0 + Duration::from_secs(1));
+        s.record_failure("d", t0 + Duration::from_secs(2));
+        assert_eq!(s.due(t0 + Duration::from_secs(10)), ["d", "b", "a", "c"].map(Arc::from));
+    }
+}
@@ -0,0 +1,414 @@
+//assert=EXPR: a small expression language for checks the flags cannot express, evaluated against each answer.
+//  status latency body headers["name"]   ints, "strings", true/false
+//  || && ! == != < <= > >= in + - * / % ( )   "x" in headers, "ok" in body
+//  len(s) lower(s) int(s) contains(s, t) starts_with(s, t) ends_with(s, t) matches(s, "regex")
+use std::fmt;
+
+use crate::pattern::Regex;
+
+//what an assertion sees of one answer
+#[derive(Debug, Clone, Default)]
+pub struct Context<'a> {
+    pub status: u16,
+    pub headers: &'a [(String, String)],
+    pub latency_ms: u64,
+    //first BODY_SCAN_BYTES, empty unless an assertion reads body
+    pub body: &'a str,
+}
+
+#[derive(Debug, Clone)]
+enum Value<'a> {
+    Int(i64),
+    Str(std::borrow::Cow<'a, str>),
+    Bool(bool),
+    Headers(&'a [(String, String)]),
+}
+
+impl Value<'_> {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Str(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::Headers(_) => "headers",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Var {
+    Status,
+    Latency,
+    Body,
+    Headers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Or, And, Eq, Ne, Lt, Le, Gt, Ge, In, Add, Sub, Mul, Div, Rem,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Int(i64),
+    Str(String),
+    Bool(bool),
+    Var(Var),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Index(Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    //regex compiled once, when the script is parsed
+    Matches(Box<Expr>, Regex),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(i64),
+    Str(String),
+    Ident(String),
+    Sym(&'static str),
+}
+
+const SYMBOLS: [&str; 19] = ["||", "&&", "==", "!=", "<=", ">=", "<", ">", "!", "+", "-", "*", "/", "%", "(", ")", "[", "]", ","];
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = src.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_digit() {
+            let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            tokens.push(Token::Int(rest[..end].parse().map_err(|_| format!("number too large: {}", &rest[..end]))?));
+            rest = &rest[end..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if c == '"' || c == '\'' {
+            //either quote, the other one can then appear inside; \ escapes only the quote and itself, regex escapes pass through
+            let mut s = String::new();
+            let mut chars = rest[1..].char_indices().peekable();
+            let end = loop {
+                match chars.next() {
+                    Some((i, q)) if q == c => break i + 2,
+                    Some((_, '\\')) if chars.peek().is_some_and(|&(_, e)| e == c || e == '\\') => s.extend(chars.next().map(|(_, e)| e)),
+                    Some((_, ch)) => s.push(ch),
+                    None => return Err("unterminated string".into()),
+                }
+            };
+            tokens.push(Token::Str(s));
+            rest = &rest[end..];
+        } else {
+            let sym = SYMBOLS.iter().find(|s| rest.starts_with(**s)).ok_or_else(|| format!("unexpected '{}'", c))?;
+            tokens.push(Token::Sym(sym));
+            rest = &rest[sym.len()..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, sym: &str) -> bool {
+        let hit = matches!(self.peek(), Some(Token::Sym(s)) if *s == sym);
+        if hit { self.pos += 1; }
+        hit
+    }
+
+    fn expect(&mut self, sym: &str) -> Result<(), String> {
+        if self.eat(sym) { Ok(()) } else { Err(format!("expected '{}'", sym)) }
+    }
+
+    //binary operators from loosest to tightest binding
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        const LEVELS: [&[(&str, Op)]; 5] = [
+            &[("||", Op::Or)],
+            &[("&&", Op::And)],
+            &[("==", Op::Eq), ("!=", Op::Ne), ("<=", Op::Le), (">=", Op::Ge), ("<", Op::Lt), (">", Op::Gt), ("in", Op::In)],
+            &[("+", Op::Add), ("-", Op::Sub)],
+            &[("*", Op::Mul), ("/", Op::Div), ("%", Op::Rem)],
+        ];
+        let Some(ops) = LEVELS.get(level) else { return self.unary() };
+        let mut left = self.binary(level + 1)?;
+        loop {
+            let op = ops.iter().find(|(sym, _)| match self.peek() {
+                Some(Token::Sym(s)) => s == sym,
+                Some(Token::Ident(word)) => word == sym,
+                _ => false,
+            });
+            let Some(&(_, op)) = op else { return Ok(left) };
+            self.pos += 1;
+            let right = self.binary(level + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+            //a == b == c reads as a mistake, not as a chain
+            if level == 2 { return Ok(left); }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("!") { return Ok(Expr::Not(Box::new(self.unary()?))); }
+        if self.eat("-") { return Ok(Expr::Neg(Box::new(self.unary()?))); }
+        let mut e = self.primary()?;
+        while self.eat("[") {
+            let key = self.binary(0)?;
+            self.expect("]")?;
+            e = Expr::Index(Box::new(e), Box::new(key));
+        }
+        Ok(e)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self.peek().cloned().ok_or("unexpected end of expression")?;
+        self.pos += 1;
+        match token {
+            Token::Int(n) => Ok(Expr::Int(n)),
+            Token::Str(s) => Ok(Expr::Str(s)),
+            Token::Sym("(") => {
+                let e = self.binary(0)?;
+                self.expect(")")?;
+                Ok(e)
+            }
+            Token::Ident(name) if self.eat("(") => self.call(name),
+            Token::Ident(name) => match name.as_str() {
+                "true" => Ok(Expr::Bool(true)),
+                "false" => Ok(Expr::Bool(false)),
+                "status" => Ok(Expr::Var(Var::Status)),
+                "latency" => Ok(Expr::Var(Var::Latency)),
+                "body" => Ok(Expr::Var(Var::Body)),
+                "headers" => Ok(Expr::Var(Var::Headers)),
+                _ => Err(format!("unknown name '{}'", name)),
+            },
+            Token::Sym(s) => Err(format!("unexpected '{}'", s)),
+        }
+    }
+
+    fn call(&mut self, name: String) -> Result<Expr, String> {
+        let mut args = Vec::new();
+        if !self.eat(")") {
+            loop {
+                args.push(self.binary(0)?);
+                if self.eat(")") { break; }
+                self.expect(",")?;
+            }
+        }
+        let arity = match name.as_str() {
+            "len" | "lower" | "int" => 1,
+            "contains" | "starts_with" | "ends_with" | "matches" => 2,
+            _ => return Err(format!("unknown function '{}'", name)),
+        };
+        if args.len() != arity { return Err(format!("{} takes {} argument(s), got {}", name, arity, args.len())); }
+        if name == "matches" {
+            let Some(Expr::Str(pattern)) = args.pop() else { return Err("matches needs a string literal pattern".into()) };
+            let re = Regex::new(&pattern).map_err(|e| format!("matches: {}", e))?;
+            return Ok(Expr::Matches(Box::new(args.remove(0)), re));
+        }
+        Ok(Expr::Call(name, args))
+    }
+}
+
+fn str_arg<'a>(v: Value<'a>, what: &str) -> Result<std::borrow::Cow<'a, str>, String> {
+    match v {
+        Value::Str(s) => Ok(s),
+        other => Err(format!("{} needs a string, got {}", what, other.type_name())),
+    }
+}
+
+fn int_arg(v: &Value, what: &str) -> Result<i64, String> {
+    match v {
+        Value::Int(n) => Ok(*n),
+        other => Err(format!("{} needs an int, got {}", what, other.type_name())),
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+}
+
+impl Expr {
+    fn eval<'a>(&self, cx: &Context<'a>) -> Result<Value<'a>, String> {
+        use std::borrow::Cow;
+        Ok(match self {
+            Expr::Int(n) => Value::Int(*n),
+            Expr::Str(s) => Value::Str(Cow::Owned(s.clone())),
+            Expr::Bool(b) => Value::Bool(*b),
+            Expr::Var(Var::Status) => Value::Int(cx.status as i64),
+            Expr::Var(Var::Latency) => Value::Int(cx.latency_ms as i64),
+            Expr::Var(Var::Body) => Value::Str(Cow::Borrowed(cx.body)),
+            Expr::Var(Var::Headers) => Value::Headers(cx.headers),
+            Expr::Not(e) => Value::Bool(!e.truth(cx)?),
+            Expr::Neg(e) => Value::Int(int_arg(&e.eval(cx)?, "-")?.wrapping_neg()),
+            //short-circuit, the right side may only make sense when the left holds
+            Expr::Binary(Op::And, l, r) => Value::Bool(l.truth(cx)? && r.truth(cx)?),
+            Expr::Binary(Op::Or, l, r) => Value::Bool(l.truth(cx)? || r.truth(cx)?),
+            Expr::Binary(op, l, r) => binary(*op, l.eval(cx)?, r.eval(cx)?)?,
+            Expr::Index(e, key) => match e.eval(cx)? {
+                //a missing header reads as "", use "name" in headers to tell the two apart
+                Value::Headers(h) => Value::Str(Cow::Borrowed(header(h, &str_arg(key.eval(cx)?, "header name")?).unwrap_or(""))),
+                other => return Err(format!("cannot index {}", other.type_name())),
+            },
+            Expr::Matches(e, re) => Value::Bool(re.is_match(&str_arg(e.eval(cx)?, "matches")?)),
+            Expr::Call(name, args) => {
+                let mut vals = args.iter().map(|a| a.eval(cx)).collect::<Result<Vec<_>, _>>()?.into_iter();
+                let mut next = || str_arg(vals.next().expect("arity checked at parse"), name);
+                match name.as_str() {
+                    "len" => Value::Int(next()?.chars().count() as i64),
+                    "lower" => Value::Str(Cow::Owned(next()?.to_lowercase())),
+                    "int" => {
+                        let s = next()?;
+                        Value::Int(s.trim().parse().map_err(|_| format!("int: '{}' is not a number", s))?)
+                    }
+                    "contains" => { let (s, t) = (next()?, next()?); Value::Bool(s.contains(&*t)) }
+                    "starts_with" => { let (s, t) = (next()?, next()?); Value::Bool(s.starts_with(&*t)) }
+                    "ends_with" => { let (s, t) = (next()?, next()?); Value::Bool(s.ends_with(&*t)) }
+                    _ => unreachable!("functions checked at parse"),
+                }
+            }
+        })
+    }
+
+    fn truth(&self, cx: &Context) -> Result<bool, String> {
+        match self.eval(cx)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(format!("expected a condition, got {}", other.type_name())),
+        }
+    }
+
+    fn uses_body(&self) -> bool {
+        match self {
+            Expr::Var(v) => *v == Var::Body,
+            Expr::Not(e) | Expr::Neg(e) | Expr::Matches(e, _) => e.uses_body(),
+            Expr::Binary(_, l, r) | Expr::Index(l, r) => l.uses_body() || r.uses_body(),
+            Expr::Call(_, args) => args.iter().any(Expr::uses_body),
+            Expr::Int(_) | Expr::Str(_) | Expr::Bool(_) => false,
+        }
+    }
+}
+
+fn binary<'a>(op: Op, l: Value<'a>, r: Value<'a>) -> Result<Value<'a>, String> {
+    use std::cmp::Ordering;
+    let order = |l: &Value, r: &Value| -> Result<Ordering, String> {
+        match (l, r) {
+            (Value::Int(a), Value::Int(b)) => Ok(a.cmp(b)),
+            (Value::Str(a), Value::Str(b)) => Ok(a.cmp(b)),
+            (Value::Bool(a), Value::Bool(b)) => Ok(a.cmp(b)),
+            (a, b) => Err(format!("cannot compare {} with {}", a.type_name(), b.type_name())),
+        }
+    };
+    Ok(match op {
+        Op::Eq => Value::Bool(order(&l, &r)? == Ordering::Equal),
+        Op::Ne => Value::Bool(order(&l, &r)? != Ordering::Equal),
+        Op::Lt => Value::Bool(order(&l, &r)? == Ordering::Less),
+        Op::Le => Value::Bool(order(&l, &r)? != Ordering::Greater),
+        Op::Gt => Value::Bool(order(&l, &r)? == Ordering::Greater),
+        Op::Ge => Value::Bool(order(&l, &r)? != Ordering::Less),
+        Op::In => match (l, r) {
+            (Value::Str(needle), Value::Str(hay)) => Value::Bool(hay.contains(&*needle)),
+            (Value::Str(name), Value::Headers(h)) => Value::Bool(header(h, &name).is_some()),
+            (a, b) => return Err(format!("cannot look for {} in {}", a.type_name(), b.type_name())),
+        },
+        Op::Add if matches!((&l, &r), (Value::Str(_), Value::Str(_))) => {
+            Value::Str(format!("{}{}", str_arg(l, "+")?, str_arg(r, "+")?).into())
+        }
+        Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Rem => {
+            let (a, b) = (int_arg(&l, "arithmetic")?, int_arg(&r, "arithmetic")?);
+            let v = match op {
+                Op::Add => a.checked_add(b),
+                Op::Sub => a.checked_sub(b),
+                Op::Mul => a.checked_mul(b),
+                Op::Div => a.checked_div(b),
+                _ => a.checked_rem(b),
+            };
+            Value::Int(v.ok_or("arithmetic overflow or division by zero")?)
+        }
+        Op::And | Op::Or => unreachable!("short-circuited in eval"),
+    })
+}
+
+//one parsed assertion, kept with its source for messages
+#[derive(Debug, Clone)]
+pub struct Script {
+    source: String,
+    expr: Expr,
+}
+
+impl PartialEq for Script {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Script {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let err = |e: String| format!("assertion '{}': {}", source, e);
+        let mut p = Parser { tokens: tokenize(source).map_err(err)?, pos: 0 };
+        let expr = p.binary(0).map_err(err)?;
+        if p.pos < p.tokens.len() { return Err(err(format!("unexpected {:?} after the expression", p.tokens[p.pos]))); }
+        Ok(Self { source: source.to_string(), expr })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    //the check reads the start of the body only for assertions that look at it
+    pub fn uses_body(&self) -> bool {
+        self.expr.uses_body()
+    }
+
+    //Err says why it did not hold, or why it could not be evaluated
+    pub fn check(&self, cx: &Context) -> Result<(), String> {
+        match self.expr.truth(cx) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(format!("assertion failed: {}", self.source)),
+            Err(e) => Err(format!("assertion error: {}: {}", self.source, e)),
+        }
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script() {
+        let headers = vec![("Content-Type".to_string(), "application/json".to_string()), ("X-Cache".to_string(), "HIT".to_string())];
+        let cx = Context { status: 201, headers: &headers, latency_ms: 120, body: "{\"ok\":true,\"version\":\"2.4.1\"}" };
+        let holds = |src: &str| Script::parse(src).unwrap().check(&cx);
+        assert_eq!(holds("status >= 200 && status < 300 && latency < 500"), Ok(()));
+        assert_eq!(holds("starts_with(headers[\"content-type\"], 'application/') && lower(headers['x-cache']) == 'hit'"), Ok(()));
+        assert_eq!(holds("'X-Age' in headers || headers['x-age'] == ''"), Ok(()));
+        assert_eq!(holds("!('x-age' in headers) && '\"ok\":true' in body && matches(body, \"\\\"version\\\":\\\"2\\.\\d+\")"), Ok(()));
+        assert_eq!(holds("(latency + 80) * 2 == 400 && status % 100 == 1 && -status < 0 && len('héllo') == 5"), Ok(()));
+        assert_eq!(holds("status == 200 || latency > 1000"), Err("assertion failed: status == 200 || latency > 1000".into()));
+        //the right side of || is not evaluated once the left holds
+        assert_eq!(holds("true || int(body) > 0"), Ok(()));
+        assert_eq!(holds("int(body) > 0"), Err(format!("assertion error: int(body) > 0: int: '{}' is not a number", cx.body)));
+        assert_eq!(holds("status == 'ok'"), Err("assertion error: status == 'ok': cannot compare int with string".into()));
+        assert_eq!(holds("status"), Err("assertion error: status: expected a condition, got int".into()));
+
+        assert!(Script::parse("status ==").unwrap_err().contains("unexpected end"));
+        assert!(Script::parse("status == 200 == true").unwrap_err().contains("after the expression"));
+        assert!(Script::parse("size(body) > 0").unwrap_err().contains("unknown function 'size'"));
+        assert!(Script::parse("matches(body, lower('x'))").unwrap_err().contains("string literal"));
+        assert!(Script::parse("body == 'open").unwrap_err().contains("unterminated"));
+        assert!(Script::parse("lengthy > 1").unwrap_err().contains("unknown name 'lengthy'"));
+        assert!(Script::parse("'ok' in body").unwrap().uses_body());
+        assert!(!Script::parse("headers['etag'] != ''").unwrap().uses_body());
+    }
+}
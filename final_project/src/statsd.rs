@@ -0,0 +1,88 @@
+//dogstatsd over udp: a latency histogram and success/failure counters per check, tagged by url
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+use crate::WebsiteStatus;
+
+//keeps datagrams under a typical mtu
+const MAX_PACKET: usize = 1432;
+
+//tag values may not hold the separators of the datagram format
+fn tag(s: &str) -> String {
+    s.replace(['|', ',', '#', '\n'], "_")
+}
+
+//metric lines of one check
+pub fn lines(r: &WebsiteStatus, prefix: &str) -> Vec<String> {
+    let mut tags = format!("url:{}", tag(&r.url));
+    if let Ok(code) = r.status { tags.push_str(&format!(",status:{}", code)); }
+    let outcome = if r.is_up() { "success" } else { "failure" };
+    let mut out = vec![
+        format!("{}.response_time:{}|h|#{}", prefix, r.response_time.as_millis(), tags),
+        format!("{}.check.{}:1|c|#{}", prefix, outcome, tags),
+    ];
+    if let Some(tcp) = r.tcp_connect { out.push(format!("{}.tcp_connect:{}|h|#{}", prefix, tcp.as_millis(), tags)); }
+    if r.retries > 0 { out.push(format!("{}.check.retries:{}|c|#{}", prefix, r.retries, tags)); }
+    out
+}
+
+//connected udp socket; sends are fire-and-forget
+#[derive(Debug)]
+pub struct StatsdSink {
+    sock: UdpSocket,
+    prefix: String,
+}
+
+impl StatsdSink {
+    pub fn connect(addr: &str, prefix: &str) -> io::Result<Self> {
+        let target = addr.to_socket_addrs()?.next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address"))?;
+        let sock = UdpSocket::bind(if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })?;
+        sock.connect(target)?;
+        Ok(Self { sock, prefix: prefix.to_string() })
+    }
+
+    //lines packed newline-separated into as few datagrams as fit
+    pub fn send(&self, results: &[WebsiteStatus]) -> io::Result<()> {
+        let mut packet = String::new();
+        for line in results.iter().flat_map(|r| lines(r, &self.prefix)) {
+            if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET {
+                self.sock.send(packet.as_bytes())?;
+                packet.clear();
+            }
+            if !packet.is_empty() { packet.push('\n'); }
+            packet.push_str(&line);
+        }
+        if !packet.is_empty() { self.sock.send(packet.as_bytes())?; }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+    use crate::{CheckError, ErrorKind};
+    use std::time::Duration;
+
+    #[test]
+    fn test_statsd_datagrams() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let sink = StatsdSink::connect(&server.local_addr().unwrap().to_string(), "sw").unwrap();
+
+        let down = status_for("http://b/x,y", Err(CheckError::new(ErrorKind::Transport, "refused")), 7);
+        let many: Vec<_> = (0..40).map(|i| status_for(&format!("http://a/{}", i), Ok(200), 12)).collect();
+        sink.send(&[down]).unwrap();
+        sink.send(&many).unwrap();
+
+        let mut buf = [0u8; 2048];
+        let n = server.recv(&mut buf).unwrap();
+        let first = String::from_utf8_lossy(&buf[..n]).to_string();
+        assert_eq!(first, "sw.response_time:7|h|#url:http://b/x_y\nsw.check.failure:1|c|#url:http://b/x_y");
+        //80 lines do not fit one datagram
+        let n = server.recv(&mut buf).unwrap();
+        assert!(n <= MAX_PACKET);
+        assert!(String::from_utf8_lossy(&buf[..n]).starts_with("sw.response_time:12|h|#url:http://a/0,status:200\nsw.check.success:1|c|"));
+    }
+}
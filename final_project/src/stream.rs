@@ -0,0 +1,121 @@
+//stream: checks for sse, streaming and long-poll endpoints: data has to start quickly and keep flowing
+use std::io::{ErrorKind as IoErrorKind, Read};
+use std::time::{Duration, Instant};
+
+use crate::CheckError;
+
+//http(s) url to open for a stream:// or stream:https://... entry
+pub fn target(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("stream:")?;
+    if rest.starts_with("http://") || rest.starts_with("https://") {
+        return Some(rest.to_string());
+    }
+    rest.strip_prefix("//").map(|r| format!("https://{}", r))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StreamLimits {
+    //longest wait for the first body byte
+    pub first_byte: Duration,
+    //how long the stream is read once it started
+    pub read_for: Duration,
+    pub min_bytes_per_sec: Option<u64>,
+}
+
+//what a stream delivered in its window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamStats {
+    pub status: u16,
+    pub first_byte: Duration,
+    pub bytes: u64,
+    //reading time after the first byte, shorter than the window when the server closed early
+    pub read: Duration,
+}
+
+//reads until the window is over or the server ends the body; http errors answer without a stream
+pub fn check(req: ureq::Request, limits: StreamLimits) -> Result<StreamStats, CheckError> {
+    let start = Instant::now();
+    let resp = match req.timeout(limits.first_byte + limits.read_for).call() {
+        Ok(resp) => resp,
+        Err(ureq::Error::Status(code, _)) => return Ok(StreamStats { status: code, first_byte: start.elapsed(), bytes: 0, read: Duration::ZERO }),
+        Err(e) => return Err(CheckError::transport(format!("transport error: {}", e))),
+    };
+    let status = resp.status();
+    let mut reader = resp.into_reader();
+    let mut buf = [0u8; 16 * 1024];
+    let mut bytes = 0u64;
+    let mut first_byte = None;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(n) => n,
+            //the request deadline ends the window mid-read
+            Err(e) if matches!(e.kind(), IoErrorKind::TimedOut | IoErrorKind::WouldBlock) => 0,
+            Err(e) => return Err(CheckError::transport(format!("stream read failed after {} bytes: {}", bytes, e))),
+        };
+        if n == 0 { break; }
+        bytes += n as u64;
+        let first = *first_byte.get_or_insert_with(|| start.elapsed());
+        if start.elapsed() >= first + limits.read_for { break; }
+    }
+    let Some(first) = first_byte else {
+        return Err(CheckError::protocol(format!("no stream data within {}ms", start.elapsed().as_millis())));
+    };
+    if first > limits.first_byte {
+        return Err(CheckError::protocol(format!("first stream byte after {}ms (max {}ms)", first.as_millis(), limits.first_byte.as_millis())));
+    }
+    let read = start.elapsed().saturating_sub(first);
+    if let Some(min) = limits.min_bytes_per_sec {
+        let rate = bytes as f64 / read.as_secs_f64().max(0.001);
+        if rate < min as f64 {
+            return Err(CheckError::protocol(format!("stream delivered {:.0} B/s over {}ms (min {} B/s)", rate, read.as_millis(), min)));
+        }
+    }
+    Ok(StreamStats { status, first_byte: first, bytes, read })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread;
+
+    //chunked stream: waits `delay`, then `chunks` events `gap` apart
+    fn serve(delay: Duration, chunks: usize, gap: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let mut req = [0u8; 1024];
+            let _ = s.read(&mut req);
+            let _ = s.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\n\r\n");
+            thread::sleep(delay);
+            for i in 0..chunks {
+                let event = format!("data: {}\n\n", i);
+                if s.write_all(format!("{:x}\r\n{}\r\n", event.len(), event).as_bytes()).is_err() { return; }
+                thread::sleep(gap);
+            }
+            let _ = s.write_all(b"0\r\n\r\n");
+        });
+        format!("http://127.0.0.1:{}/events", port)
+    }
+
+    #[test]
+    fn test_stream_limits() {
+        assert_eq!(target("stream://a.test/sse").as_deref(), Some("https://a.test/sse"));
+        let limits = StreamLimits { first_byte: Duration::from_millis(300), read_for: Duration::from_millis(300), min_bytes_per_sec: Some(10) };
+
+        let url = serve(Duration::ZERO, 100, Duration::from_millis(20));
+        let stats = check(ureq::get(&url), limits).unwrap();
+        assert_eq!(stats.status, 200);
+        assert!(stats.bytes > 10 && stats.read >= Duration::from_millis(280), "{:?}", stats);
+
+        //late, but inside the request deadline
+        let url = serve(Duration::from_millis(450), 1, Duration::ZERO);
+        assert!(check(ureq::get(&url), limits).unwrap_err().message.contains("first stream byte after"));
+
+        let url = serve(Duration::ZERO, 100, Duration::from_millis(20));
+        let strict = StreamLimits { min_bytes_per_sec: Some(100_000), ..limits };
+        assert!(check(ureq::get(&url), strict).unwrap_err().message.contains("B/s"));
+    }
+}
@@ -0,0 +1,129 @@
+//current state of every url, rewritten in place so readers always see one whole round
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{json, memory, DateTime, Stats, Utc, WebsiteStatus, PROBE_OK};
+
+struct UrlState {
+    last: WebsiteStatus,
+    //first result of the current up/down streak
+    since: DateTime<Utc>,
+}
+
+//latest result per url across rounds, scheduled urls may skip some
+#[derive(Default)]
+pub struct Current {
+    urls: HashMap<Arc<str>, UrlState>,
+}
+
+fn ms(t: SystemTime) -> u128 {
+    t.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()
+}
+
+impl Current {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //rough bytes held, for --memory-log
+    pub fn heap_bytes(&self) -> usize {
+        memory::table_bytes(&self.urls) + self.urls.values().map(|s| memory::status_bytes(&s.last)).sum::<usize>()
+    }
+
+    pub fn record(&mut self, results: &[WebsiteStatus]) {
+        for r in results {
+            match self.urls.get_mut(&r.url) {
+                Some(s) => {
+                    if s.last.is_up() != r.is_up() { s.since = r.timestamp; }
+                    s.last = r.clone();
+                }
+                None => { self.urls.insert(r.url.clone(), UrlState { last: r.clone(), since: r.timestamp }); }
+            }
+        }
+    }
+
+    //one json object: fleet counts, then a compact entry per url sorted by url; agg adds uptime so far
+    pub fn json(&self, agg: &HashMap<Arc<str>, Stats>, generated: SystemTime) -> String {
+        let mut urls: Vec<_> = self.urls.iter().collect();
+        urls.sort_by(|a, b| a.0.cmp(b.0));
+        let up = urls.iter().filter(|(_, s)| s.last.is_up()).count();
+        let entries: Vec<String> = urls.iter().map(|(url, s)| {
+            let r = &s.last;
+            let status = match &r.status {
+                Ok(PROBE_OK) | Err(_) => "null".to_string(),
+                Ok(code) => code.to_string(),
+            };
+            let error = r.status.as_ref().err().map(|e| json::string(&e.message)).unwrap_or_else(|| "null".into());
+            let uptime = agg.get(*url).map(|st| format!("{:.2}", st.uptime_pct())).unwrap_or_else(|| "null".into());
+            let health = agg.get(*url).map(|st| st.health_score().to_string()).unwrap_or_else(|| "null".into());
+            format!(
+                "{{\"url\":{},\"up\":{},\"status\":{},\"ms\":{},\"error\":{},\"checked_ms\":{},\"since_ms\":{},\"uptime_pct\":{},\"health\":{}}}",
+                json::string(url),
+                r.is_up(),
+                status,
+                r.response_time.as_millis(),
+                error,
+                ms(r.timestamp.as_system_time()),
+                ms(s.since.as_system_time()),
+                uptime,
+                health,
+            )
+        }).collect();
+        format!(
+            "{{\"ts_ms\":{},\"total\":{},\"up\":{},\"down\":{},\"urls\":[{}]}}\n",
+            ms(generated),
+            urls.len(),
+            up,
+            urls.len() - up,
+            entries.join(","),
+        )
+    }
+}
+
+//write next to the target then rename over it, readers never see a half-written file
+pub fn write_atomic(path: &str, contents: &str) -> io::Result<()> {
+    let tmp = format!("{}.tmp", path);
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, Path::new(path)).inspect_err(|_| { let _ = fs::remove_file(&tmp); })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+    use crate::{CheckError, ErrorKind};
+    use std::env;
+    use std::time::Duration;
+
+    #[test]
+    fn test_current_summary() {
+        let at = |r: WebsiteStatus, secs: u64| WebsiteStatus { timestamp: (UNIX_EPOCH + Duration::from_secs(secs)).into(), ..r };
+        let mut cur = Current::new();
+        let mut agg: HashMap<Arc<str>, Stats> = HashMap::new();
+        let rounds = [
+            vec![at(status_for("http://b/", Ok(200), 10), 1), at(status_for("http://a/", Ok(200), 5), 1)],
+            vec![at(status_for("http://b/", Err(CheckError::new(ErrorKind::Transport, "refused")), 3), 2)],
+            vec![at(status_for("http://b/", Ok(503), 4), 3)],
+        ];
+        for round in &rounds {
+            cur.record(round);
+            for r in round { agg.entry(r.url.clone()).or_default().record(r); }
+        }
+        let out = cur.json(&agg, UNIX_EPOCH + Duration::from_secs(4));
+        assert!(out.starts_with("{\"ts_ms\":4000,\"total\":2,\"up\":1,\"down\":1,\"urls\":[{\"url\":\"http://a/\",\"up\":true,\"status\":200,"));
+        //a stays at its only result, b has been down since the round at 2s
+        assert!(out.contains("{\"url\":\"http://b/\",\"up\":false,\"status\":503,\"ms\":4,\"error\":null,\"checked_ms\":3000,\"since_ms\":2000,\"uptime_pct\":33.33,\"health\":52}"));
+
+        let path = env::temp_dir().join(format!("sitewatch-summary-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_atomic(path, "old").unwrap();
+        write_atomic(path, &out).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), out);
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+        fs::remove_file(path).unwrap();
+    }
+}
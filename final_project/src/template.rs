@@ -0,0 +1,119 @@
+//brace expansion for url entries: {eu,us,ap} lists and {01..16} ranges
+
+//refuse templates that would explode into an unusable url list
+const MAX_EXPANSION: usize = 100_000;
+
+//every url a template stands for, in order; plain urls come back as-is
+pub fn expand(template: &str) -> Result<Vec<String>, String> {
+    let out = expand_from(template, 0)?;
+    if out.len() > MAX_EXPANSION {
+        return Err(format!("'{}' expands to more than {} URLs", template, MAX_EXPANSION));
+    }
+    Ok(out)
+}
+
+fn expand_from(s: &str, from: usize) -> Result<Vec<String>, String> {
+    //first expandable group at or after `from`, literal braces are skipped
+    let mut search = from;
+    while let Some(rel) = s[search..].find('{') {
+        let open = search + rel;
+        let Some(close) = matching_brace(s, open) else {
+            return Err(format!("unclosed '{{' in '{}'", s));
+        };
+        let inner = &s[open + 1..close];
+        if let Some(items) = alternatives(inner)? {
+            let (head, tail) = (&s[..open], &s[close + 1..]);
+            let mut out = Vec::new();
+            for item in items {
+                //nested groups and later groups expand on the substituted string
+                out.extend(expand_from(&format!("{}{}{}", head, item, tail), open)?);
+                if out.len() > MAX_EXPANSION { break; }
+            }
+            return Ok(out);
+        }
+        search = open + 1;
+    }
+    Ok(vec![s.to_string()])
+}
+
+fn matching_brace(s: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 { return Some(open + i); }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+//items of a {a,b} list or {lo..hi} range, None when the group is literal
+fn alternatives(inner: &str) -> Result<Option<Vec<String>>, String> {
+    //split on top-level commas only, nested groups stay intact
+    let mut items = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(inner[start..i].to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !items.is_empty() {
+        items.push(inner[start..].to_string());
+        return Ok(Some(items));
+    }
+    let Some((lo, hi)) = inner.split_once("..") else { return Ok(None) };
+    let (Ok(a), Ok(b)) = (lo.parse::<i64>(), hi.parse::<i64>()) else {
+        return Err(format!("range '{{{}}}' needs integer bounds", inner));
+    };
+    if a.abs_diff(b) as usize >= MAX_EXPANSION {
+        return Err(format!("range '{{{}}}' is too large", inner));
+    }
+    //a leading zero on either bound pads every value to that width
+    let padded = |v: &str| v.trim_start_matches('-').len() > 1 && v.trim_start_matches('-').starts_with('0');
+    let width = if padded(lo) || padded(hi) { lo.len().max(hi.len()) } else { 0 };
+    let values: Vec<i64> = if a <= b { (a..=b).collect() } else { (b..=a).rev().collect() };
+    Ok(Some(values.into_iter().map(|v| format!("{:0width$}", v, width = width)).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let shards = expand("https://shard-{01..16}.example.com/health").unwrap();
+        assert_eq!(shards.len(), 16);
+        assert_eq!(shards[0], "https://shard-01.example.com/health");
+        assert_eq!(shards[15], "https://shard-16.example.com/health");
+
+        assert_eq!(
+            expand("https://{eu,us}.example.com/{a,b{1..2}}").unwrap(),
+            vec![
+                "https://eu.example.com/a",
+                "https://eu.example.com/b1",
+                "https://eu.example.com/b2",
+                "https://us.example.com/a",
+                "https://us.example.com/b1",
+                "https://us.example.com/b2",
+            ]
+        );
+        assert_eq!(expand("https://a/{3..1}").unwrap(), vec!["https://a/3", "https://a/2", "https://a/1"]);
+
+        //single-item groups are literal
+        assert_eq!(expand("https://a/{id}").unwrap(), vec!["https://a/{id}"]);
+        assert!(expand("https://a/{x..y}").is_err());
+        assert!(expand("https://a/{1,2").is_err());
+        assert!(expand("https://{1..1000}.{1..1000}/").is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//terminal text: column widths of unicode text and urls cut to fit the table
+use std::borrow::Cow;
+
+//narrowest --max-url-width that still shows a host
+pub const MIN_URL_WIDTH: usize = 10;
+
+//columns a character takes: wide east asian and emoji two, combining marks and joiners none
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F => 0,
+        0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xA000..=0xA4CF => 2,
+        0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 => 2,
+        0x1F300..=0x1F64F | 0x1F900..=0x1F9FF | 0x20000..=0x3FFFD => 2,
+        _ if c.is_control() => 0,
+        _ => 1,
+    }
+}
+
+pub fn width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+//control characters spelled out, a url cannot move the cursor or recolor the terminal
+pub fn printable(s: &str) -> Cow<'_, str> {
+    if !s.chars().any(char::is_control) { return Cow::Borrowed(s); }
+    Cow::Owned(s.chars().map(|c| if c.is_control() { c.escape_default().to_string() } else { c.to_string() }).collect())
+}
+
+//at most max columns; the middle gives way to … so the host and the end of the path stay readable
+pub fn truncate_middle(s: &str, max: usize) -> Cow<'_, str> {
+    if width(s) <= max { return Cow::Borrowed(s); }
+    let budget = max.saturating_sub(1);
+    let (head_max, tail_max) = (budget - budget / 3, budget / 3);
+    let mut head = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let w = char_width(c);
+        if used + w > head_max { break; }
+        used += w;
+        head.push(c);
+    }
+    let mut tail = Vec::new();
+    let mut used = 0;
+    for c in s.chars().rev() {
+        let w = char_width(c);
+        if used + w > tail_max { break; }
+        used += w;
+        tail.push(c);
+    }
+    //a combining mark without the character it belongs to is dropped
+    while tail.last().is_some_and(|&c| char_width(c) == 0) { tail.pop(); }
+    Cow::Owned(format!("{}…{}", head, tail.iter().rev().collect::<String>()))
+}
+
+//a url as the tables print it
+pub fn url_cell(url: &str, max: Option<usize>) -> String {
+    let url = printable(url);
+    match max {
+        Some(max) => truncate_middle(&url, max).into_owned(),
+        None => url.into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_cell() {
+        assert_eq!(width("https://例え.jp/"), 16);
+        assert_eq!(width("e\u{301}"), 1);
+        let long = "https://shop.example.com/search?q=aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa&page=2";
+        let cut = url_cell(long, Some(30));
+        assert_eq!(cut, "https://shop.example…aa&page=2");
+        assert_eq!(width(&cut), 30);
+        assert_eq!(url_cell("https://a.test/", Some(30)), "https://a.test/");
+        //never more columns than asked, whatever the script
+        let cut = url_cell("https://例え.テスト/日本語のページ/説明", Some(20));
+        assert!(width(&cut) <= 20 && cut.contains('…'), "{}", cut);
+        assert_eq!(truncate_middle("abcdefgh\u{301}ij", 6), "abcd…j");
+        assert_eq!(url_cell("https://a.test/\u{1b}[31mred", None), "https://a.test/\\u{1b}[31mred");
+    }
+}
@@ -0,0 +1,128 @@
+//--timing: one extra request on a fresh connection, timed phase by phase (dns, tcp connect, tls, first byte)
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use ureq::rustls;
+use url::Url;
+
+use crate::Network;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Phases {
+    pub dns: Duration,
+    pub connect: Duration,
+    //None for plain http
+    pub tls: Option<Duration>,
+    //request sent to first response byte, the server's share
+    pub ttfb: Duration,
+}
+
+impl Phases {
+    pub fn total(&self) -> Duration {
+        self.dns + self.connect + self.tls.unwrap_or_default() + self.ttfb
+    }
+}
+
+impl fmt::Display for Phases {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "dns {}ms, connect {}ms", self.dns.as_millis(), self.connect.as_millis())?;
+        if let Some(tls) = self.tls { write!(f, ", tls {}ms", tls.as_millis())?; }
+        write!(f, ", ttfb {}ms (total {}ms)", self.ttfb.as_millis(), self.total().as_millis())
+    }
+}
+
+fn first_byte(stream: &mut impl Read) -> Result<(), String> {
+    let mut byte = [0u8; 1];
+    match stream.read(&mut byte) {
+        Ok(0) => Err("connection closed before the response".into()),
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("read failed: {}", e)),
+    }
+}
+
+pub fn measure(net: &Network, url: &str, headers: &[(String, String)], timeout: Duration) -> Result<Phases, String> {
+    let url = Url::parse(url).map_err(|e| format!("invalid url: {}", e))?;
+    let tls = match url.scheme() {
+        "http" => false,
+        "https" => true,
+        other => return Err(format!("unsupported scheme '{}'", other)),
+    };
+    let start = Instant::now();
+    let addr = net.socket_addrs(&url, None).map_err(|e| format!("dns error: {}", e))?
+        .into_iter().next().ok_or("dns returned no addresses")?;
+    let dns = start.elapsed();
+
+    let start = Instant::now();
+    let mut tcp = TcpStream::connect_timeout(&addr, timeout).map_err(|e| format!("tcp connect to {} failed: {}", addr, e))?;
+    let connect = start.elapsed();
+    tcp.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    tcp.set_write_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+
+    let mut target = url.path().to_string();
+    if let Some(q) = url.query() {
+        target.push('?');
+        target.push_str(q);
+    }
+    let host = match url.port() {
+        Some(p) => format!("{}:{}", url.host_str().unwrap_or_default(), p),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let mut head = format!("GET {} HTTP/1.1\r\nHost: {}\r\nAccept: */*\r\nConnection: close\r\n", target, host);
+    if !headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("user-agent")) { head.push_str("User-Agent: sitewatch\r\n"); }
+    for (k, v) in headers {
+        head.push_str(&format!("{}: {}\r\n", k, v));
+    }
+    head.push_str("\r\n");
+
+    if !tls {
+        let start = Instant::now();
+        tcp.write_all(head.as_bytes()).map_err(|e| format!("write failed: {}", e))?;
+        first_byte(&mut tcp)?;
+        return Ok(Phases { dns, connect, tls: None, ttfb: start.elapsed() });
+    }
+
+    let start = Instant::now();
+    let mut conn = rustls::ClientConnection::new(net.tls.clone(), crate::net::server_name(&url)?).map_err(|e| format!("tls error: {}", e))?;
+    while conn.is_handshaking() {
+        conn.complete_io(&mut tcp).map_err(|e| format!("tls handshake failed: {}", e))?;
+    }
+    let tls_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut stream = rustls::StreamOwned::new(conn, tcp);
+    stream.write_all(head.as_bytes()).map_err(|e| format!("write failed: {}", e))?;
+    first_byte(&mut stream)?;
+    Ok(Phases { dns, connect, tls: Some(tls_time), ttfb: start.elapsed() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_phases() {
+        //thinks for 80ms before answering
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let n = s.read(&mut buf).unwrap();
+            assert!(String::from_utf8_lossy(&buf[..n]).contains("\r\nX-Probe: 1\r\n"));
+            thread::sleep(Duration::from_millis(80));
+            let _ = s.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        });
+        let p = measure(&Network::default(), &format!("http://127.0.0.1:{}/x?y=1", port), &[("X-Probe".into(), "1".into())], Duration::from_secs(2)).unwrap();
+        assert!(p.tls.is_none());
+        assert!(p.ttfb >= Duration::from_millis(80) && p.connect < Duration::from_millis(80), "{:?}", p);
+        assert_eq!(p.total(), p.dns + p.connect + p.ttfb);
+
+        let shown = Phases { dns: Duration::from_millis(3), connect: Duration::from_millis(12), tls: Some(Duration::from_millis(25)), ttfb: Duration::from_millis(80) };
+        assert_eq!(shown.to_string(), "dns 3ms, connect 12ms, tls 25ms, ttfb 80ms (total 120ms)");
+        assert!(measure(&Network::default(), "ftp://127.0.0.1/", &[], Duration::from_secs(1)).is_err());
+    }
+}
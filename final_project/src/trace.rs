@@ -0,0 +1,164 @@
+//otlp/json spans: a "check" span per result with a child per phase and per attempt
+use std::hash::{BuildHasher, RandomState};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use url::Url;
+
+use crate::{json, DateTime, Utc, WebsiteStatus};
+
+//otlp span kind client and status codes
+const KIND_CLIENT: u8 = 3;
+const STATUS_OK: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+
+fn random_hex(bytes: usize) -> String {
+    (0..bytes.div_ceil(8))
+        .map(|i| format!("{:016x}", RandomState::new().hash_one((i, SystemTime::now()))))
+        .collect::<String>()[..bytes * 2]
+        .to_string()
+}
+
+fn nanos(t: SystemTime) -> String {
+    //otlp/json carries 64-bit integers as strings
+    t.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos().to_string()
+}
+
+fn attr_str(key: &str, value: &str) -> String {
+    format!("{{\"key\":{},\"value\":{{\"stringValue\":{}}}}}", json::string(key), json::string(value))
+}
+
+fn attr_int(key: &str, value: impl ToString) -> String {
+    format!("{{\"key\":{},\"value\":{{\"intValue\":\"{}\"}}}}", json::string(key), value.to_string())
+}
+
+struct Span<'a> {
+    trace_id: &'a str,
+    id: String,
+    parent: Option<&'a str>,
+    name: String,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<String>,
+    error: Option<&'a str>,
+}
+
+impl Span<'_> {
+    fn json(&self) -> String {
+        let status = match self.error {
+            Some(msg) => format!("{{\"code\":{},\"message\":{}}}", STATUS_ERROR, json::string(msg)),
+            None => format!("{{\"code\":{}}}", STATUS_OK),
+        };
+        format!(
+            "{{\"traceId\":\"{}\",\"spanId\":\"{}\",\"parentSpanId\":\"{}\",\"name\":{},\"kind\":{},\"startTimeUnixNano\":\"{}\",\"endTimeUnixNano\":\"{}\",\"attributes\":[{}],\"status\":{}}}",
+            self.trace_id,
+            self.id,
+            self.parent.unwrap_or_default(),
+            json::string(&self.name),
+            KIND_CLIENT,
+            nanos(self.start),
+            nanos(self.end),
+            self.attributes.join(","),
+            status,
+        )
+    }
+}
+
+fn at(t: DateTime<Utc>) -> SystemTime {
+    t.as_system_time()
+}
+
+//spans of one check, the check span first
+fn check_spans(r: &WebsiteStatus) -> Vec<String> {
+    let trace_id = random_hex(16);
+    let root_id = random_hex(8);
+    let (first, last_end) = match (r.attempts.first(), r.attempts.last()) {
+        (Some(f), Some(l)) => (at(f.start), at(l.start) + l.duration),
+        _ => (at(r.timestamp), at(r.timestamp) + r.response_time),
+    };
+    //the hand-made tcp connect ran right before the first attempt
+    let start = first - r.tcp_connect.unwrap_or(Duration::ZERO);
+
+    let error = r.status.as_ref().err().map(|e| e.message.as_str());
+    let mut attributes = vec![attr_str("url.full", &r.url), attr_int("sitewatch.attempts", r.attempts.len().max(1)), attr_int("sitewatch.retries", r.retries)];
+    if let Ok(code) = r.status { attributes.push(attr_int("http.response.status_code", code)); }
+    if let Err(e) = &r.status { attributes.push(attr_str("sitewatch.error_kind", &format!("{:?}", e.kind).to_lowercase())); }
+    if let Some(slot) = r.slot { attributes.push(attr_int("sitewatch.worker", slot.worker)); }
+    let mut spans = vec![Span { trace_id: &trace_id, id: root_id.clone(), parent: None, name: "check".into(), start, end: last_end, attributes, error }.json()];
+
+    if let Some(tcp) = r.tcp_connect {
+        spans.push(Span { trace_id: &trace_id, id: random_hex(8), parent: Some(&root_id), name: "tcp_connect".into(), start, end: start + tcp, attributes: Vec::new(), error: None }.json());
+    }
+    for (i, a) in r.attempts.iter().enumerate() {
+        spans.push(Span {
+            trace_id: &trace_id,
+            id: random_hex(8),
+            parent: Some(&root_id),
+            name: format!("attempt {}", i + 1),
+            start: at(a.start),
+            end: at(a.start) + a.duration,
+            attributes: vec![attr_int("sitewatch.attempt", i + 1)],
+            error: a.error.as_deref(),
+        }.json());
+    }
+    spans
+}
+
+//one otlp/json export request (resourceSpans) for a round
+pub fn export_json(results: &[WebsiteStatus]) -> String {
+    let spans: Vec<String> = results.iter().flat_map(check_spans).collect();
+    format!(
+        "{{\"resourceSpans\":[{{\"resource\":{{\"attributes\":[{}]}},\"scopeSpans\":[{{\"scope\":{{\"name\":\"sitewatch\"}},\"spans\":[{}]}}]}}]}}",
+        attr_str("service.name", "sitewatch"),
+        spans.join(","),
+    )
+}
+
+//collector base urls get the standard traces path
+pub fn traces_url(endpoint: &str) -> Result<String, String> {
+    let mut url = Url::parse(endpoint).map_err(|e| format!("invalid otlp url: {}", e))?;
+    if url.path() == "/" || url.path().is_empty() { url.set_path("/v1/traces"); }
+    Ok(url.into())
+}
+
+pub fn post(endpoint: &str, body: &str, timeout: Duration) -> Result<(), String> {
+    ureq::post(endpoint)
+        .timeout(timeout)
+        .set("Content-Type", "application/json")
+        .send_string(body)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::status_for;
+    use crate::{Attempt, CheckError, ErrorKind};
+
+    #[test]
+    fn test_check_spans() {
+        let t0: DateTime<Utc> = (UNIX_EPOCH + Duration::from_secs(1000)).into();
+        let t1: DateTime<Utc> = (UNIX_EPOCH + Duration::from_millis(1_000_300)).into();
+        let mut r = status_for("http://a/", Err(CheckError::new(ErrorKind::Transport, "reset")), 100);
+        r.retries = 1;
+        r.tcp_connect = Some(Duration::from_millis(5));
+        r.attempts = vec![
+            Attempt { start: t0, duration: Duration::from_millis(100), error: Some("refused".into()) },
+            Attempt { start: t1, duration: Duration::from_millis(50), error: Some("reset".into()) },
+        ];
+        let spans = check_spans(&r);
+        assert_eq!(spans.len(), 4);
+        assert!(spans[0].contains("\"name\":\"check\""));
+        assert!(spans[0].contains("\"startTimeUnixNano\":\"999995000000\",\"endTimeUnixNano\":\"1000350000000\""));
+        assert!(spans[0].contains("{\"key\":\"sitewatch.attempts\",\"value\":{\"intValue\":\"2\"}}"));
+        assert!(spans[0].contains("\"status\":{\"code\":2,\"message\":\"reset\"}"));
+        assert!(spans[1].contains("\"name\":\"tcp_connect\""));
+        assert!(spans[2].contains("\"name\":\"attempt 1\"") && spans[2].contains("\"message\":\"refused\""));
+        //children share the trace and point at the check span
+        let field = |span: &str, key: &str| span.split(&format!("\"{}\":\"", key)).nth(1).unwrap().split('"').next().unwrap().to_string();
+        assert_eq!(field(&spans[0], "traceId").len(), 32);
+        assert_eq!(field(&spans[3], "traceId"), field(&spans[0], "traceId"));
+        assert_eq!(field(&spans[3], "parentSpanId"), field(&spans[0], "spanId"));
+        assert_eq!(traces_url("http://collector:4318").unwrap(), "http://collector:4318/v1/traces");
+    }
+}
@@ -0,0 +1,203 @@
+//ttl-stepped tcp connect probes, used to triage network vs application failures
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+//one hop on the path
+#[derive(Debug, Clone)]
+pub struct Hop {
+    pub ttl: u8,
+    pub addr: Option<IpAddr>,
+    pub rtt: Option<Duration>,
+    pub reached: bool,
+}
+
+//probe with increasing ttl until the target answers or max_hops is hit
+pub fn trace(target: SocketAddr, max_hops: u8, hop_timeout: Duration) -> Result<Vec<Hop>, String> {
+    let mut hops = Vec::new();
+    for ttl in 1..=max_hops.max(1) {
+        let hop = probe(target, ttl, hop_timeout)?;
+        let reached = hop.reached;
+        hops.push(hop);
+        if reached { break; }
+    }
+    Ok(hops)
+}
+
+//report lines for the incident record
+pub fn format_path(target: SocketAddr, hops: &[Hop]) -> Vec<String> {
+    let mut lines = vec![format!("path to {}:", target)];
+    for h in hops {
+        let addr = h.addr.map(|a| a.to_string()).unwrap_or_else(|| "*".into());
+        let rtt = h.rtt.map(|d| format!("{}ms", d.as_millis())).unwrap_or_else(|| "-".into());
+        let mark = if h.reached { " (target)" } else { "" };
+        lines.push(format!("  {:>2}  {:<40} {}{}", h.ttl, addr, rtt, mark));
+    }
+    if !hops.iter().any(|h| h.reached) {
+        lines.push("  target not reached".into());
+    }
+    lines
+}
+
+//single connect with a capped ttl; the icmp time-exceeded source lands on the error queue
+#[cfg(target_os = "linux")]
+fn probe(target: SocketAddr, ttl: u8, timeout: Duration) -> Result<Hop, String> {
+    use std::io;
+    use std::mem;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::time::Instant;
+
+    let (family, level, ttl_opt, err_opt) = match target {
+        SocketAddr::V4(_) => (libc::AF_INET, libc::IPPROTO_IP, libc::IP_TTL, libc::IP_RECVERR),
+        SocketAddr::V6(_) => (libc::AF_INET6, libc::IPPROTO_IPV6, libc::IPV6_UNICAST_HOPS, libc::IPV6_RECVERR),
+    };
+
+    let raw = unsafe { libc::socket(family, libc::SOCK_STREAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC, 0) };
+    if raw < 0 {
+        return Err(format!("socket: {}", io::Error::last_os_error()));
+    }
+    //closes on drop
+    let sock = unsafe { OwnedFd::from_raw_fd(raw) };
+    let fd = sock.as_raw_fd();
+
+    let set = |opt: libc::c_int, val: libc::c_int| -> Result<(), String> {
+        let rc = unsafe {
+            libc::setsockopt(fd, level, opt, &val as *const _ as *const libc::c_void, mem::size_of::<libc::c_int>() as libc::socklen_t)
+        };
+        if rc < 0 { Err(format!("setsockopt: {}", io::Error::last_os_error())) } else { Ok(()) }
+    };
+    set(ttl_opt, ttl as libc::c_int)?;
+    set(err_opt, 1)?;
+
+    //sockaddr for connect
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let len = match target {
+        SocketAddr::V4(a) => {
+            let sin = libc::sockaddr_in {
+                sin_family: libc::AF_INET as libc::sa_family_t,
+                sin_port: a.port().to_be(),
+                sin_addr: libc::in_addr { s_addr: u32::from_ne_bytes(a.ip().octets()) },
+                sin_zero: [0; 8],
+            };
+            unsafe { std::ptr::write(&mut storage as *mut _ as *mut libc::sockaddr_in, sin) };
+            mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(a) => {
+            let sin6 = libc::sockaddr_in6 {
+                sin6_family: libc::AF_INET6 as libc::sa_family_t,
+                sin6_port: a.port().to_be(),
+                sin6_flowinfo: a.flowinfo(),
+                sin6_addr: libc::in6_addr { s6_addr: a.ip().octets() },
+                sin6_scope_id: a.scope_id(),
+            };
+            unsafe { std::ptr::write(&mut storage as *mut _ as *mut libc::sockaddr_in6, sin6) };
+            mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+
+    let start = Instant::now();
+    let rc = unsafe { libc::connect(fd, &storage as *const _ as *const libc::sockaddr, len as libc::socklen_t) };
+    if rc < 0 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::EINPROGRESS) {
+            return Ok(hop_from_error(fd, target, ttl, Some(start.elapsed())));
+        }
+    }
+
+    //wait for syn-ack, rst or icmp
+    let mut pfd = libc::pollfd { fd, events: libc::POLLOUT, revents: 0 };
+    let ms = timeout.as_millis().min(i32::MAX as u128) as libc::c_int;
+    let n = unsafe { libc::poll(&mut pfd, 1, ms) };
+    let rtt = start.elapsed();
+    if n <= 0 {
+        return Ok(Hop { ttl, addr: None, rtt: None, reached: false });
+    }
+    Ok(hop_from_error(fd, target, ttl, Some(rtt)))
+}
+
+//classify a finished connect: error queue offender, refused/accepted target, or unknown
+#[cfg(target_os = "linux")]
+fn hop_from_error(fd: libc::c_int, target: SocketAddr, ttl: u8, rtt: Option<Duration>) -> Hop {
+    use std::mem;
+
+    if let Some(addr) = read_offender(fd) {
+        let reached = addr == target.ip();
+        return Hop { ttl, addr: Some(addr), rtt, reached };
+    }
+    let mut so_err: libc::c_int = 0;
+    let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+    let rc = unsafe {
+        libc::getsockopt(fd, libc::SOL_SOCKET, libc::SO_ERROR, &mut so_err as *mut _ as *mut libc::c_void, &mut len)
+    };
+    if rc == 0 && (so_err == 0 || so_err == libc::ECONNREFUSED) {
+        return Hop { ttl, addr: Some(target.ip()), rtt, reached: true };
+    }
+    Hop { ttl, addr: None, rtt: None, reached: false }
+}
+
+//address of the router that reported the error
+#[cfg(target_os = "linux")]
+fn read_offender(fd: libc::c_int) -> Option<IpAddr> {
+    use std::mem;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    let mut data = [0u8; 64];
+    let mut control = [0u8; 512];
+    let mut iov = libc::iovec { iov_base: data.as_mut_ptr() as *mut libc::c_void, iov_len: data.len() };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = control.len() as _;
+
+    let n = unsafe { libc::recvmsg(fd, &mut msg, libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT) };
+    if n < 0 { return None; }
+
+    let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+    while !cmsg.is_null() {
+        let hdr = unsafe { &*cmsg };
+        let is_err = (hdr.cmsg_level == libc::IPPROTO_IP && hdr.cmsg_type == libc::IP_RECVERR)
+            || (hdr.cmsg_level == libc::IPPROTO_IPV6 && hdr.cmsg_type == libc::IPV6_RECVERR);
+        if is_err {
+            let ee = unsafe { libc::CMSG_DATA(cmsg) } as *const libc::sock_extended_err;
+            let origin = unsafe { std::ptr::read_unaligned(ee) }.ee_origin;
+            if origin != libc::SO_EE_ORIGIN_ICMP && origin != libc::SO_EE_ORIGIN_ICMP6 { return None; }
+            let sa = unsafe { libc::SO_EE_OFFENDER(ee) };
+            let family = unsafe { std::ptr::read_unaligned(sa) }.sa_family as libc::c_int;
+            return match family {
+                libc::AF_INET => {
+                    let sin = unsafe { std::ptr::read_unaligned(sa as *const libc::sockaddr_in) };
+                    Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr))))
+                }
+                libc::AF_INET6 => {
+                    let sin6 = unsafe { std::ptr::read_unaligned(sa as *const libc::sockaddr_in6) };
+                    Some(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+                }
+                _ => None,
+            };
+        }
+        cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+    }
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+fn probe(_target: SocketAddr, _ttl: u8, _timeout: Duration) -> Result<Hop, String> {
+    Err("traceroute is only supported on linux".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_trace_loopback_reaches_target() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = listener.local_addr().unwrap();
+        let hops = trace(target, 4, Duration::from_millis(500)).unwrap();
+        assert_eq!(hops.len(), 1);
+        assert!(hops[0].reached);
+        assert!(format_path(target, &hops).iter().any(|l| l.contains("(target)")));
+    }
+}
@@ -0,0 +1,265 @@
+//txn:// checks: an ordered list of requests sharing one cookie jar (login, then the dashboard), up only if every step passes
+use std::io::Read;
+use std::sync::Arc;
+
+use url::Url;
+
+use crate::{load_body, Agents, parse_method, with_headers, CheckError, Config, ErrorKind, ExpectStatus};
+
+//redirects followed per step, as many as ureq follows for plain checks
+const MAX_REDIRECTS: usize = 5;
+//body read per step for contains=
+const STEP_BODY_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub method: String,
+    pub url: String,
+    pub body: Option<Arc<[u8]>>,
+    pub content_type: Option<String>,
+    //None takes any 2xx/3xx
+    pub expect: Option<ExpectStatus>,
+    //text the final response body must contain
+    pub contains: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub steps: Vec<Step>,
+}
+
+impl Transaction {
+    //one step per line: "METHOD URL key=value ..." with expect, contains, body (TEXT or @FILE) and content_type; # comments
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut steps = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let at = |e: String| format!("line {}: {}", n + 1, e);
+            let mut words = line.split_whitespace();
+            let method = parse_method(words.next().unwrap_or_default()).map_err(at)?;
+            let url = words.next().ok_or_else(|| at("missing url".into()))?;
+            let parsed = Url::parse(url).map_err(|e| at(format!("invalid url '{}': {}", url, e)))?;
+            if !matches!(parsed.scheme(), "http" | "https") { return Err(at(format!("steps need http(s) urls, got '{}'", url))); }
+            let mut step = Step { method, url: url.to_string(), body: None, content_type: None, expect: None, contains: None };
+            for word in words {
+                let (key, value) = word.split_once('=').ok_or_else(|| at(format!("expected key=value, got '{}'", word)))?;
+                match key {
+                    "expect" => step.expect = Some(ExpectStatus::parse(value).map_err(at)?),
+                    "contains" => step.contains = Some(value.to_string()),
+                    "body" => step.body = Some(load_body(value).map_err(at)?),
+                    "content_type" => step.content_type = Some(value.to_string()),
+                    _ => return Err(at(format!("unknown step setting '{}'", key))),
+                }
+            }
+            if step.body.is_some() && matches!(step.method.as_str(), "GET" | "HEAD") {
+                return Err(at(format!("a request body needs a method like POST or PUT, not {}", step.method)));
+            }
+            steps.push(step);
+        }
+        if steps.is_empty() { return Err("transaction has no steps".into()); }
+        Ok(Self { steps })
+    }
+
+    //final status of the last step, or the first step that failed; a fresh jar every run
+    pub fn run(&self, cfg: &Config) -> Result<u16, CheckError> {
+        let agents = Agents::new(cfg);
+        let mut jar = CookieJar::default();
+        let mut last = 0;
+        for (i, step) in self.steps.iter().enumerate() {
+            let fail = |kind, why: String| CheckError::new(kind, format!("step {}/{} {} {}: {}", i + 1, self.steps.len(), step.method, step.url, why));
+            let (code, body) = send(&agents, step, &mut jar, cfg).map_err(|e| fail(ErrorKind::Transport, e))?;
+            let ok = match &step.expect {
+                Some(expect) => expect.contains(code),
+                None => (200..400).contains(&code),
+            };
+            if !ok {
+                let want = step.expect.as_ref().map(|e| e.to_string()).unwrap_or_else(|| "2xx/3xx".into());
+                return Err(fail(ErrorKind::Transaction, format!("status {}, expected {}", code, want)));
+            }
+            if let Some(text) = step.contains.as_ref().filter(|t| !body.contains(t.as_str())) {
+                return Err(fail(ErrorKind::Transaction, format!("body does not contain '{}'", text)));
+            }
+            last = code;
+        }
+        Ok(last)
+    }
+}
+
+//one step with its redirects walked by hand, so cookies set on the way are kept
+fn send(agents: &Agents, step: &Step, jar: &mut CookieJar, cfg: &Config) -> Result<(u16, String), String> {
+    let mut url = Url::parse(&step.url).map_err(|e| e.to_string())?;
+    let mut method = step.method.clone();
+    let mut body = step.body.clone();
+    for _ in 0..=MAX_REDIRECTS {
+        let mut req = with_headers(agents.get(url.as_str()).request(&method, url.as_str()), &cfg.request_headers);
+        if let Some(cookies) = jar.header(&url) { req = req.set("Cookie", &cookies); }
+        if let (Some(t), Some(_)) = (&step.content_type, &body) { req = req.set("Content-Type", t); }
+        let resp = match &body {
+            Some(b) => req.send_bytes(b),
+            None => req.call(),
+        };
+        let resp = match resp {
+            Ok(resp) | Err(ureq::Error::Status(_, resp)) => resp,
+            Err(e) => return Err(format!("transport error: {}", e)),
+        };
+        for set in resp.all("set-cookie") { jar.store(&url, set); }
+        let code = resp.status();
+        match resp.header("location").filter(|_| matches!(code, 301 | 302 | 303 | 307 | 308)) {
+            //the step asked for the redirect itself
+            Some(_) if step.expect.as_ref().is_some_and(|e| e.contains(code)) => return Ok((code, String::new())),
+            Some(loc) => {
+                url = url.join(loc).map_err(|e| format!("bad redirect location '{}': {}", loc, e))?;
+                //browsers turn a redirected form post into a get
+                if code == 303 || (matches!(code, 301 | 302) && method == "POST") {
+                    method = "GET".into();
+                    body = None;
+                }
+            }
+            None => {
+                let mut text = Vec::new();
+                let _ = resp.into_reader().take(STEP_BODY_BYTES).read_to_end(&mut text);
+                return Ok((code, String::from_utf8_lossy(&text).into_owned()));
+            }
+        }
+    }
+    Err(format!("more than {} redirects", MAX_REDIRECTS))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Cookie {
+    name: String,
+    value: String,
+    domain: String,
+    //no Domain attribute: only the exact host gets it back
+    host_only: bool,
+    path: String,
+    secure: bool,
+}
+
+//just enough of rfc 6265 for session cookies: domain, path, secure and max-age
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    pub fn store(&mut self, url: &Url, set_cookie: &str) {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let mut parts = set_cookie.split(';');
+        let Some((name, value)) = parts.next().and_then(|nv| nv.split_once('=')) else { return };
+        let name = name.trim();
+        if name.is_empty() { return; }
+        //default path is the directory of the request path
+        let default_path = match url.path().rfind('/') {
+            Some(0) | None => "/".to_string(),
+            Some(i) => url.path()[..i].to_string(),
+        };
+        let mut cookie = Cookie { name: name.to_string(), value: value.trim().to_string(), domain: host.clone(), host_only: true, path: default_path, secure: false };
+        let mut expired = false;
+        for attr in parts {
+            let (k, v) = attr.split_once('=').map(|(k, v)| (k.trim(), v.trim())).unwrap_or((attr.trim(), ""));
+            match k.to_ascii_lowercase().as_str() {
+                "domain" if !v.is_empty() => {
+                    let domain = v.trim_start_matches('.').to_ascii_lowercase();
+                    //a server can only set cookies for itself or a parent domain
+                    if host != domain && !host.ends_with(&format!(".{}", domain)) { return; }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if v.starts_with('/') => cookie.path = v.to_string(),
+                "secure" => cookie.secure = true,
+                "max-age" => expired = v.parse::<i64>().is_ok_and(|s| s <= 0),
+                _ => {}
+            }
+        }
+        self.cookies.retain(|c| !(c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path));
+        if !expired { self.cookies.push(cookie); }
+    }
+
+    //Cookie header value for a request to url, longer paths first
+    pub fn header(&self, url: &Url) -> Option<String> {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let path = url.path();
+        let mut matching: Vec<&Cookie> = self.cookies.iter().filter(|c| {
+            let domain_ok = if c.host_only { host == c.domain } else { host == c.domain || host.ends_with(&format!(".{}", c.domain)) };
+            let path_ok = path == c.path || (path.starts_with(&c.path) && (c.path.ends_with('/') || path[c.path.len()..].starts_with('/')));
+            domain_ok && path_ok && (!c.secure || url.scheme() == "https")
+        }).collect();
+        if matching.is_empty() { return None; }
+        matching.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+        Some(matching.iter().map(|c| format!("{}={}", c.name, c.value)).collect::<Vec<_>>().join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_login_then_dashboard() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut reader = BufReader::new(stream.unwrap());
+                let (mut head, mut len) = (String::new(), 0);
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" { break; }
+                    if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") { len = v.trim().parse().unwrap(); }
+                    head.push_str(&line);
+                }
+                let mut body = vec![0u8; len];
+                let _ = reader.read_exact(&mut body);
+                let reply = if head.starts_with("POST /login ") && body == b"user=a&pass=b" {
+                    "HTTP/1.1 302 Found\r\nLocation: /dash\r\nSet-Cookie: sid=abc; Path=/; HttpOnly\r\nSet-Cookie: pref=x; Path=/other\r\nContent-Length: 0\r\n\r\n".to_string()
+                } else if head.starts_with("GET /dash ") && head.contains("\r\nCookie: sid=abc\r\n") {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\nWelcome".to_string()
+                } else {
+                    "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n".to_string()
+                };
+                let _ = reader.get_mut().write_all(reply.as_bytes());
+            }
+        });
+        let base = format!("http://127.0.0.1:{}", port);
+        let cfg = Config::default();
+        let txn = Transaction::parse(&format!("# log in, then look\nPOST {}/login body=user=a&pass=b content_type=application/x-www-form-urlencoded\nGET {}/dash contains=Welcome\n", base, base)).unwrap();
+        assert_eq!(txn.run(&cfg), Ok(200));
+        //the redirect itself, cookie not needed
+        assert_eq!(Transaction::parse(&format!("POST {}/login body=user=a&pass=b expect=302", base)).unwrap().run(&cfg), Ok(302));
+        //no login, no session
+        let err = Transaction::parse(&format!("GET {}/dash", base)).unwrap().run(&cfg).unwrap_err();
+        assert_eq!((err.kind, err.message.as_str()), (ErrorKind::Transaction, &*format!("step 1/1 GET {}/dash: status 401, expected 2xx/3xx", base)));
+        let err = Transaction::parse(&format!("POST {}/login body=user=a&pass=b\nGET {}/dash contains=Goodbye", base, base)).unwrap().run(&cfg).unwrap_err();
+        assert!(err.message.starts_with("step 2/2 GET") && err.message.ends_with("body does not contain 'Goodbye'"), "{}", err.message);
+
+        assert!(Transaction::parse("").unwrap_err().contains("no steps"));
+        assert!(Transaction::parse("GET ftp://a/").unwrap_err().starts_with("line 1:"));
+        assert!(Transaction::parse("GET https://a/ body=x").unwrap_err().contains("not GET"));
+    }
+
+    #[test]
+    fn test_cookie_jar() {
+        let at = |u: &str| Url::parse(u).unwrap();
+        let mut jar = CookieJar::default();
+        jar.store(&at("https://app.example.com/account/login"), "a=1");
+        jar.store(&at("https://app.example.com/"), "b=2; Domain=.example.com; Path=/");
+        jar.store(&at("https://app.example.com/"), "s=3; Secure; Path=/api");
+        jar.store(&at("https://app.example.com/"), "evil=1; Domain=other.com");
+        assert_eq!(jar.header(&at("https://app.example.com/account/x")).as_deref(), Some("a=1; b=2"));
+        assert_eq!(jar.header(&at("https://www.example.com/account")).as_deref(), Some("b=2"));
+        assert_eq!(jar.header(&at("https://app.example.com/api/v1")).as_deref(), Some("s=3; b=2"));
+        assert_eq!(jar.header(&at("http://app.example.com/api/v1")).as_deref(), Some("b=2"));
+        assert_eq!(jar.header(&at("https://app.example.com/apix")).as_deref(), Some("b=2"));
+        assert_eq!(jar.header(&at("https://other.com/")), None);
+        //replaced, then deleted
+        jar.store(&at("https://app.example.com/"), "b=9; Domain=example.com; Path=/");
+        assert_eq!(jar.header(&at("https://www.example.com/")).as_deref(), Some("b=9"));
+        jar.store(&at("https://app.example.com/"), "b=; Domain=example.com; Path=/; Max-Age=0");
+        assert_eq!(jar.header(&at("https://www.example.com/")), None);
+    }
+}
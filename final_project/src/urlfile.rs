@@ -0,0 +1,118 @@
+//--file format: "URL [key=value ...] [# note]" per entry; an indented line of settings continues the entry above,
+//double quotes keep spaces in a value (header:Server="Apache 2")
+use crate::UrlOptions;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    //as written, templates like {1..3} not expanded yet
+    pub url: String,
+    //None when the entry has no settings
+    pub options: Option<UrlOptions>,
+    pub note: Option<String>,
+    //of the url line, 1-based
+    pub line: usize,
+}
+
+pub fn parse(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries: Vec<Entry> = Vec::new();
+    //settings words per entry, parsed once the entry is complete
+    let mut settings: Vec<Vec<String>> = Vec::new();
+    for (n, raw) in text.lines().enumerate() {
+        let at = |e: String| format!("line {}: {}", n + 1, e);
+        if raw.trim_start().starts_with('#') { continue; }
+        let (words, note) = split_words(raw).map_err(at)?;
+        if words.is_empty() { continue; }
+        //each line on its own first, so an error names the line it is on
+        let continued = raw.starts_with(char::is_whitespace) && is_setting(&words[0]);
+        let own = if continued { &words[..] } else { &words[1..] };
+        UrlOptions::parse(own.iter().map(String::as_str)).map_err(at)?;
+        match entries.last_mut() {
+            Some(entry) if continued => {
+                if entry.note.is_none() { entry.note = note.map(String::from); }
+                settings.last_mut().into_iter().for_each(|s| s.extend_from_slice(own));
+            }
+            None if continued => return Err(at("settings without a url above them".into())),
+            _ => {
+                entries.push(Entry { url: words[0].clone(), options: None, note: note.map(String::from), line: n + 1 });
+                settings.push(own.to_vec());
+            }
+        }
+    }
+    for (entry, words) in entries.iter_mut().zip(settings) {
+        if words.is_empty() { continue; }
+        let opts = UrlOptions::parse(words.iter().map(String::as_str)).map_err(|e| format!("line {}: {}", entry.line, e))?;
+        entry.options = Some(opts);
+    }
+    Ok(entries)
+}
+
+//a url line indented by accident still reads as a url
+fn is_setting(word: &str) -> bool {
+    word.contains('=') && !word.contains("://")
+}
+
+//whitespace separated, "..." kept together with the quotes dropped; "URL settings  # note": an unquoted #
+//starting a word begins the note, one inside the url is a fragment
+fn split_words(line: &str) -> Result<(Vec<String>, Option<&str>), String> {
+    let line = line.trim();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    let mut note = None;
+    for (i, c) in line.char_indices() {
+        match c {
+            '#' if !quoted && !started && i > 0 => {
+                note = Some(line[i + 1..].trim()).filter(|n| !n.is_empty());
+                break;
+            }
+            '"' => { quoted = !quoted; started = true; }
+            c if c.is_whitespace() && !quoted => {
+                if started { words.push(std::mem::take(&mut word)); }
+                started = false;
+            }
+            c => { word.push(c); started = true; }
+        }
+    }
+    if quoted { return Err("unterminated quote".into()); }
+    if started { words.push(word); }
+    Ok((words, note))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_split_words() {
+        let words = |line: &'static str| split_words(line).map(|(w, note)| (w.join("|"), note));
+        assert_eq!(words("  https://a.test/  # behind Cloudflare, 403s expected "), Ok(("https://a.test/".into(), Some("behind Cloudflare, 403s expected"))));
+        assert_eq!(words("https://a.test/#frag"), Ok(("https://a.test/#frag".into(), None)));
+        assert_eq!(words("https://a.test/ #"), Ok(("https://a.test/".into(), None)));
+        //a # inside quotes is part of the value
+        assert_eq!(words("https://a.test/ header:X-Tag=\"a #1\" # tagged"), Ok(("https://a.test/|header:X-Tag=a #1".into(), Some("tagged"))));
+        assert_eq!(words("https://a.test/ header:X=\"open # note"), Err("unterminated quote".into()));
+    }
+
+    #[test]
+    fn test_url_file() {
+        let text = "# fleet\nhttps://a.example.com timeout=2000 expect=200 header:Server=nginx tag=prod  # edge\n\
+            https://b.example.com\n    header:X-Served-By=\"cache 1\" tag=eu\n    retries=2\n  https://c.example.com/?q=1\n";
+        let entries = parse(text).unwrap();
+        assert_eq!(entries.iter().map(|e| (e.url.as_str(), e.line)).collect::<Vec<_>>(), [("https://a.example.com", 2), ("https://b.example.com", 3), ("https://c.example.com/?q=1", 6)]);
+        let a = entries[0].options.as_ref().unwrap();
+        assert_eq!((a.timeout, a.header_checks.clone(), a.tags.clone()), (Some(Duration::from_secs(2)), vec![("Server".into(), "nginx".into())], vec!["prod".to_string()]));
+        assert_eq!(entries[0].note.as_deref(), Some("edge"));
+        let b = entries[1].options.as_ref().unwrap();
+        assert_eq!((b.retries, b.header_checks.clone(), b.tags.clone()), (Some(2), vec![("X-Served-By".into(), "cache 1".into())], vec!["eu".to_string()]));
+        assert_eq!(entries[2].options, None);
+        let tagged = parse("https://a/ header:X-Tag=\"a #1\"  # quoted").unwrap().remove(0);
+        assert_eq!((tagged.options.unwrap().header_checks, tagged.note.as_deref()), (vec![("X-Tag".into(), "a #1".into())], Some("quoted")));
+
+        assert_eq!(parse("https://a/\n  retries=x\n").unwrap_err(), "line 2: invalid retries 'x'");
+        assert_eq!(parse("  retries=1\n").unwrap_err(), "line 1: settings without a url above them");
+        assert_eq!(parse("https://a/ header:X=\"open\n").unwrap_err(), "line 1: unterminated quote");
+        assert!(parse("https://a/ header:=x").unwrap_err().contains("header name is empty"));
+    }
+}
@@ -0,0 +1,215 @@
+//ws:// and wss:// checks: upgrade handshake with key/accept validation, optional ping/pong
+use std::hash::{BuildHasher, RandomState};
+use std::io::{BufReader, Read, Write};
+use std::time::{Duration, SystemTime};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use url::Url;
+
+use crate::{rawhttp, CheckError, Network, PROBE_OK};
+
+//rfc 6455 handshake suffix
+const WS_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const PING_PAYLOAD: &[u8] = b"sitewatch";
+//frames skipped while waiting for the pong
+const MAX_FRAMES: usize = 16;
+const MAX_FRAME_LEN: u64 = 64 * 1024;
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut out = [0u8; N];
+    for (i, chunk) in out.chunks_mut(8).enumerate() {
+        let v = RandomState::new().hash_one((SystemTime::now(), i));
+        chunk.copy_from_slice(&v.to_le_bytes()[..chunk.len()]);
+    }
+    out
+}
+
+//Sec-WebSocket-Accept expected for a key
+pub fn accept_for(key: &str) -> String {
+    BASE64.encode(sha1(format!("{}{}", key, WS_GUID).as_bytes()))
+}
+
+pub fn check(net: &Network, url: &str, ping: bool, timeout: Duration) -> Result<u16, CheckError> {
+    let parsed = Url::parse(url).map_err(|e| CheckError::transport(format!("invalid url: {}", e)))?;
+    let tls = match parsed.scheme() {
+        "ws" => false,
+        "wss" => true,
+        other => return Err(CheckError::transport(format!("unsupported scheme '{}'", other))),
+    };
+    let stream = net.connect(&parsed, None, timeout, tls).map_err(CheckError::transport)?;
+    let mut reader = BufReader::new(stream);
+
+    let key = BASE64.encode(random_bytes::<16>());
+    let mut target = parsed.path().to_string();
+    if let Some(q) = parsed.query() {
+        target.push('?');
+        target.push_str(q);
+    }
+    let host = match parsed.port() {
+        Some(p) => format!("{}:{}", parsed.host_str().unwrap_or_default(), p),
+        None => parsed.host_str().unwrap_or_default().to_string(),
+    };
+    let head = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: sitewatch\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        target, host, key,
+    );
+    reader.get_mut().write_all(head.as_bytes()).map_err(CheckError::transport)?;
+
+    let resp = rawhttp::read_head(&mut reader).map_err(CheckError::transport)?;
+    if resp.status != 101 {
+        return Err(CheckError::protocol(format!("upgrade refused with HTTP {}", resp.status)));
+    }
+    if !resp.header("Upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket")) {
+        return Err(CheckError::protocol("101 without Upgrade: websocket"));
+    }
+    let expected = accept_for(&key);
+    match resp.header("Sec-WebSocket-Accept") {
+        Some(v) if v == expected => {}
+        Some(v) => return Err(CheckError::protocol(format!("Sec-WebSocket-Accept '{}' does not match the key", v))),
+        None => return Err(CheckError::protocol("101 without Sec-WebSocket-Accept")),
+    }
+
+    if ping {
+        write_frame(reader.get_mut(), 0x9, PING_PAYLOAD).map_err(CheckError::transport)?;
+        let mut got_pong = false;
+        for _ in 0..MAX_FRAMES {
+            let (opcode, payload) = read_frame(&mut reader)?;
+            match opcode {
+                0xA if payload == PING_PAYLOAD => {
+                    got_pong = true;
+                    break;
+                }
+                0x8 => return Err(CheckError::protocol("server closed the connection instead of answering the ping")),
+                _ => {}
+            }
+        }
+        if !got_pong {
+            return Err(CheckError::protocol(format!("no pong within {} frames", MAX_FRAMES)));
+        }
+    }
+    //polite close, the answer is not awaited
+    let _ = write_frame(reader.get_mut(), 0x8, &1000u16.to_be_bytes());
+    Ok(PROBE_OK)
+}
+
+//single masked client frame, payloads here are always short
+fn write_frame(w: &mut impl Write, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+    let mask = random_bytes::<4>();
+    let mut frame = vec![0x80 | opcode, 0x80 | payload.len() as u8];
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    w.write_all(&frame)?;
+    w.flush()
+}
+
+fn read_frame(r: &mut impl Read) -> Result<(u8, Vec<u8>), CheckError> {
+    let mut hdr = [0u8; 2];
+    r.read_exact(&mut hdr).map_err(CheckError::transport)?;
+    let opcode = hdr[0] & 0x0f;
+    let masked = hdr[1] & 0x80 != 0;
+    let len = match hdr[1] & 0x7f {
+        126 => {
+            let mut b = [0u8; 2];
+            r.read_exact(&mut b).map_err(CheckError::transport)?;
+            u16::from_be_bytes(b) as u64
+        }
+        127 => {
+            let mut b = [0u8; 8];
+            r.read_exact(&mut b).map_err(CheckError::transport)?;
+            u64::from_be_bytes(b)
+        }
+        n => n as u64,
+    };
+    if len > MAX_FRAME_LEN {
+        return Err(CheckError::protocol(format!("frame of {} bytes is too large", len)));
+    }
+    let mut mask = [0u8; 4];
+    if masked { r.read_exact(&mut mask).map_err(CheckError::transport)?; }
+    let mut payload = vec![0u8; len as usize];
+    r.read_exact(&mut payload).map_err(CheckError::transport)?;
+    if masked {
+        for (i, b) in payload.iter_mut().enumerate() { *b ^= mask[i % 4]; }
+    }
+    Ok((opcode, payload))
+}
+
+//sha-1, only for the handshake accept value
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 { msg.push(0); }
+    msg.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let t = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (x, v) in h.iter_mut().zip([a, b, c, d, e]) { *x = x.wrapping_add(v); }
+    }
+    let mut out = [0u8; 20];
+    for (i, v) in h.iter().enumerate() { out[i * 4..i * 4 + 4].copy_from_slice(&v.to_be_bytes()); }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_accept_value() {
+        //example from rfc 6455 section 1.3
+        assert_eq!(accept_for("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn test_handshake_and_ping() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (s, _) = listener.accept().unwrap();
+            let mut out = s.try_clone().unwrap();
+            let mut reader = BufReader::new(s);
+            let mut key = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(k) = line.strip_prefix("Sec-WebSocket-Key: ") { key = k.trim().to_string(); }
+                if line == "\r\n" { break; }
+            }
+            let resp = format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", accept_for(&key));
+            out.write_all(resp.as_bytes()).unwrap();
+            //unsolicited text frame first, then the pong
+            out.write_all(&[0x81, 2, b'h', b'i']).unwrap();
+            let (opcode, payload) = read_frame(&mut reader).unwrap();
+            assert_eq!(opcode, 0x9);
+            let mut pong = vec![0x8A, payload.len() as u8];
+            pong.extend_from_slice(&payload);
+            out.write_all(&pong).unwrap();
+        });
+        assert_eq!(check(&Network::default(), &format!("ws://127.0.0.1:{}/socket", port), true, Duration::from_secs(2)), Ok(PROBE_OK));
+    }
+}